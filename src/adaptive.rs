@@ -0,0 +1,215 @@
+/// Running estimate of the luminance of a pixel, sampled in batches each as
+/// large as the samples before it, so that its error can be estimated from
+/// the difference between the two halves of its samples. A batch may be
+/// taken in several parts, when passes limit the samples.
+///
+/// Unlike the variance of the samples, the difference measures the error
+/// of the mean itself, which low-discrepancy samplers make smaller: each
+/// half of their samples is stratified on its own.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Estimate {
+    count: u32,
+    sum: f64,
+    /// Number and sum of the samples taken before the current batch
+    previous: (u32, f64),
+}
+
+impl Estimate {
+    /// Starts a new batch of samples, unless the current one is unfinished
+    pub fn start_batch(&mut self) {
+        if self.remaining() == 0 {
+            self.previous = (self.count, self.sum);
+        }
+    }
+
+    /// Returns the number of samples missing for the current batch to
+    /// double the samples
+    pub fn remaining(&self) -> u32 {
+        (2 * self.previous.0).saturating_sub(self.count)
+    }
+
+    /// Adds the luminance of a sample to the current batch
+    pub fn add(&mut self, value: f64) {
+        self.count += 1;
+        self.sum += value;
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Encodes the estimate in little-endian bytes, for checkpoints
+    pub fn to_bytes(self) -> [u8; 24] {
+        let mut bytes = [0; 24];
+        bytes[0..4].copy_from_slice(&self.count.to_le_bytes());
+        bytes[4..12].copy_from_slice(&self.sum.to_le_bytes());
+        bytes[12..16].copy_from_slice(&self.previous.0.to_le_bytes());
+        bytes[16..24].copy_from_slice(&self.previous.1.to_le_bytes());
+
+        bytes
+    }
+
+    /// Decodes an estimate encoded by `to_bytes`
+    pub fn from_bytes(bytes: [u8; 24]) -> Estimate {
+        let u32_at = |i: usize| u32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+        let f64_at = |i: usize| f64::from_le_bytes(bytes[i..i + 8].try_into().unwrap());
+
+        Estimate {
+            count: u32_at(0),
+            sum: f64_at(4),
+            previous: (u32_at(12), f64_at(16)),
+        }
+    }
+
+    /// Returns the error of the mean relative to the mean, infinite unless
+    /// the last batch doubled the samples and is finished. Dark pixels are
+    /// compared to a luminance of `DARK`, so that their noise, hardly
+    /// visible, does not keep them sampled.
+    pub fn relative_error(&self) -> f64 {
+        const DARK: f64 = 0.05;
+
+        let (previous_count, previous_sum) = self.previous;
+        if previous_count == 0 || 2 * previous_count != self.count {
+            return f64::INFINITY;
+        }
+
+        let first = previous_sum / previous_count as f64;
+        let second = (self.sum - previous_sum) / previous_count as f64;
+        let mean = self.sum / self.count as f64;
+        (first - second).abs() / 2.0 / mean.max(DARK)
+    }
+}
+
+/// Sampling taking more samples in noisy pixels than in smooth ones: each
+/// pixel gets a minimum number of samples, then twice as many as it has
+/// until its relative error reaches the target or its samples the maximum.
+///
+/// The error of a single pixel is itself noisy, so pixels are compared to
+/// the target with the largest error known around them, lest noisy pixels
+/// stop early by chance.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AdaptiveSampling {
+    pub min_samples: u32,
+    pub target_error: f64,
+}
+
+impl AdaptiveSampling {
+    /// Returns the numbers of samples to add to the pixels of a tile, given
+    /// row by row, 0 for the pixels that are done
+    pub fn next_batches(&self, estimates: &[Estimate], width: u32, max_samples: u32) -> Vec<u32> {
+        let width = width as usize;
+        let height = estimates.len() / width;
+        let min_samples = self.min_samples.min(max_samples);
+        let errors: Vec<f64> = estimates.iter().map(Estimate::relative_error).collect();
+
+        (0..estimates.len())
+            .map(|k| {
+                let count = estimates[k].count();
+                if count >= max_samples {
+                    return 0;
+                }
+
+                // The minimum is taken in two halves, to estimate its error
+                if count == 0 {
+                    return (min_samples / 2).max(1);
+                }
+                if count < min_samples {
+                    return min_samples - count;
+                }
+
+                let remaining = estimates[k].remaining();
+                if remaining > 0 {
+                    return remaining.min(max_samples - count);
+                }
+
+                let (i, j) = (k % width, k / width);
+                let error = (j.saturating_sub(1)..(j + 2).min(height))
+                    .flat_map(|y| (i.saturating_sub(1)..(i + 2).min(width)).map(move |x| (x, y)))
+                    .map(|(x, y)| errors[y * width + x])
+                    .filter(|error| error.is_finite())
+                    .fold(errors[k], f64::max);
+                if error <= self.target_error {
+                    return 0;
+                }
+
+                // Doubling keeps the sample counts of low-discrepancy
+                // samplers powers of two
+                count.min(max_samples - count)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Returns the estimate of a pixel sampled in two batches
+    fn estimate(first: &[f64], second: &[f64]) -> Estimate {
+        let mut estimate = Estimate::default();
+        for batch in [first, second] {
+            estimate.start_batch();
+            for &value in batch {
+                estimate.add(value);
+            }
+        }
+
+        estimate
+    }
+
+    #[test]
+    fn estimate_works() {
+        assert_eq!(f64::INFINITY, Estimate::default().relative_error());
+        assert_eq!(
+            f64::INFINITY,
+            estimate(&[1.0, 2.0], &[3.0]).relative_error()
+        );
+
+        let noisy = estimate(&[1.0, 2.0], &[3.0, 4.0]);
+        assert_eq!(4, noisy.count());
+        assert!((noisy.relative_error() - 1.0 / 2.5).abs() < 1e-12);
+
+        // Dark pixels
+        let dark = estimate(&[0.0], &[0.02]);
+        assert!((dark.relative_error() - 0.01 / 0.05).abs() < 1e-12);
+    }
+
+    #[test]
+    fn next_batches_works() {
+        let adaptive = AdaptiveSampling {
+            min_samples: 16,
+            target_error: 0.01,
+        };
+
+        let smooth = estimate(&[0.5; 8], &[0.5; 8]);
+        let noisy = estimate(&[0.4; 8], &[0.6; 8]);
+
+        // The smooth pixel next to the noisy one is not trusted
+        let row = [noisy, smooth, smooth, smooth];
+        assert_eq!(vec![16, 16, 0, 0], adaptive.next_batches(&row, 4, 256));
+        assert_eq!(vec![4, 4, 0, 0], adaptive.next_batches(&row, 4, 20));
+        assert_eq!(vec![0; 4], adaptive.next_batches(&row, 4, 16));
+
+        // The minimum
+        let column = [Estimate::default(), estimate(&[0.5; 4], &[0.5; 4])];
+        assert_eq!(vec![8, 8], adaptive.next_batches(&column, 1, 256));
+        assert_eq!(vec![2, 0], adaptive.next_batches(&column, 1, 4));
+
+        // A batch cut short is finished first, its error being unknown
+        let mut unfinished = smooth;
+        unfinished.start_batch();
+        for _ in 0..4 {
+            unfinished.add(0.5);
+        }
+        unfinished.start_batch();
+        assert_eq!(12, unfinished.remaining());
+        assert_eq!(
+            vec![12, 0],
+            adaptive.next_batches(&[unfinished, smooth], 2, 256)
+        );
+        assert_eq!(
+            vec![4, 0],
+            adaptive.next_batches(&[unfinished, smooth], 2, 24)
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::vec3::{Point3, Vec3};
 
 #[allow(dead_code)]
@@ -53,9 +54,10 @@ impl Camera {
     }
 
     /// Returns a ray going from the camera origin to the point represented by
-    /// `u` and `v` (a pixel on the image).
-    pub fn get_ray(&self, s: f64, t: f64) -> Ray {
-        let rd = self.lens_radius * Vec3::random_in_unit_disk();
+    /// `u` and `v` (a pixel on the image), through a point of the lens given
+    /// by the next two dimensions of the `sampler`.
+    pub fn get_ray(&self, s: f64, t: f64, sampler: &mut dyn Sampler) -> Ray {
+        let rd = self.lens_radius * Vec3::sample_in_unit_disk(sampler.get_2d());
         let offset = self.u * rd.x() + self.v * rd.y();
         Ray::new(
             self.origin + offset,
@@ -0,0 +1,231 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::framebuffer::Framebuffer;
+
+/// First bytes of a checkpoint file
+const MAGIC: &[u8; 8] = b"RTRSCKPT";
+
+/// Version of the layout of checkpoint files, increased when it changes
+const VERSION: u32 = 1;
+
+/// The state of an unfinished render, from which it can be resumed: the
+/// samples gathered by the pixels and the seed of the samplers. The samplers
+/// draw the samples of a pixel from the seed and the index of the sample, so
+/// that the samples taken in each pixel are all the state they need.
+///
+/// The hash of the scene and of the settings the render depends on is kept
+/// along, lest a render be resumed with another scene.
+pub struct Checkpoint {
+    pub scene_hash: u64,
+    pub seed: u64,
+    pub framebuffer: Framebuffer,
+}
+
+/// Error occurring while saving or loading a checkpoint
+#[derive(Debug)]
+pub enum CheckpointError {
+    /// The file could not be read or written
+    Io(io::Error),
+    /// The file is not a checkpoint
+    NotACheckpoint,
+    /// The checkpoint was written by another version of the renderer
+    UnsupportedVersion(u32),
+}
+
+impl Display for CheckpointError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CheckpointError::Io(err) => write!(f, "cannot access checkpoint file: {}", err),
+            CheckpointError::NotACheckpoint => write!(f, "not a checkpoint file"),
+            CheckpointError::UnsupportedVersion(version) => {
+                write!(f, "unsupported checkpoint version: {}", version)
+            }
+        }
+    }
+}
+
+impl Error for CheckpointError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CheckpointError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CheckpointError {
+    fn from(err: io::Error) -> Self {
+        CheckpointError::Io(err)
+    }
+}
+
+/// Saves the checkpoint of a framebuffer rendered with the given scene hash
+/// and seed to a file. Like images, it is written next to the file then
+/// moved over it, so that a render killed while saving keeps its previous
+/// checkpoint.
+pub fn save(
+    framebuffer: &Framebuffer,
+    path: &Path,
+    scene_hash: u64,
+    seed: u64,
+) -> Result<(), CheckpointError> {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+
+    let mut writer = BufWriter::new(File::create(&partial)?);
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    writer.write_all(&scene_hash.to_le_bytes())?;
+    writer.write_all(&seed.to_le_bytes())?;
+    framebuffer.write(&mut writer)?;
+    writer
+        .into_inner()
+        .map_err(|err| err.into_error())?
+        .sync_all()?;
+
+    fs::rename(&partial, path)?;
+
+    Ok(())
+}
+
+/// Loads a checkpoint saved by `save`
+pub fn load(path: &Path) -> Result<Checkpoint, CheckpointError> {
+    let mut reader = BufReader::new(File::open(path)?);
+
+    let mut header = [0; 28];
+    match reader.read_exact(&mut header) {
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+            return Err(CheckpointError::NotACheckpoint)
+        }
+        result => result?,
+    }
+    if &header[0..8] != MAGIC {
+        return Err(CheckpointError::NotACheckpoint);
+    }
+    let version = u32::from_le_bytes(header[8..12].try_into().unwrap());
+    if version != VERSION {
+        return Err(CheckpointError::UnsupportedVersion(version));
+    }
+
+    Ok(Checkpoint {
+        scene_hash: u64::from_le_bytes(header[12..20].try_into().unwrap()),
+        seed: u64::from_le_bytes(header[20..28].try_into().unwrap()),
+        framebuffer: Framebuffer::read(&mut reader)?,
+    })
+}
+
+/// Hashes the description of a scene and the files it is read from, with
+/// 64-bit FNV-1a: unlike the hasher of the standard library, its hashes do
+/// not change from a version of Rust to another.
+pub struct SceneHasher {
+    hash: u64,
+}
+
+impl SceneHasher {
+    pub fn new() -> SceneHasher {
+        SceneHasher {
+            hash: 0xcbf2_9ce4_8422_2325,
+        }
+    }
+
+    /// Adds bytes to the hash
+    pub fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.hash ^= byte as u64;
+            self.hash = self.hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    /// Adds the contents of a file to the hash
+    pub fn write_file(&mut self, path: &Path) -> io::Result<()> {
+        self.write(&fs::read(path)?);
+        Ok(())
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+impl Default for SceneHasher {
+    fn default() -> Self {
+        SceneHasher::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::color::Color;
+    use crate::framebuffer::Pixel;
+    use crate::tile::Tile;
+
+    #[test]
+    fn save_load_works() {
+        let mut framebuffer = Framebuffer::new(3, 2);
+        let tile = Tile {
+            x: 1,
+            y: 0,
+            width: 2,
+            height: 2,
+        };
+        let mut pixel = Pixel::default();
+        pixel.add_sample(Color::new(0.25, 0.5, 3.0), 0.75);
+        framebuffer.merge(&tile, &[pixel; 4]);
+        let mut estimates = framebuffer.estimates(&tile);
+        for (k, estimate) in estimates.iter_mut().enumerate() {
+            estimate.start_batch();
+            for _ in 0..=k {
+                estimate.add(0.1 * k as f64);
+            }
+        }
+        framebuffer.set_estimates(&tile, &estimates);
+
+        let path =
+            std::env::temp_dir().join(format!("save_load_works_{}.ckpt", std::process::id()));
+        save(&framebuffer, &path, 0x0123_4567_89ab_cdef, 42).unwrap();
+        let loaded = load(&path).unwrap();
+
+        assert_eq!(0x0123_4567_89ab_cdef, loaded.scene_hash);
+        assert_eq!(42, loaded.seed);
+        assert_eq!(
+            (3, 2),
+            (loaded.framebuffer.width(), loaded.framebuffer.height())
+        );
+        assert_eq!(estimates, loaded.framebuffer.estimates(&tile));
+        for y in 0..2 {
+            for x in 0..3 {
+                assert_eq!(framebuffer.color(x, y), loaded.framebuffer.color(x, y));
+                assert_eq!(framebuffer.weight(x, y), loaded.framebuffer.weight(x, y));
+            }
+        }
+
+        // Other files are refused
+        fs::write(&path, b"P6\n3 2\n255\n").unwrap();
+        assert!(matches!(load(&path), Err(CheckpointError::NotACheckpoint)));
+        let mut other_version = MAGIC.to_vec();
+        other_version.extend_from_slice(&2u32.to_le_bytes());
+        other_version.extend_from_slice(&[0; 16]);
+        fs::write(&path, other_version).unwrap();
+        assert!(matches!(
+            load(&path),
+            Err(CheckpointError::UnsupportedVersion(2))
+        ));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn scene_hasher_works() {
+        // Reference values of FNV-1a
+        assert_eq!(0xcbf2_9ce4_8422_2325, SceneHasher::new().finish());
+        let mut hasher = SceneHasher::new();
+        hasher.write(b"a");
+        assert_eq!(0xaf63_dc4c_8601_ec8c, hasher.finish());
+    }
+}
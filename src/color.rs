@@ -1,26 +1,24 @@
 use crate::vec3::Vec3;
-use image::{Rgb, RgbImage};
+use image::Rgb;
 
 /// Alias of Vec3 representing a color
 pub type Color = Vec3;
 
 impl Color {
-    pub fn write(&self, img: &mut RgbImage, x: u32, y: u32, samples_per_pixel: i32) {
-        // Divide the color by the number of samples
-        let scale = 1.0 / samples_per_pixel as f64;
+    /// Returns the relative luminance of a linear color
+    pub fn luminance(self) -> f64 {
+        0.2126 * self.x() + 0.7152 * self.y() + 0.0722 * self.z()
+    }
 
-        let red = (self.x() * scale).sqrt();
-        let green = (self.y() * scale).sqrt();
-        let blue = (self.z() * scale).sqrt();
+    /// Converts an encoded color, between 0 and 1, to 8-bit RGB
+    pub fn to_rgb8(self) -> Rgb<u8> {
+        let quantize = |value: f64| (255.0 * value.clamp(0.0, 1.0)).round() as u8;
+        Rgb([quantize(self.x()), quantize(self.y()), quantize(self.z())])
+    }
 
-        img.put_pixel(
-            x,
-            y,
-            Rgb([
-                (255.99 * red.clamp(0.0, 0.999)) as u8,
-                (255.99 * green.clamp(0.0, 0.999)) as u8,
-                (255.99 * blue.clamp(0.0, 0.999)) as u8,
-            ]),
-        );
+    /// Converts an encoded color, between 0 and 1, to 16-bit RGB
+    pub fn to_rgb16(self) -> Rgb<u16> {
+        let quantize = |value: f64| (65535.0 * value.clamp(0.0, 1.0)).round() as u16;
+        Rgb([quantize(self.x()), quantize(self.y()), quantize(self.z())])
     }
 }
@@ -0,0 +1,882 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Component, Path};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+
+use crate::adaptive::Estimate;
+use crate::framebuffer::Pixel;
+use crate::image::{Image, RenderedTile, TileJob, TileOutcome};
+use crate::parallel::ThreadPool;
+use crate::tile::Tile;
+
+/// First bytes sent by coordinators, followed by the version of the protocol
+const MAGIC: &[u8; 8] = b"RTRSDIST";
+const VERSION: u32 = 2;
+
+// Messages from coordinators to workers
+const SETUP: u8 = 1;
+const TILE: u8 = 2;
+const DONE: u8 = 3;
+
+// Messages from workers to coordinators
+const READY: u8 = 1;
+const FAILED: u8 = 2;
+const RENDERED: u8 = 3;
+const TILE_FAILED: u8 = 4;
+
+/// Tiles sent to a worker for each of its threads before it returns any,
+/// so that its threads do not wait for the network
+const TILES_PER_THREAD: usize = 2;
+
+/// Most pixels of a tile or region read from the network
+const MAX_PIXELS: u32 = 1 << 24;
+
+/// What workers need to build the image of the coordinator: the
+/// command-line options the scene and its sampling depend on, some of which
+/// name files, sent along with their contents and the files they refer to.
+pub struct Setup {
+    pub args: Vec<String>,
+    /// Indices of the arguments naming files, with the files those refer
+    /// to, relative to their directory
+    pub files: Vec<(usize, Vec<String>)>,
+}
+
+/// A tile waiting for a worker, and where to send it once rendered
+struct Queued {
+    index: usize,
+    job: TileJob,
+    cancelled: Option<&'static AtomicBool>,
+    sender: Sender<(usize, TileOutcome)>,
+}
+
+struct QueueState {
+    tiles: VecDeque<Queued>,
+    connected: usize,
+    closed: bool,
+}
+
+/// Tiles waiting for workers, shared by the connections
+struct Queue {
+    state: Mutex<QueueState>,
+    changed: Condvar,
+}
+
+impl Queue {
+    /// Takes at most `count` tiles, waiting for some if `wait` is set and
+    /// none is queued. Returns `None` once the queue is closed and empty.
+    fn take(&self, count: usize, wait: bool) -> Option<Vec<Queued>> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if !state.tiles.is_empty() || !wait {
+                let count = count.min(state.tiles.len());
+                return Some(state.tiles.drain(..count).collect());
+            }
+            if state.closed {
+                return None;
+            }
+            state = self.changed.wait(state).unwrap();
+        }
+    }
+}
+
+/// Remote workers rendering the tiles of a coordinator, over connections
+/// served by threads of their own. Tiles are queued and taken by the
+/// connections as their workers get free threads, so that faster workers
+/// render more tiles; the tiles of a worker disconnecting are given back to
+/// the others.
+pub struct Workers {
+    queue: Arc<Queue>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl Workers {
+    /// Connects to the workers listening at the given addresses, and has
+    /// them build their image from the setup. The image is `width` by
+    /// `height` pixels: rendered tiles reaching out of it are refused.
+    pub fn connect(
+        addresses: &[String],
+        setup: &Setup,
+        width: u32,
+        height: u32,
+    ) -> io::Result<Workers> {
+        let mut connections = Vec::new();
+        for address in addresses {
+            let connection = TcpStream::connect(address.as_str())
+                .and_then(|stream| {
+                    stream.set_nodelay(true)?;
+                    let threads = send_setup(&stream, setup)?;
+                    Ok((stream, threads))
+                })
+                .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", address, err)))?;
+            connections.push((address.clone(), connection));
+        }
+
+        let queue = Arc::new(Queue {
+            state: Mutex::new(QueueState {
+                tiles: VecDeque::new(),
+                connected: connections.len(),
+                closed: false,
+            }),
+            changed: Condvar::new(),
+        });
+
+        let image = Tile {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        };
+        let threads = connections
+            .into_iter()
+            .map(|(address, (stream, threads))| {
+                let queue = Arc::clone(&queue);
+                thread::spawn(move || {
+                    Workers::serve(
+                        &stream,
+                        threads * TILES_PER_THREAD,
+                        &queue,
+                        &image,
+                        &address,
+                    )
+                })
+            })
+            .collect();
+
+        Ok(Workers { queue, threads })
+    }
+
+    /// Queues a tile, to be sent back with its index once rendered or failed,
+    /// or skipped if `cancelled` is set before a worker takes it. Without any
+    /// worker left, the tile is dropped.
+    pub fn execute(
+        &self,
+        index: usize,
+        job: TileJob,
+        cancelled: Option<&'static AtomicBool>,
+        sender: Sender<(usize, TileOutcome)>,
+    ) {
+        let mut state = self.queue.state.lock().unwrap();
+        if state.connected > 0 {
+            state.tiles.push_back(Queued {
+                index,
+                job,
+                cancelled,
+                sender,
+            });
+            self.queue.changed.notify_all();
+        }
+    }
+
+    /// Serves a worker until the queue is closed or the worker disconnects
+    fn serve(stream: &TcpStream, capacity: usize, queue: &Queue, image: &Tile, address: &str) {
+        let mut in_flight = Vec::new();
+        let result = Workers::exchange(stream, capacity.max(1), queue, image, &mut in_flight);
+
+        let mut state = queue.state.lock().unwrap();
+        state.connected -= 1;
+        if let Err(err) = result {
+            eprintln!("Worker {} disconnected: {}", address, err);
+            for queued in in_flight.into_iter().rev() {
+                state.tiles.push_front(queued);
+            }
+            if state.connected == 0 {
+                state.tiles.clear();
+            }
+            queue.changed.notify_all();
+        }
+    }
+
+    /// Sends tiles to a worker and forwards the rendered ones, keeping at
+    /// most `capacity` of them in flight. Tiles not matching their jobs are
+    /// an error, lest they be merged out of the framebuffer.
+    fn exchange(
+        stream: &TcpStream,
+        capacity: usize,
+        queue: &Queue,
+        image: &Tile,
+        in_flight: &mut Vec<Queued>,
+    ) -> io::Result<()> {
+        let mut reader = BufReader::new(stream);
+        let mut writer = BufWriter::new(stream);
+
+        loop {
+            let tiles = match queue.take(capacity - in_flight.len(), in_flight.is_empty()) {
+                Some(tiles) => tiles,
+                None => {
+                    writer.write_all(&[DONE])?;
+                    return writer.flush();
+                }
+            };
+
+            for queued in tiles {
+                if queued
+                    .cancelled
+                    .is_some_and(|cancelled| cancelled.load(Ordering::SeqCst))
+                {
+                    queued
+                        .sender
+                        .send((queued.index, TileOutcome::Skipped))
+                        .ok();
+                    continue;
+                }
+
+                write_tile_job(&mut writer, queued.index as u32, &queued.job)?;
+                in_flight.push(queued);
+            }
+            writer.flush()?;
+
+            if in_flight.is_empty() {
+                continue;
+            }
+
+            let (index, outcome) = match read_u8(&mut reader)? {
+                RENDERED => {
+                    let (index, rendered) = read_rendered(&mut reader)?;
+                    (index, TileOutcome::Rendered(rendered))
+                }
+                TILE_FAILED => {
+                    let index = read_u32(&mut reader)?;
+                    (index, TileOutcome::Failed(read_string(&mut reader)?))
+                }
+                _ => return Err(invalid("unexpected message")),
+            };
+            let position = in_flight
+                .iter()
+                .position(|queued| queued.index == index as usize)
+                .ok_or_else(|| invalid("unexpected tile"))?;
+            if let TileOutcome::Rendered(rendered) = &outcome {
+                let tile = &in_flight[position].job.tile;
+                if rendered.estimates.len() != (tile.width * tile.height) as usize
+                    || !rendered.region.contains(tile)
+                    || !image.contains(&rendered.region)
+                {
+                    return Err(invalid("rendered tile not matching its job"));
+                }
+            }
+            let queued = in_flight.swap_remove(position);
+            queued.sender.send((queued.index, outcome)).ok();
+        }
+    }
+}
+
+impl Drop for Workers {
+    fn drop(&mut self) {
+        self.queue.state.lock().unwrap().closed = true;
+        self.queue.changed.notify_all();
+
+        for thread in self.threads.drain(..) {
+            thread.join().unwrap();
+        }
+    }
+}
+
+/// Sends the setup to a worker, and returns its number of threads once its
+/// image is built
+fn send_setup(stream: &TcpStream, setup: &Setup) -> io::Result<usize> {
+    let mut writer = BufWriter::new(stream);
+    writer.write_all(MAGIC)?;
+    write_u32(&mut writer, VERSION)?;
+    writer.write_all(&[SETUP])?;
+    write_u32(&mut writer, setup.args.len() as u32)?;
+    for arg in &setup.args {
+        write_bytes(&mut writer, arg.as_bytes())?;
+    }
+    write_u32(&mut writer, setup.files.len() as u32)?;
+    for (index, dependencies) in &setup.files {
+        let path = Path::new(&setup.args[*index]);
+        write_u32(&mut writer, *index as u32)?;
+        write_bytes(&mut writer, &fs::read(path)?)?;
+
+        let directory = path.parent().unwrap_or_else(|| Path::new(""));
+        write_u32(&mut writer, dependencies.len() as u32)?;
+        for dependency in dependencies {
+            write_bytes(&mut writer, dependency.as_bytes())?;
+            write_bytes(&mut writer, &fs::read(directory.join(dependency))?)?;
+        }
+    }
+    writer.flush()?;
+
+    let mut reader = BufReader::new(stream);
+    match read_u8(&mut reader)? {
+        READY => Ok(read_u32(&mut reader)? as usize),
+        FAILED => Err(io::Error::other(read_string(&mut reader)?)),
+        _ => Err(invalid("unexpected message")),
+    }
+}
+
+/// Serves the coordinators connecting to the listener, one after the other,
+/// rendering their tiles with `thread_number` threads. The image of each
+/// coordinator is built by `build` from the arguments of its setup, its
+/// files being copied to a temporary directory they then name.
+pub fn serve(
+    listener: TcpListener,
+    thread_number: usize,
+    build: impl Fn(&[String]) -> Result<Image, String>,
+) -> io::Result<()> {
+    for (session, stream) in listener.incoming().enumerate() {
+        let stream = stream?;
+        let peer = stream.peer_addr()?;
+        let directory = std::env::temp_dir().join(format!(
+            "raytracing_in_rust_{}_{}",
+            std::process::id(),
+            session
+        ));
+
+        if let Err(err) = serve_coordinator(&stream, &directory, thread_number, &build) {
+            eprintln!("Coordinator {} disconnected: {}", peer, err);
+        }
+        fs::remove_dir_all(&directory).ok();
+    }
+
+    Ok(())
+}
+
+/// Builds the image of a coordinator, then renders the tiles it sends until
+/// it is done
+fn serve_coordinator(
+    stream: &TcpStream,
+    directory: &Path,
+    thread_number: usize,
+    build: impl Fn(&[String]) -> Result<Image, String>,
+) -> io::Result<()> {
+    stream.set_nodelay(true)?;
+    let mut reader = BufReader::new(stream);
+    let mut writer = BufWriter::new(stream);
+
+    let mut magic = [0; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid("not a coordinator"));
+    }
+    let version = read_u32(&mut reader)?;
+    if version != VERSION {
+        writer.write_all(&[FAILED])?;
+        write_bytes(
+            &mut writer,
+            format!("unsupported version: {}", version).as_bytes(),
+        )?;
+        return writer.flush();
+    }
+
+    if read_u8(&mut reader)? != SETUP {
+        return Err(invalid("unexpected message"));
+    }
+    let mut args = Vec::new();
+    for _ in 0..read_u32(&mut reader)? {
+        args.push(read_string(&mut reader)?);
+    }
+
+    // Each file gets a directory of its own, where it is named after the
+    // one of the coordinator, next to the files it refers to
+    for file in 0..read_u32(&mut reader)? {
+        let index = read_u32(&mut reader)? as usize;
+        let contents = read_bytes(&mut reader)?;
+        let arg = args
+            .get_mut(index)
+            .ok_or_else(|| invalid("no such argument"))?;
+        let name = Path::new(arg.as_str())
+            .file_name()
+            .ok_or_else(|| invalid("not a file"))?;
+        let file_directory = directory.join(file.to_string());
+        let path = file_directory.join(name);
+        fs::create_dir_all(&file_directory)?;
+        fs::write(&path, contents)?;
+        *arg = path.to_string_lossy().into_owned();
+
+        for _ in 0..read_u32(&mut reader)? {
+            let dependency = read_string(&mut reader)?;
+            let contents = read_bytes(&mut reader)?;
+
+            // Nothing is written out of the directory
+            let dependency = Path::new(&dependency);
+            if !dependency
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)))
+            {
+                return Err(invalid("invalid file name"));
+            }
+            let path = file_directory.join(dependency);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, contents)?;
+        }
+    }
+
+    let image = match build(&args) {
+        Ok(image) => image,
+        Err(message) => {
+            writer.write_all(&[FAILED])?;
+            write_bytes(&mut writer, message.as_bytes())?;
+            return writer.flush();
+        }
+    };
+    writer.write_all(&[READY])?;
+    write_u32(&mut writer, thread_number as u32)?;
+    writer.flush()?;
+
+    // Rendered tiles, or the messages of their failures, are sent back by a
+    // thread of their own, in the order they are done
+    let mut writer = BufWriter::new(stream.try_clone()?);
+    let (sender, receiver) = mpsc::channel::<(u32, Result<RenderedTile, String>)>();
+    let sending = thread::spawn(move || -> io::Result<()> {
+        for (index, rendered) in receiver {
+            match rendered {
+                Ok(rendered) => write_rendered(&mut writer, index, &rendered)?,
+                Err(message) => {
+                    writer.write_all(&[TILE_FAILED])?;
+                    write_u32(&mut writer, index)?;
+                    write_bytes(&mut writer, message.as_bytes())?;
+                }
+            }
+            writer.flush()?;
+        }
+        Ok(())
+    });
+
+    let pool = ThreadPool::new(thread_number);
+    let result = pool.scope(|scope| loop {
+        let job = match read_u8(&mut reader) {
+            Ok(TILE) => read_tile_job(&mut reader),
+            Ok(DONE) => break Ok(()),
+            Ok(_) => Err(invalid("unexpected message")),
+            Err(err) => Err(err),
+        };
+        let (index, job) = match job {
+            Ok(job) => job,
+            Err(err) => break Err(err),
+        };
+
+        let image = &image;
+        let sender = sender.clone();
+        scope.spawn(move || {
+            sender.send((index, image.render_tile(job))).ok();
+        });
+    });
+
+    drop(sender);
+    let sent = sending.join().unwrap();
+
+    result.and(sent)
+}
+
+fn write_tile_job(writer: &mut impl Write, index: u32, job: &TileJob) -> io::Result<()> {
+    writer.write_all(&[TILE])?;
+    write_u32(writer, index)?;
+    write_tile(writer, &job.tile)?;
+    write_u32(writer, job.max_samples)?;
+    writer.write_all(&job.max_depth.to_le_bytes())?;
+    for estimate in &job.estimates {
+        writer.write_all(&estimate.to_bytes())?;
+    }
+
+    Ok(())
+}
+
+fn read_tile_job(reader: &mut impl Read) -> io::Result<(u32, TileJob)> {
+    let index = read_u32(reader)?;
+    let tile = read_tile(reader)?;
+    let max_samples = read_u32(reader)?;
+    let max_depth = read_u32(reader)? as i32;
+    let estimates = read_estimates(reader, tile.width * tile.height)?;
+
+    Ok((
+        index,
+        TileJob {
+            tile,
+            estimates,
+            max_samples,
+            max_depth,
+        },
+    ))
+}
+
+fn write_rendered(writer: &mut impl Write, index: u32, rendered: &RenderedTile) -> io::Result<()> {
+    writer.write_all(&[RENDERED])?;
+    write_u32(writer, index)?;
+    write_tile(writer, &rendered.region)?;
+    for pixel in &rendered.pixels {
+        writer.write_all(&pixel.to_bytes())?;
+    }
+    write_u32(writer, rendered.estimates.len() as u32)?;
+    for estimate in &rendered.estimates {
+        writer.write_all(&estimate.to_bytes())?;
+    }
+
+    Ok(())
+}
+
+fn read_rendered(reader: &mut impl Read) -> io::Result<(u32, RenderedTile)> {
+    let index = read_u32(reader)?;
+    let region = read_tile(reader)?;
+    let mut pixels = Vec::with_capacity((region.width * region.height) as usize);
+    for _ in 0..region.width * region.height {
+        let mut bytes = [0; 16];
+        reader.read_exact(&mut bytes)?;
+        pixels.push(Pixel::from_bytes(bytes));
+    }
+    let count = read_u32(reader)?;
+    if count > MAX_PIXELS {
+        return Err(invalid("tile too large"));
+    }
+    let estimates = read_estimates(reader, count)?;
+
+    Ok((
+        index,
+        RenderedTile {
+            region,
+            pixels,
+            estimates,
+        },
+    ))
+}
+
+fn read_estimates(reader: &mut impl Read, count: u32) -> io::Result<Vec<Estimate>> {
+    let mut estimates = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let mut bytes = [0; 24];
+        reader.read_exact(&mut bytes)?;
+        estimates.push(Estimate::from_bytes(bytes));
+    }
+
+    Ok(estimates)
+}
+
+fn write_tile(writer: &mut impl Write, tile: &Tile) -> io::Result<()> {
+    for value in [tile.x, tile.y, tile.width, tile.height] {
+        write_u32(writer, value)?;
+    }
+
+    Ok(())
+}
+
+fn read_tile(reader: &mut impl Read) -> io::Result<Tile> {
+    let tile = Tile {
+        x: read_u32(reader)?,
+        y: read_u32(reader)?,
+        width: read_u32(reader)?,
+        height: read_u32(reader)?,
+    };
+    if tile
+        .width
+        .checked_mul(tile.height)
+        .is_none_or(|count| count > MAX_PIXELS)
+    {
+        return Err(invalid("tile too large"));
+    }
+
+    Ok(tile)
+}
+
+fn write_u32(writer: &mut impl Write, value: u32) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn read_u8(reader: &mut impl Read) -> io::Result<u8> {
+    let mut bytes = [0; 1];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes[0])
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+/// Writes bytes after their length
+fn write_bytes(writer: &mut impl Write, bytes: &[u8]) -> io::Result<()> {
+    writer.write_all(&(bytes.len() as u64).to_le_bytes())?;
+    writer.write_all(bytes)
+}
+
+fn read_bytes(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut length = [0; 8];
+    reader.read_exact(&mut length)?;
+    let length = u64::from_le_bytes(length);
+
+    let mut bytes = Vec::new();
+    reader.take(length).read_to_end(&mut bytes)?;
+    if (bytes.len() as u64) < length {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    Ok(bytes)
+}
+
+fn read_string(reader: &mut impl Read) -> io::Result<String> {
+    String::from_utf8(read_bytes(reader)?).map_err(|_| invalid("invalid string"))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::camera::Camera;
+    use crate::color::Color;
+    use crate::filter::MitchellFilter;
+    use crate::hittable::hittable_list::HittableList;
+    use crate::hittable::sphere::Sphere;
+    use crate::image::RenderError;
+    use crate::material::lambertian::Lambertian;
+    use crate::sampler::sobol::SobolSampler;
+    use crate::sampler::Sampler;
+    use crate::tile::TileOrder;
+    use crate::vec3::{Point3, Vec3};
+    use std::sync::atomic::AtomicUsize;
+
+    fn image() -> Image {
+        let mut world = HittableList::new();
+        let material = Arc::new(Lambertian::new(Color::new(0.5, 0.2, 0.1)));
+        world.add(Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, -1.0),
+            0.5,
+            material.clone(),
+        )));
+        world.add(Arc::new(Sphere::new(
+            Point3::new(0.0, -100.5, -1.0),
+            100.0,
+            material,
+        )));
+        let camera = Camera::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            90.0,
+            1.5,
+            0.0,
+            1.0,
+        );
+
+        Image::new(1.5, 24, Arc::new(world), Vec::new(), Arc::new(camera))
+            .with_filter(Arc::new(MitchellFilter::new(2.0, 1.0 / 3.0, 1.0 / 3.0)))
+            .with_sampler(Box::new(SobolSampler::new(7)))
+    }
+
+    #[test]
+    fn workers_work() {
+        let scene = std::env::temp_dir().join(format!("workers_work_{}.txt", std::process::id()));
+        let mesh = std::env::temp_dir().join(format!("workers_work_{}.ply", std::process::id()));
+        fs::write(&scene, b"scene").unwrap();
+        fs::write(&mesh, b"mesh").unwrap();
+
+        // Workers on localhost, checking they get the setup
+        let addresses: Vec<String> = (0..2)
+            .map(|_| {
+                let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+                let address = listener.local_addr().unwrap().to_string();
+                thread::spawn(move || {
+                    serve(listener, 2, |args| {
+                        assert_eq!("--scene", args[0]);
+                        let scene = Path::new(&args[1]);
+                        assert_eq!(b"scene", &fs::read(scene).unwrap()[..]);
+                        let mesh = scene.with_extension("ply");
+                        assert_eq!(b"mesh", &fs::read(mesh).unwrap()[..]);
+                        Ok(image())
+                    })
+                });
+                address
+            })
+            .collect();
+
+        let setup = Setup {
+            args: vec!["--scene".to_string(), scene.to_string_lossy().into_owned()],
+            files: vec![(
+                1,
+                vec![mesh.file_name().unwrap().to_string_lossy().into_owned()],
+            )],
+        };
+        let workers = Workers::connect(&addresses, &setup, 24, 16).unwrap();
+
+        // Tiles are merged in the same order, whoever renders them
+        let local = image()
+            .with_passes(4)
+            .render_image(8, 5, 2, 8, TileOrder::Spiral, |_, _| ())
+            .unwrap();
+        let remote = image()
+            .with_passes(4)
+            .with_workers(workers)
+            .render_image(8, 5, 2, 8, TileOrder::Spiral, |_, _| ())
+            .unwrap();
+        for y in 0..local.height() {
+            for x in 0..local.width() {
+                assert_eq!(local.color(x, y), remote.color(x, y));
+                assert_eq!(local.samples(x, y), remote.samples(x, y));
+            }
+        }
+        assert_eq!(8 * 24 * 16, remote.total_samples());
+
+        fs::remove_file(&scene).unwrap();
+        fs::remove_file(&mesh).unwrap();
+    }
+
+    /// Serves a coordinator as a worker of one thread, but sends back the
+    /// tiles made by `corrupt`, counting the jobs it gets
+    fn misbehaving_worker(corrupt: fn(&TileJob) -> RenderedTile, jobs: Arc<AtomicUsize>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(&stream);
+            let mut writer = BufWriter::new(&stream);
+
+            // A setup without arguments nor files
+            let mut setup = [0; 21];
+            reader.read_exact(&mut setup).unwrap();
+            writer.write_all(&[READY]).unwrap();
+            write_u32(&mut writer, 1).unwrap();
+            writer.flush().unwrap();
+
+            // Until the coordinator disconnects
+            while let Ok(TILE) = read_u8(&mut reader) {
+                let (index, job) = read_tile_job(&mut reader).unwrap();
+                jobs.fetch_add(1, Ordering::SeqCst);
+                let sent = write_rendered(&mut writer, index, &corrupt(&job));
+                if sent.and_then(|_| writer.flush()).is_err() {
+                    break;
+                }
+            }
+        });
+
+        address
+    }
+
+    #[test]
+    fn misbehaving_worker_works() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || serve(listener, 2, |_| Ok(image())));
+
+        let jobs = Arc::new(AtomicUsize::new(0));
+        let addresses = [
+            address,
+            // Estimates missing
+            misbehaving_worker(
+                |job| RenderedTile {
+                    region: job.tile,
+                    pixels: vec![Pixel::default(); (job.tile.width * job.tile.height) as usize],
+                    estimates: Vec::new(),
+                },
+                Arc::clone(&jobs),
+            ),
+            // Region out of the image
+            misbehaving_worker(
+                |job| {
+                    let region = Tile {
+                        width: job.tile.width + 24,
+                        ..job.tile
+                    };
+                    RenderedTile {
+                        region,
+                        pixels: vec![Pixel::default(); (region.width * region.height) as usize],
+                        estimates: job.estimates.clone(),
+                    }
+                },
+                Arc::clone(&jobs),
+            ),
+        ];
+        let setup = Setup {
+            args: Vec::new(),
+            files: Vec::new(),
+        };
+        let workers = Workers::connect(&addresses, &setup, 24, 16).unwrap();
+
+        // The tiles of the misbehaving workers are rendered by the other one
+        let local = image()
+            .with_passes(4)
+            .render_image(8, 5, 2, 8, TileOrder::Spiral, |_, _| ())
+            .unwrap();
+        let remote = image()
+            .with_passes(4)
+            .with_workers(workers)
+            .render_image(8, 5, 2, 8, TileOrder::Spiral, |_, _| ())
+            .unwrap();
+        for y in 0..local.height() {
+            for x in 0..local.width() {
+                assert_eq!(local.color(x, y), remote.color(x, y));
+                assert_eq!(local.samples(x, y), remote.samples(x, y));
+            }
+        }
+        assert!(jobs.load(Ordering::SeqCst) > 0);
+    }
+
+    /// A sampler panicking in the pixel (13, 9)
+    struct FailingSampler;
+
+    impl Sampler for FailingSampler {
+        fn clone_sampler(&self) -> Box<dyn Sampler + Send> {
+            Box::new(FailingSampler)
+        }
+
+        fn start_pixel_sample(&mut self, x: u32, y: u32, _index: u32) {
+            if (x, y) == (13, 9) {
+                panic!("NaN radiance");
+            }
+        }
+
+        fn get_1d(&mut self) -> f64 {
+            0.5
+        }
+
+        fn get_2d(&mut self) -> (f64, f64) {
+            (0.5, 0.5)
+        }
+    }
+
+    #[test]
+    fn failed_tile_works() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            serve(listener, 2, |_| {
+                Ok(image().with_sampler(Box::new(FailingSampler)))
+            })
+        });
+        let setup = Setup {
+            args: Vec::new(),
+            files: Vec::new(),
+        };
+        let workers = Workers::connect(std::slice::from_ref(&address), &setup, 24, 16).unwrap();
+
+        // The tile of the pixel fails, whether rendered locally or remotely
+        for workers in [None, Some(workers)] {
+            let mut image = image().with_sampler(Box::new(FailingSampler));
+            if let Some(workers) = workers {
+                image = image.with_workers(workers);
+            }
+            let err = image
+                .render_image(8, 5, 2, 8, TileOrder::Spiral, |_, _| ())
+                .err()
+                .unwrap();
+            assert_eq!(
+                "the rendering of the 8x8 tile at (8, 8) failed: NaN radiance",
+                err.to_string()
+            );
+            assert!(matches!(err, RenderError::TileFailed { .. }));
+        }
+    }
+
+    #[test]
+    fn failed_setup_works() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || serve(listener, 1, |_| Err("no such scene".to_string())));
+
+        let setup = Setup {
+            args: Vec::new(),
+            files: Vec::new(),
+        };
+        let err = Workers::connect(std::slice::from_ref(&address), &setup, 24, 16)
+            .err()
+            .unwrap();
+        assert_eq!(format!("{}: no such scene", address), err.to_string());
+    }
+}
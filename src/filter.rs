@@ -0,0 +1,214 @@
+use std::f64::consts::PI;
+
+/// Represents a pixel reconstruction filter: each sample is added to every
+/// pixel whose center is within the radius, weighted by the filter
+pub trait Filter {
+    /// Returns the distance, in pixels along each axis, beyond which the
+    /// weight is zero
+    fn radius(&self) -> f64;
+
+    /// Returns the weight of a sample at the offset `(x, y)`, in pixels,
+    /// from the center of a pixel
+    fn evaluate(&self, x: f64, y: f64) -> f64;
+}
+
+/// A filter weighting equally every sample within the radius (a radius of
+/// half a pixel only keeps the samples inside the pixel). The interval is
+/// half-open, so that a sample on the edge of two pixels only counts in one.
+pub struct BoxFilter {
+    radius: f64,
+}
+
+impl BoxFilter {
+    pub fn new(radius: f64) -> BoxFilter {
+        BoxFilter { radius }
+    }
+}
+
+impl Filter for BoxFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        let inside = -self.radius..self.radius;
+        if inside.contains(&x) && inside.contains(&y) {
+            1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+/// A filter whose weight decreases linearly with the distance to the center
+/// on each axis
+pub struct TentFilter {
+    radius: f64,
+}
+
+impl TentFilter {
+    pub fn new(radius: f64) -> TentFilter {
+        TentFilter { radius }
+    }
+}
+
+impl Filter for TentFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        (self.radius - x.abs()).max(0.0) * (self.radius - y.abs()).max(0.0)
+    }
+}
+
+/// A Gaussian filter, shifted down to reach zero at the radius
+pub struct GaussianFilter {
+    radius: f64,
+    /// Falloff of the Gaussian, `exp(-alpha * x^2)`
+    alpha: f64,
+}
+
+impl GaussianFilter {
+    pub fn new(radius: f64, alpha: f64) -> GaussianFilter {
+        GaussianFilter { radius, alpha }
+    }
+
+    fn gaussian(&self, x: f64) -> f64 {
+        ((-self.alpha * x * x).exp() - (-self.alpha * self.radius * self.radius).exp()).max(0.0)
+    }
+}
+
+impl Filter for GaussianFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        self.gaussian(x) * self.gaussian(y)
+    }
+}
+
+/// The cubic filter of Mitchell and Netravali, sharpening edges with
+/// negative lobes
+pub struct MitchellFilter {
+    radius: f64,
+    b: f64,
+    c: f64,
+}
+
+impl MitchellFilter {
+    /// Constructs a new filter with the parameters `b` and `c` of the cubic,
+    /// `b = c = 1/3` being recommended
+    pub fn new(radius: f64, b: f64, c: f64) -> MitchellFilter {
+        MitchellFilter { radius, b, c }
+    }
+
+    /// Evaluates the cubic, which spans `[-2, 2]`
+    fn mitchell(&self, x: f64) -> f64 {
+        let (b, c) = (self.b, self.c);
+        let x = x.abs();
+
+        if x < 1.0 {
+            ((12.0 - 9.0 * b - 6.0 * c) * x * x * x
+                + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+                + (6.0 - 2.0 * b))
+                / 6.0
+        } else if x < 2.0 {
+            ((-b - 6.0 * c) * x * x * x
+                + (6.0 * b + 30.0 * c) * x * x
+                + (-12.0 * b - 48.0 * c) * x
+                + (8.0 * b + 24.0 * c))
+                / 6.0
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Filter for MitchellFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        self.mitchell(2.0 * x / self.radius) * self.mitchell(2.0 * y / self.radius)
+    }
+}
+
+/// A sinc filter windowed by the central lobe of a wider sinc, spanning the
+/// radius
+pub struct LanczosFilter {
+    radius: f64,
+}
+
+impl LanczosFilter {
+    pub fn new(radius: f64) -> LanczosFilter {
+        LanczosFilter { radius }
+    }
+
+    fn lanczos(&self, x: f64) -> f64 {
+        if x.abs() >= self.radius {
+            return 0.0;
+        }
+
+        sinc(x) * sinc(x / self.radius)
+    }
+}
+
+impl Filter for LanczosFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        self.lanczos(x) * self.lanczos(y)
+    }
+}
+
+/// Returns the normalized sinc, `sin(pi x) / (pi x)`
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-5 {
+        return 1.0;
+    }
+
+    (PI * x).sin() / (PI * x)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn filters_work() {
+        let filters: Vec<Box<dyn Filter>> = vec![
+            Box::new(BoxFilter::new(0.5)),
+            Box::new(TentFilter::new(1.0)),
+            Box::new(GaussianFilter::new(1.5, 2.0)),
+            Box::new(MitchellFilter::new(2.0, 1.0 / 3.0, 1.0 / 3.0)),
+            Box::new(LanczosFilter::new(3.0)),
+        ];
+
+        for filter in filters {
+            let radius = filter.radius();
+            let center = filter.evaluate(0.0, 0.0);
+
+            assert!(center > 0.0);
+            assert!(filter.evaluate(0.3 * radius, 0.0) <= center);
+            assert_eq!(filter.evaluate(0.2, -0.4), filter.evaluate(-0.2, 0.4));
+            assert_eq!(0.0, filter.evaluate(radius + 0.01, 0.0));
+            assert_eq!(0.0, filter.evaluate(0.0, -radius - 0.01));
+        }
+
+        // Boxes keep their left edge but not their right one
+        let box_filter = BoxFilter::new(0.5);
+        assert_eq!(1.0, box_filter.evaluate(-0.5, -0.5));
+        assert_eq!(0.0, box_filter.evaluate(0.5, 0.0));
+        assert_eq!(0.0, box_filter.evaluate(0.0, 0.5));
+
+        // Negative lobes
+        let mitchell = MitchellFilter::new(2.0, 1.0 / 3.0, 1.0 / 3.0);
+        assert!(mitchell.evaluate(1.5, 0.0) < 0.0);
+        assert!(LanczosFilter::new(3.0).evaluate(1.5, 0.0) < 0.0);
+    }
+}
@@ -0,0 +1,351 @@
+use crate::adaptive::Estimate;
+use crate::color::Color;
+use crate::tile::Tile;
+use crate::tone_mapping::ToneMapping;
+
+use image::{DynamicImage, RgbImage};
+use std::io::{self, Read, Write};
+
+/// The samples gathered by a pixel: the sums of their weighted linear
+/// colors and of their weights
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Pixel {
+    sum: [f32; 3],
+    weight: f32,
+}
+
+impl Pixel {
+    /// Adds a sample of the given color and weight
+    pub fn add_sample(&mut self, color: Color, weight: f64) {
+        self.sum[0] += (weight * color.x()) as f32;
+        self.sum[1] += (weight * color.y()) as f32;
+        self.sum[2] += (weight * color.z()) as f32;
+        self.weight += weight as f32;
+    }
+
+    /// Adds the samples gathered by another pixel
+    pub fn merge(&mut self, other: &Pixel) {
+        for c in 0..3 {
+            self.sum[c] += other.sum[c];
+        }
+        self.weight += other.weight;
+    }
+
+    /// Encodes the pixel in little-endian bytes, for checkpoints
+    pub fn to_bytes(self) -> [u8; 16] {
+        let mut bytes = [0; 16];
+        for (chunk, value) in bytes
+            .chunks_exact_mut(4)
+            .zip(self.sum.iter().chain([&self.weight]))
+        {
+            chunk.copy_from_slice(&value.to_le_bytes());
+        }
+
+        bytes
+    }
+
+    /// Decodes a pixel encoded by `to_bytes`
+    pub fn from_bytes(bytes: [u8; 16]) -> Pixel {
+        let f32_at = |i: usize| f32::from_le_bytes(bytes[i..i + 4].try_into().unwrap());
+
+        Pixel {
+            sum: [f32_at(0), f32_at(4), f32_at(8)],
+            weight: f32_at(12),
+        }
+    }
+
+    /// Returns the weighted average of the samples, black without samples
+    pub fn color(&self) -> Color {
+        if self.weight <= 0.0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+
+        Color::new(
+            (self.sum[0] / self.weight) as f64,
+            (self.sum[1] / self.weight) as f64,
+            (self.sum[2] / self.weight) as f64,
+        )
+    }
+}
+
+/// The film of the camera: the samples gathered by every pixel of an image,
+/// row by row from the bottom, in linear RGB.
+///
+/// Threads render tiles in buffers of their own, which are merged here by
+/// the thread owning the framebuffer, so that no lock is needed. Images are
+/// derived from it once the rendering is over.
+#[derive(Clone)]
+pub struct Framebuffer {
+    width: u32,
+    height: u32,
+    pixels: Vec<Pixel>,
+    /// Samples taken in each pixel, whatever the pixels they were added
+    /// to, and the estimate of its error
+    estimates: Vec<Estimate>,
+}
+
+impl Framebuffer {
+    /// Constructs a new framebuffer without samples
+    pub fn new(width: u32, height: u32) -> Framebuffer {
+        Framebuffer {
+            width,
+            height,
+            pixels: vec![Pixel::default(); (width * height) as usize],
+            estimates: vec![Estimate::default(); (width * height) as usize],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Writes the size and the samples of the framebuffer, to be read back
+    /// by `read`
+    pub fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(&self.width.to_le_bytes())?;
+        writer.write_all(&self.height.to_le_bytes())?;
+        for (pixel, estimate) in self.pixels.iter().zip(&self.estimates) {
+            writer.write_all(&pixel.to_bytes())?;
+            writer.write_all(&estimate.to_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads a framebuffer written by `write`
+    pub fn read(reader: &mut impl Read) -> io::Result<Framebuffer> {
+        let mut size = [0; 8];
+        reader.read_exact(&mut size)?;
+        let width = u32::from_le_bytes(size[0..4].try_into().unwrap());
+        let height = u32::from_le_bytes(size[4..8].try_into().unwrap());
+        if width
+            .checked_mul(height)
+            .is_none_or(|count| count > 1 << 28)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "invalid framebuffer size",
+            ));
+        }
+
+        let mut framebuffer = Framebuffer::new(width, height);
+        for (pixel, estimate) in framebuffer
+            .pixels
+            .iter_mut()
+            .zip(&mut framebuffer.estimates)
+        {
+            let mut bytes = [0; 40];
+            reader.read_exact(&mut bytes)?;
+            *pixel = Pixel::from_bytes(bytes[..16].try_into().unwrap());
+            *estimate = Estimate::from_bytes(bytes[16..].try_into().unwrap());
+        }
+
+        Ok(framebuffer)
+    }
+
+    /// Returns the smallest number of samples taken in a pixel
+    pub fn min_samples(&self) -> u32 {
+        self.estimates
+            .iter()
+            .map(Estimate::count)
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// Adds the samples gathered by the pixels of a tile, given row by row
+    pub fn merge(&mut self, tile: &Tile, pixels: &[Pixel]) {
+        assert_eq!((tile.width * tile.height) as usize, pixels.len());
+
+        for (row, tile_row) in pixels.chunks_exact(tile.width as usize).enumerate() {
+            let start = ((tile.y + row as u32) * self.width + tile.x) as usize;
+            for (pixel, other) in self.pixels[start..].iter_mut().zip(tile_row) {
+                pixel.merge(other);
+            }
+        }
+    }
+
+    /// Returns the estimates of the pixels of a tile, row by row
+    pub fn estimates(&self, tile: &Tile) -> Vec<Estimate> {
+        (tile.y..tile.y + tile.height)
+            .flat_map(|y| {
+                let start = (y * self.width + tile.x) as usize;
+                self.estimates[start..start + tile.width as usize]
+                    .iter()
+                    .copied()
+            })
+            .collect()
+    }
+
+    /// Replaces the estimates of the pixels of a tile, given row by row, once
+    /// more samples have been taken in them
+    pub fn set_estimates(&mut self, tile: &Tile, estimates: &[Estimate]) {
+        assert_eq!((tile.width * tile.height) as usize, estimates.len());
+
+        for (row, tile_row) in estimates.chunks_exact(tile.width as usize).enumerate() {
+            let start = ((tile.y + row as u32) * self.width + tile.x) as usize;
+            self.estimates[start..start + tile.width as usize].copy_from_slice(tile_row);
+        }
+    }
+
+    /// Returns the linear color of the pixel at column `x` and row `y`
+    pub fn color(&self, x: u32, y: u32) -> Color {
+        self.pixels[(y * self.width + x) as usize].color()
+    }
+
+    /// Returns the sum of the weights of the samples of the pixel at column
+    /// `x` and row `y`
+    pub fn weight(&self, x: u32, y: u32) -> f32 {
+        self.pixels[(y * self.width + x) as usize].weight
+    }
+
+    /// Returns the number of samples taken in the pixel at column `x` and
+    /// row `y`
+    pub fn samples(&self, x: u32, y: u32) -> u32 {
+        self.estimates[(y * self.width + x) as usize].count()
+    }
+
+    /// Returns the number of samples taken in the whole image
+    pub fn total_samples(&self) -> u64 {
+        self.estimates
+            .iter()
+            .map(|estimate| estimate.count() as u64)
+            .sum()
+    }
+
+    /// Returns the average number of samples taken per pixel
+    pub fn samples_per_pixel(&self) -> f64 {
+        self.total_samples() as f64 / (self.width as f64 * self.height as f64)
+    }
+
+    /// Paints the pixels without samples of their own with a magenta
+    /// checkerboard, for renders stopped halfway to show what is missing
+    pub fn mark_unrendered(&mut self) {
+        for (k, (pixel, estimate)) in self.pixels.iter_mut().zip(&self.estimates).enumerate() {
+            if estimate.count() > 0 {
+                continue;
+            }
+
+            let (x, y) = (k as u32 % self.width, k as u32 / self.width);
+            let color = if (x / 8 + y / 8) % 2 == 0 {
+                Color::new(1.0, 0.0, 1.0)
+            } else {
+                Color::new(0.25, 0.0, 0.25)
+            };
+            *pixel = Pixel::default();
+            pixel.add_sample(color, 1.0);
+        }
+    }
+
+    /// Converts the framebuffer to an 8-bit sRGB image (upside down)
+    pub fn to_rgb8(&self, tone_mapping: &ToneMapping) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(self.width, self.height, |x, y| {
+            tone_mapping.encode(self.color(x, y)).to_rgb8()
+        }))
+    }
+
+    /// Converts the numbers of samples of the pixels to an 8-bit image
+    /// (upside down), from black for the fewest to light yellow for the
+    /// most on a logarithmic scale
+    pub fn to_heatmap(&self) -> DynamicImage {
+        // Stops of a colormap close to inferno
+        const STOPS: [[f64; 3]; 5] = [
+            [0.0, 0.0, 0.016],
+            [0.341, 0.063, 0.431],
+            [0.737, 0.216, 0.329],
+            [0.976, 0.557, 0.035],
+            [0.988, 1.0, 0.643],
+        ];
+
+        let counts = self.estimates.iter().map(Estimate::count);
+        let fewest = counts.clone().min().unwrap_or(0).max(1) as f64;
+        let most = counts.max().unwrap_or(0).max(1) as f64;
+        let range = (most / fewest).ln();
+
+        DynamicImage::ImageRgb8(RgbImage::from_fn(self.width, self.height, |x, y| {
+            let count = self.samples(x, y).max(1) as f64;
+            let t = if range > 0.0 {
+                (count / fewest).ln() / range * (STOPS.len() - 1) as f64
+            } else {
+                0.0
+            };
+
+            let stop = (t.floor() as usize).min(STOPS.len() - 2);
+            let (a, b, f) = (STOPS[stop], STOPS[stop + 1], t - stop as f64);
+            Color::new(
+                a[0] + f * (b[0] - a[0]),
+                a[1] + f * (b[1] - a[1]),
+                a[2] + f * (b[2] - a[2]),
+            )
+            .to_rgb8()
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn merge_works() {
+        let mut framebuffer = Framebuffer::new(4, 3);
+        let tile = Tile {
+            x: 2,
+            y: 1,
+            width: 2,
+            height: 2,
+        };
+
+        let mut pixel = Pixel::default();
+        pixel.add_sample(Color::new(1.0, 0.5, 4.0), 1.0);
+        pixel.add_sample(Color::new(0.0, 0.5, 1.0), 3.0);
+        framebuffer.merge(&tile, &[pixel; 4]);
+        framebuffer.merge(&tile, &[pixel; 4]);
+
+        assert_eq!(Color::new(0.0, 0.0, 0.0), framebuffer.color(1, 1));
+        assert_eq!(Color::new(0.25, 0.5, 1.75), framebuffer.color(2, 1));
+        assert_eq!(Color::new(0.25, 0.5, 1.75), framebuffer.color(3, 2));
+        assert_eq!(Color::new(0.0, 0.0, 0.0), framebuffer.color(3, 0));
+
+        let mut estimates = framebuffer.estimates(&tile);
+        for (k, estimate) in estimates.iter_mut().enumerate() {
+            for _ in 0..=k {
+                estimate.add(0.5);
+            }
+        }
+        framebuffer.set_estimates(&tile, &estimates);
+        assert_eq!(estimates, framebuffer.estimates(&tile));
+        assert_eq!(0, framebuffer.samples(1, 1));
+        assert_eq!(2, framebuffer.samples(3, 1));
+        assert_eq!(4, framebuffer.samples(3, 2));
+        assert_eq!(10, framebuffer.total_samples());
+        assert_eq!(10.0 / 12.0, framebuffer.samples_per_pixel());
+        assert_eq!(0, framebuffer.min_samples());
+    }
+
+    #[test]
+    fn mark_unrendered_works() {
+        let mut framebuffer = Framebuffer::new(10, 2);
+        let tile = Tile {
+            x: 0,
+            y: 0,
+            width: 1,
+            height: 1,
+        };
+        let mut pixel = Pixel::default();
+        pixel.add_sample(Color::new(0.5, 0.5, 0.5), 1.0);
+        framebuffer.merge(&tile, &[pixel]);
+        let mut estimates = framebuffer.estimates(&tile);
+        estimates[0].add(0.5);
+        framebuffer.set_estimates(&tile, &estimates);
+
+        framebuffer.mark_unrendered();
+        assert_eq!(Color::new(0.5, 0.5, 0.5), framebuffer.color(0, 0));
+        assert_eq!(Color::new(1.0, 0.0, 1.0), framebuffer.color(1, 1));
+        assert_eq!(Color::new(0.25, 0.0, 0.25), framebuffer.color(9, 1));
+        assert_eq!(1, framebuffer.total_samples());
+    }
+}
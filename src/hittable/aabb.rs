@@ -0,0 +1,89 @@
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+
+/// An axis-aligned bounding box
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Aabb {
+    pub min: Point3,
+    pub max: Point3,
+}
+
+impl Aabb {
+    /// Constructs a new bounding box with `min` and `max` two opposite corners
+    pub fn new(min: Point3, max: Point3) -> Aabb {
+        Aabb { min, max }
+    }
+
+    /// Returns the smallest box containing every given point
+    pub fn from_points(points: &[Point3]) -> Aabb {
+        let mut min = Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+        let mut max = -min;
+
+        for p in points {
+            min = Point3::new(min.x().min(p.x()), min.y().min(p.y()), min.z().min(p.z()));
+            max = Point3::new(max.x().max(p.x()), max.y().max(p.y()), max.z().max(p.z()));
+        }
+
+        Aabb { min, max }
+    }
+
+    /// Returns the bounding box of a disk of given `center`, `normal` and
+    /// `radius`
+    pub fn from_disk(center: Point3, normal: Vec3, radius: f64) -> Aabb {
+        let n = normal.normalized();
+        let extent = radius
+            * Vec3::new(
+                (1.0 - n.x() * n.x()).max(0.0).sqrt(),
+                (1.0 - n.y() * n.y()).max(0.0).sqrt(),
+                (1.0 - n.z() * n.z()).max(0.0).sqrt(),
+            );
+
+        Aabb::new(center - extent, center + extent).padded()
+    }
+
+    /// Returns the smallest box containing both boxes
+    pub fn surrounding(a: &Aabb, b: &Aabb) -> Aabb {
+        Aabb::from_points(&[a.min, a.max, b.min, b.max])
+    }
+
+    /// Returns the box slightly enlarged in every dimension, so that flat
+    /// objects do not have an empty bounding box
+    pub fn padded(&self) -> Aabb {
+        let delta = Vec3::new(1e-4, 1e-4, 1e-4);
+        Aabb::new(self.min - delta, self.max + delta)
+    }
+
+    /// Returns the range of the ray inside the box, if it goes through it
+    /// on the given range
+    pub fn intersect(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<(f64, f64)> {
+        let origin = [r.origin().x(), r.origin().y(), r.origin().z()];
+        let direction = [r.direction().x(), r.direction().y(), r.direction().z()];
+        let min = [self.min.x(), self.min.y(), self.min.z()];
+        let max = [self.max.x(), self.max.y(), self.max.z()];
+
+        let mut t_min = t_min;
+        let mut t_max = t_max;
+
+        for axis in 0..3 {
+            let inv_d = 1.0 / direction[axis];
+            let mut t0 = (min[axis] - origin[axis]) * inv_d;
+            let mut t1 = (max[axis] - origin[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            t_min = t_min.max(t0);
+            t_max = t_max.min(t1);
+            if t_max <= t_min {
+                return None;
+            }
+        }
+
+        Some((t_min, t_max))
+    }
+
+    /// Tells whether the ray goes through the box on the given range
+    pub fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.intersect(r, t_min, t_max).is_some()
+    }
+}
@@ -0,0 +1,123 @@
+use crate::hittable::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// A flat ring: a disk with a hole in its center
+pub struct Annulus {
+    center: Point3,
+    frame: Onb,
+    inner_radius: f64,
+    outer_radius: f64,
+    material: Arc<dyn Material + Sync + Send>,
+}
+
+impl Annulus {
+    /// Constructs a new ring between `inner_radius` and `outer_radius`,
+    /// facing the direction of `normal`
+    pub fn new(
+        center: Point3,
+        normal: Vec3,
+        inner_radius: f64,
+        outer_radius: f64,
+        material: Arc<dyn Material + Sync + Send>,
+    ) -> Annulus {
+        Annulus {
+            center,
+            frame: Onb::from_w(normal),
+            inner_radius,
+            outer_radius,
+            material,
+        }
+    }
+}
+
+impl Hittable for Annulus {
+    /// Tries to hit a ring object.
+    ///
+    /// Surface coordinates are the angle around the center and the position
+    /// between the inner and the outer circles.
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let o = self.frame.local(r.origin() - self.center);
+        let d = self.frame.local(r.direction());
+
+        if d.z() == 0.0 {
+            return None;
+        }
+
+        let t = -o.z() / d.z();
+        if t < t_min || t_max < t {
+            return None;
+        }
+
+        let p = o + t * d;
+        let distance = (p.x() * p.x() + p.y() * p.y()).sqrt();
+        if distance < self.inner_radius || distance > self.outer_radius {
+            return None;
+        }
+
+        let u = (p.y().atan2(p.x()) + PI) / (2.0 * PI);
+        let v = (distance - self.inner_radius) / (self.outer_radius - self.inner_radius);
+
+        Some(HitRecord::new(
+            r,
+            self.frame.w,
+            t,
+            (u, v),
+            Arc::clone(&self.material),
+        ))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::from_disk(
+            self.center,
+            self.frame.w,
+            self.outer_radius,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::color::Color;
+    use crate::material::lambertian::Lambertian;
+
+    /// A ring between 0.5 and 1.0 facing the Z axis
+    fn ring() -> Annulus {
+        let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        Annulus::new(Point3::zero(), Vec3::new(0.0, 0.0, 1.0), 0.5, 1.0, material)
+    }
+
+    #[test]
+    fn hit_works() {
+        let r = Ray::new(Point3::new(0.75, 0.0, 3.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_record = ring().hit(&r, 0.0, f64::INFINITY).unwrap();
+
+        assert!((hit_record.t - 3.0).abs() < 1e-9);
+        assert!((hit_record.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-9);
+        assert!(hit_record.front_face);
+        assert!((hit_record.u - 0.5).abs() < 1e-9);
+        assert!((hit_record.v - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn miss_works() {
+        // Through the hole, and beside the ring
+        for x in [0.25, 1.5] {
+            let r = Ray::new(Point3::new(x, 0.0, 3.0), Vec3::new(0.0, 0.0, -1.0));
+            assert!(ring().hit(&r, 0.0, f64::INFINITY).is_none());
+        }
+    }
+
+    #[test]
+    fn bounding_box_works() {
+        let bounding_box = ring().bounding_box().unwrap();
+        assert!((bounding_box.min - Point3::new(-1.0, -1.0, 0.0)).length() < 1e-3);
+        assert!((bounding_box.max - Point3::new(1.0, 1.0, 0.0)).length() < 1e-3);
+    }
+}
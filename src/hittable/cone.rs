@@ -0,0 +1,220 @@
+use crate::hittable::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// A truncated cone between two points (a lamp shade), optionally closed by
+/// disks at both ends. A zero radius at one end makes a regular cone.
+pub struct Cone {
+    base: Point3,
+    frame: Onb,
+    base_radius: f64,
+    top_radius: f64,
+    height: f64,
+    capped: bool,
+    material: Arc<dyn Material + Sync + Send>,
+}
+
+impl Cone {
+    /// Constructs a new cone going from `base` to `top`, with given radius
+    /// at both ends. If not `capped`, both ends are left open.
+    ///
+    /// # Panics
+    ///
+    /// The `new` function will panic if `base` and `top` are the same point.
+    pub fn new(
+        base: Point3,
+        top: Point3,
+        base_radius: f64,
+        top_radius: f64,
+        capped: bool,
+        material: Arc<dyn Material + Sync + Send>,
+    ) -> Cone {
+        let height = (top - base).length();
+        assert!(height > 0.0);
+
+        Cone {
+            base,
+            frame: Onb::from_w(top - base),
+            base_radius,
+            top_radius,
+            height,
+            capped,
+            material,
+        }
+    }
+
+    /// Returns the roots of the side of the cone along the ray given in the
+    /// frame of the cone, sorted by increasing `t`
+    fn side_roots(&self, o: Vec3, d: Vec3) -> Vec<f64> {
+        // The radius at height z is `base_radius + slope * z`
+        let slope = (self.top_radius - self.base_radius) / self.height;
+        let radius = self.base_radius + slope * o.z();
+
+        let a = d.x() * d.x() + d.y() * d.y() - slope * slope * d.z() * d.z();
+        let half_b = o.x() * d.x() + o.y() * d.y() - slope * d.z() * radius;
+        let c = o.x() * o.x() + o.y() * o.y() - radius * radius;
+
+        if a.abs() < 1e-12 {
+            return if half_b != 0.0 {
+                vec![-c / (2.0 * half_b)]
+            } else {
+                Vec::new()
+            };
+        }
+
+        let discriminant = half_b * half_b - a * c;
+        if discriminant < 0.0 {
+            return Vec::new();
+        }
+
+        let sqrtd = discriminant.sqrt();
+        let (t0, t1) = ((-half_b - sqrtd) / a, (-half_b + sqrtd) / a);
+        vec![t0.min(t1), t0.max(t1)]
+    }
+}
+
+impl Hittable for Cone {
+    /// Tries to hit a cone object.
+    ///
+    /// The ray is expressed in the frame of the cone, whose axis is Z.
+    /// Surface coordinates are the angle around the axis and the height on
+    /// the side, the angle and the distance to the axis on the caps.
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let o = self.frame.local(r.origin() - self.base);
+        let d = self.frame.local(r.direction());
+
+        let mut hit = None;
+        let mut closest_so_far = t_max;
+
+        // Side of the cone
+        let slope = (self.top_radius - self.base_radius) / self.height;
+        for root in self.side_roots(o, d) {
+            let p = o + root * d;
+            if t_min <= root && root <= closest_so_far && 0.0 <= p.z() && p.z() <= self.height {
+                let u = (p.y().atan2(p.x()) + PI) / (2.0 * PI);
+                let radius = self.base_radius + slope * p.z();
+                let normal = Vec3::new(p.x(), p.y(), -radius * slope).normalized();
+                closest_so_far = root;
+                hit = Some((root, normal, (u, p.z() / self.height)));
+                break;
+            }
+        }
+
+        // Caps
+        if self.capped && d.z() != 0.0 {
+            let caps = [
+                (0.0, -1.0, self.base_radius),
+                (self.height, 1.0, self.top_radius),
+            ];
+            for (z, normal, radius) in caps {
+                let root = (z - o.z()) / d.z();
+                let p = o + root * d;
+                let distance = (p.x() * p.x() + p.y() * p.y()).sqrt();
+                if t_min <= root && root <= closest_so_far && distance < radius {
+                    let u = (p.y().atan2(p.x()) + PI) / (2.0 * PI);
+                    closest_so_far = root;
+                    hit = Some((root, Vec3::new(0.0, 0.0, normal), (u, distance / radius)));
+                }
+            }
+        }
+
+        hit.map(|(t, normal, uv)| {
+            HitRecord::new(
+                r,
+                self.frame.world(normal),
+                t,
+                uv,
+                Arc::clone(&self.material),
+            )
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let top = self.base + self.height * self.frame.w;
+        Some(Aabb::surrounding(
+            &Aabb::from_disk(self.base, self.frame.w, self.base_radius),
+            &Aabb::from_disk(top, self.frame.w, self.top_radius),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::color::Color;
+    use crate::material::lambertian::Lambertian;
+
+    /// A cone along the Z axis, from 0.0 to 1.0, with given radii
+    fn shade(base_radius: f64, top_radius: f64) -> Cone {
+        let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        Cone::new(
+            Point3::zero(),
+            Point3::new(0.0, 0.0, 1.0),
+            base_radius,
+            top_radius,
+            true,
+            material,
+        )
+    }
+
+    #[test]
+    fn side_hit_works() {
+        // Halfway up, the radius is 0.5 and the side at 45 degrees
+        let r = Ray::new(Point3::new(5.0, 0.0, 0.5), Vec3::new(-1.0, 0.0, 0.0));
+        let hit_record = shade(1.0, 0.0).hit(&r, 0.0, f64::INFINITY).unwrap();
+
+        let normal = Vec3::new(1.0, 0.0, 1.0).normalized();
+        assert!((hit_record.t - 4.5).abs() < 1e-9);
+        assert!((hit_record.normal - normal).length() < 1e-9);
+        assert!(hit_record.front_face);
+        assert!((hit_record.u - 0.5).abs() < 1e-9);
+        assert!((hit_record.v - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn cap_hit_works() {
+        let r = Ray::new(Point3::new(0.5, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0));
+        let hit_record = shade(1.0, 0.0).hit(&r, 0.0, f64::INFINITY).unwrap();
+
+        assert!((hit_record.t - 5.0).abs() < 1e-9);
+        assert!((hit_record.normal - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-9);
+        assert!(hit_record.front_face);
+        assert!((hit_record.u - 0.5).abs() < 1e-9);
+        assert!((hit_record.v - 0.5).abs() < 1e-9);
+
+        // The top of a truncated cone
+        let r = Ray::new(Point3::new(0.0, 0.25, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_record = shade(1.0, 0.5).hit(&r, 0.0, f64::INFINITY).unwrap();
+
+        assert!((hit_record.t - 4.0).abs() < 1e-9);
+        assert!((hit_record.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-9);
+        assert!((hit_record.u - 0.75).abs() < 1e-9);
+        assert!((hit_record.v - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn miss_works() {
+        // Beside the apex, where the radius is 0.1
+        let r = Ray::new(Point3::new(5.0, 0.2, 0.9), Vec3::new(-1.0, 0.0, 0.0));
+        assert!(shade(1.0, 0.0).hit(&r, 0.0, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn bounding_box_works() {
+        let bounding_box = shade(1.0, 0.5).bounding_box().unwrap();
+        assert!((bounding_box.min - Point3::new(-1.0, -1.0, 0.0)).length() < 1e-3);
+        assert!((bounding_box.max - Point3::new(1.0, 1.0, 1.0)).length() < 1e-3);
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_height_fails() {
+        let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        Cone::new(Point3::zero(), Point3::zero(), 1.0, 0.5, true, material);
+    }
+}
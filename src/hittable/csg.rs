@@ -0,0 +1,242 @@
+use std::sync::Arc;
+
+use crate::hittable::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::ray::Ray;
+
+/// Boolean operation applied by a [`Csg`] node
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CsgOperation {
+    /// Points inside either solid
+    Union,
+    /// Points inside both solids
+    Intersection,
+    /// Points inside the left solid but not inside the right one
+    Difference,
+}
+
+impl CsgOperation {
+    /// Tells whether a point is inside the combined solid given whether it
+    /// is inside the left and the right solids
+    fn contains(&self, in_left: bool, in_right: bool) -> bool {
+        match self {
+            CsgOperation::Union => in_left || in_right,
+            CsgOperation::Intersection => in_left && in_right,
+            CsgOperation::Difference => in_left && !in_right,
+        }
+    }
+}
+
+/// Constructive solid geometry: combination of two closed objects.
+///
+/// Both objects report all their intersections with the ray, which are
+/// walked in order while tracking whether the ray is inside each of them.
+/// The combined surface is where the result of the operation changes.
+pub struct Csg {
+    pub operation: CsgOperation,
+    pub left: Arc<dyn Hittable + Sync + Send>,
+    pub right: Arc<dyn Hittable + Sync + Send>,
+}
+
+impl Csg {
+    pub fn new(
+        operation: CsgOperation,
+        left: Arc<dyn Hittable + Sync + Send>,
+        right: Arc<dyn Hittable + Sync + Send>,
+    ) -> Csg {
+        Csg {
+            operation,
+            left,
+            right,
+        }
+    }
+
+    /// Walks the intersections of both objects in order, giving `boundary`
+    /// each one where the ray enters or leaves the combined object, until it
+    /// returns `false`
+    fn walk(&self, r: &Ray, mut boundary: impl FnMut(HitRecord) -> bool) {
+        let mut in_left = false;
+        let mut in_right = false;
+        let mut inside = self.operation.contains(in_left, in_right);
+
+        let mut left = self.left.hit_all(r).into_iter().peekable();
+        let mut right = self.right.hit_all(r).into_iter().peekable();
+
+        loop {
+            let from_left = match (left.peek(), right.peek()) {
+                (None, None) => break,
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (Some(a), Some(b)) => a.t <= b.t,
+            };
+
+            let hit_record = if from_left {
+                in_left = !in_left;
+                left.next().unwrap()
+            } else {
+                in_right = !in_right;
+                right.next().unwrap()
+            };
+
+            let now_inside = self.operation.contains(in_left, in_right);
+            if now_inside == inside {
+                continue;
+            }
+            inside = now_inside;
+
+            // Surfaces of the subtracted object are seen from the inside
+            let mut outward_normal = hit_record.outward_normal();
+            if !from_left && self.operation == CsgOperation::Difference {
+                outward_normal = -outward_normal;
+            }
+
+            let mut combined_record = HitRecord::new(
+                r,
+                outward_normal,
+                hit_record.t,
+                (hit_record.u, hit_record.v),
+                hit_record.material,
+            );
+            combined_record.vertex_color = hit_record.vertex_color;
+            if !boundary(combined_record) {
+                break;
+            }
+        }
+    }
+}
+
+impl Hittable for Csg {
+    /// Tries to hit the combined object. The intersections are walked up to
+    /// the first one in range only.
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        if let Some(bounding_box) = self.bounding_box() {
+            if !bounding_box.hit(r, t_min, t_max) {
+                return None;
+            }
+        }
+
+        let mut closest = None;
+        self.walk(r, |hit_record| {
+            if hit_record.t > t_max {
+                return false;
+            }
+            if hit_record.t < t_min {
+                return true;
+            }
+            closest = Some(hit_record);
+            false
+        });
+
+        closest
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        match self.operation {
+            CsgOperation::Union => Some(Aabb::surrounding(
+                &self.left.bounding_box()?,
+                &self.right.bounding_box()?,
+            )),
+            CsgOperation::Intersection => self
+                .left
+                .bounding_box()
+                .or_else(|| self.right.bounding_box()),
+            CsgOperation::Difference => self.left.bounding_box(),
+        }
+    }
+
+    /// Returns the intersections where the ray enters or leaves the
+    /// combined object
+    fn hit_all(&self, r: &Ray) -> Vec<HitRecord> {
+        let mut records = Vec::new();
+        self.walk(r, |hit_record| {
+            records.push(hit_record);
+            true
+        });
+
+        records
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::color::Color;
+    use crate::hittable::sphere::Sphere;
+    use crate::material::lambertian::Lambertian;
+    use crate::vec3::{Point3, Vec3};
+
+    fn sphere(x: f64) -> Arc<dyn Hittable + Sync + Send> {
+        let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        Arc::new(Sphere::new(Point3::new(x, 0.0, 0.0), 1.0, material))
+    }
+
+    fn hits(operation: CsgOperation) -> Vec<(f64, bool)> {
+        let csg = Csg::new(operation, sphere(0.0), sphere(1.0));
+        let r = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+
+        csg.hit_all(&r)
+            .iter()
+            .map(|hit_record| (hit_record.t, hit_record.front_face))
+            .collect()
+    }
+
+    #[test]
+    fn union_works() {
+        assert_eq!(vec![(4.0, true), (7.0, false)], hits(CsgOperation::Union));
+    }
+
+    #[test]
+    fn intersection_works() {
+        assert_eq!(
+            vec![(5.0, true), (6.0, false)],
+            hits(CsgOperation::Intersection)
+        );
+    }
+
+    #[test]
+    fn difference_works() {
+        assert_eq!(
+            vec![(4.0, true), (5.0, false)],
+            hits(CsgOperation::Difference)
+        );
+    }
+
+    /// A sphere whose hits have a vertex color, like the ones of meshes
+    struct ColoredSphere(Sphere);
+
+    impl Hittable for ColoredSphere {
+        fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+            let mut hit_record = self.0.hit(r, t_min, t_max)?;
+            hit_record.vertex_color = Some(Color::new(1.0, 0.0, 0.0));
+            Some(hit_record)
+        }
+
+        fn bounding_box(&self) -> Option<Aabb> {
+            self.0.bounding_box()
+        }
+    }
+
+    #[test]
+    fn vertex_color_works() {
+        let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let colored = ColoredSphere(Sphere::new(Point3::zero(), 1.0, material));
+        let csg = Csg::new(CsgOperation::Union, Arc::new(colored), sphere(1.0));
+        let r = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+
+        let hit_record = csg.hit(&r, 0.0, f64::INFINITY).unwrap();
+        assert_eq!(Some(Color::new(1.0, 0.0, 0.0)), hit_record.vertex_color);
+        let hit_record = csg.hit(&r, 4.5, f64::INFINITY).unwrap();
+        assert_eq!(None, hit_record.vertex_color);
+    }
+
+    #[test]
+    fn hit_respects_range() {
+        let csg = Csg::new(CsgOperation::Difference, sphere(0.0), sphere(1.0));
+        let r = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+
+        assert_eq!(5.0, csg.hit(&r, 4.5, f64::INFINITY).unwrap().t);
+        assert!(csg.hit(&r, 5.5, f64::INFINITY).is_none());
+        assert!(csg.hit(&r, 0.0, 3.5).is_none());
+        assert_eq!(4.0, csg.hit(&r, 0.0, 4.5).unwrap().t);
+    }
+}
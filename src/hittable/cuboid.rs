@@ -0,0 +1,104 @@
+use crate::hittable::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+use std::sync::Arc;
+
+/// An axis-aligned box
+pub struct Cuboid {
+    pub min: Point3,
+    pub max: Point3,
+    pub material: Arc<dyn Material + Sync + Send>,
+}
+
+impl Cuboid {
+    /// Constructs a new box with `p0` and `p1` two opposite corners
+    pub fn new(p0: Point3, p1: Point3, material: Arc<dyn Material + Sync + Send>) -> Cuboid {
+        Cuboid {
+            min: Point3::new(p0.x().min(p1.x()), p0.y().min(p1.y()), p0.z().min(p1.z())),
+            max: Point3::new(p0.x().max(p1.x()), p0.y().max(p1.y()), p0.z().max(p1.z())),
+            material,
+        }
+    }
+
+    /// Returns the entry and exit distances of the ray through the box, along
+    /// with the outward normals of the faces hit
+    fn slabs(&self, r: &Ray) -> Option<((f64, Vec3), (f64, Vec3))> {
+        let origin = [r.origin().x(), r.origin().y(), r.origin().z()];
+        let direction = [r.direction().x(), r.direction().y(), r.direction().z()];
+        let min = [self.min.x(), self.min.y(), self.min.z()];
+        let max = [self.max.x(), self.max.y(), self.max.z()];
+
+        let mut entry = (f64::NEG_INFINITY, Vec3::zero());
+        let mut exit = (f64::INFINITY, Vec3::zero());
+
+        for axis in 0..3 {
+            let inv_d = 1.0 / direction[axis];
+            let mut t0 = (min[axis] - origin[axis]) * inv_d;
+            let mut t1 = (max[axis] - origin[axis]) * inv_d;
+            let mut normal = [0.0; 3];
+            normal[axis] = -1.0;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+                normal[axis] = 1.0;
+            }
+            let normal = Vec3::new(normal[0], normal[1], normal[2]);
+
+            if t0 > entry.0 {
+                entry = (t0, normal);
+            }
+            if t1 < exit.0 {
+                exit = (t1, -normal);
+            }
+            if exit.0 <= entry.0 {
+                return None;
+            }
+        }
+
+        Some((entry, exit))
+    }
+
+    /// Returns the record of the hit at `t` along the ray on the face of
+    /// outward normal `normal`. Surface coordinates span each face.
+    fn record(&self, r: &Ray, t: f64, normal: Vec3) -> HitRecord {
+        let p = r.at(t) - self.min;
+        let size = self.max - self.min;
+        let (a, b) = if normal.x() != 0.0 {
+            (p.z() / size.z(), p.y() / size.y())
+        } else if normal.y() != 0.0 {
+            (p.x() / size.x(), p.z() / size.z())
+        } else {
+            (p.x() / size.x(), p.y() / size.y())
+        };
+
+        HitRecord::new(r, normal, t, (a, b), Arc::clone(&self.material))
+    }
+}
+
+impl Hittable for Cuboid {
+    /// Tries to hit a box object.
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (entry, exit) = self.slabs(r)?;
+
+        [entry, exit]
+            .into_iter()
+            .find(|(t, _)| t_min <= *t && *t <= t_max)
+            .map(|(t, normal)| self.record(r, t, normal))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::new(self.min, self.max))
+    }
+
+    /// Returns the entry and exit intersections of the ray with the box
+    fn hit_all(&self, r: &Ray) -> Vec<HitRecord> {
+        match self.slabs(r) {
+            None => Vec::new(),
+            Some((entry, exit)) => [entry, exit]
+                .into_iter()
+                .map(|(t, normal)| self.record(r, t, normal))
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use crate::hittable::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// A cylinder between two points, optionally closed by disks at both ends
+pub struct Cylinder {
+    base: Point3,
+    frame: Onb,
+    radius: f64,
+    height: f64,
+    capped: bool,
+    material: Arc<dyn Material + Sync + Send>,
+}
+
+impl Cylinder {
+    /// Constructs a new cylinder of given `radius` going from `base` to `top`.
+    /// If not `capped`, both ends are left open (like a pipe).
+    ///
+    /// # Panics
+    ///
+    /// The `new` function will panic if `base` and `top` are the same point.
+    pub fn new(
+        base: Point3,
+        top: Point3,
+        radius: f64,
+        capped: bool,
+        material: Arc<dyn Material + Sync + Send>,
+    ) -> Cylinder {
+        let height = (top - base).length();
+        assert!(height > 0.0);
+
+        Cylinder {
+            base,
+            frame: Onb::from_w(top - base),
+            radius,
+            height,
+            capped,
+            material,
+        }
+    }
+}
+
+impl Hittable for Cylinder {
+    /// Tries to hit a cylinder object.
+    ///
+    /// The ray is expressed in the frame of the cylinder, whose axis is Z.
+    /// Surface coordinates are the angle around the axis and the height on
+    /// the side, the angle and the distance to the axis on the caps.
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let o = self.frame.local(r.origin() - self.base);
+        let d = self.frame.local(r.direction());
+
+        let mut hit = None;
+        let mut closest_so_far = t_max;
+
+        // Side of the cylinder
+        let a = d.x() * d.x() + d.y() * d.y();
+        let half_b = o.x() * d.x() + o.y() * d.y();
+        let c = o.x() * o.x() + o.y() * o.y() - self.radius * self.radius;
+        let discriminant = half_b * half_b - a * c;
+
+        if a > 0.0 && discriminant >= 0.0 {
+            let sqrtd = discriminant.sqrt();
+            for root in [(-half_b - sqrtd) / a, (-half_b + sqrtd) / a] {
+                let p = o + root * d;
+                if t_min <= root && root <= closest_so_far && 0.0 <= p.z() && p.z() <= self.height {
+                    let u = (p.y().atan2(p.x()) + PI) / (2.0 * PI);
+                    let normal = Vec3::new(p.x(), p.y(), 0.0) / self.radius;
+                    closest_so_far = root;
+                    hit = Some((root, normal, (u, p.z() / self.height)));
+                    break;
+                }
+            }
+        }
+
+        // Caps
+        if self.capped && d.z() != 0.0 {
+            for (z, normal) in [(0.0, -1.0), (self.height, 1.0)] {
+                let root = (z - o.z()) / d.z();
+                let p = o + root * d;
+                let distance = (p.x() * p.x() + p.y() * p.y()).sqrt();
+                if t_min <= root && root <= closest_so_far && distance <= self.radius {
+                    let u = (p.y().atan2(p.x()) + PI) / (2.0 * PI);
+                    closest_so_far = root;
+                    hit = Some((
+                        root,
+                        Vec3::new(0.0, 0.0, normal),
+                        (u, distance / self.radius),
+                    ));
+                }
+            }
+        }
+
+        hit.map(|(t, normal, uv)| {
+            HitRecord::new(
+                r,
+                self.frame.world(normal),
+                t,
+                uv,
+                Arc::clone(&self.material),
+            )
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let top = self.base + self.height * self.frame.w;
+        Some(Aabb::surrounding(
+            &Aabb::from_disk(self.base, self.frame.w, self.radius),
+            &Aabb::from_disk(top, self.frame.w, self.radius),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::color::Color;
+    use crate::material::lambertian::Lambertian;
+
+    /// A cylinder of radius 1.0 along the Z axis, from 0.0 to 2.0
+    fn pipe(capped: bool) -> Cylinder {
+        let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        Cylinder::new(
+            Point3::zero(),
+            Point3::new(0.0, 0.0, 2.0),
+            1.0,
+            capped,
+            material,
+        )
+    }
+
+    #[test]
+    fn side_hit_works() {
+        let r = Ray::new(Point3::new(5.0, 0.0, 1.0), Vec3::new(-1.0, 0.0, 0.0));
+        let hit_record = pipe(true).hit(&r, 0.0, f64::INFINITY).unwrap();
+
+        assert!((hit_record.t - 4.0).abs() < 1e-9);
+        assert!((hit_record.normal - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-9);
+        assert!(hit_record.front_face);
+        assert!((hit_record.u - 0.5).abs() < 1e-9);
+        assert!((hit_record.v - 0.5).abs() < 1e-9);
+
+        // From inside, the side is seen from the back
+        let r = Ray::new(Point3::new(0.0, 0.0, 1.5), Vec3::new(0.0, 1.0, 0.0));
+        let hit_record = pipe(true).hit(&r, 0.0, f64::INFINITY).unwrap();
+
+        assert!((hit_record.t - 1.0).abs() < 1e-9);
+        assert!(!hit_record.front_face);
+        assert!((hit_record.outward_normal() - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-9);
+        assert!((hit_record.u - 0.75).abs() < 1e-9);
+        assert!((hit_record.v - 0.75).abs() < 1e-9);
+    }
+
+    #[test]
+    fn cap_hit_works() {
+        let r = Ray::new(Point3::new(0.5, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_record = pipe(true).hit(&r, 0.0, f64::INFINITY).unwrap();
+
+        assert!((hit_record.t - 3.0).abs() < 1e-9);
+        assert!((hit_record.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-9);
+        assert!(hit_record.front_face);
+        assert!((hit_record.u - 0.5).abs() < 1e-9);
+        assert!((hit_record.v - 0.5).abs() < 1e-9);
+
+        // Without caps, the ray goes through
+        assert!(pipe(false).hit(&r, 0.0, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn bounding_box_works() {
+        let bounding_box = pipe(true).bounding_box().unwrap();
+        assert!((bounding_box.min - Point3::new(-1.0, -1.0, 0.0)).length() < 1e-3);
+        assert!((bounding_box.max - Point3::new(1.0, 1.0, 2.0)).length() < 1e-3);
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_height_fails() {
+        let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        Cylinder::new(Point3::zero(), Point3::zero(), 1.0, true, material);
+    }
+}
@@ -0,0 +1,123 @@
+use crate::hittable::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// A flat disk
+pub struct Disk {
+    center: Point3,
+    frame: Onb,
+    radius: f64,
+    material: Arc<dyn Material + Sync + Send>,
+}
+
+impl Disk {
+    /// Constructs a new disk of given `radius`, facing the direction of
+    /// `normal`
+    pub fn new(
+        center: Point3,
+        normal: Vec3,
+        radius: f64,
+        material: Arc<dyn Material + Sync + Send>,
+    ) -> Disk {
+        Disk {
+            center,
+            frame: Onb::from_w(normal),
+            radius,
+            material,
+        }
+    }
+}
+
+impl Hittable for Disk {
+    /// Tries to hit a disk object.
+    ///
+    /// Surface coordinates are the angle around the center and the distance
+    /// to the center.
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let o = self.frame.local(r.origin() - self.center);
+        let d = self.frame.local(r.direction());
+
+        if d.z() == 0.0 {
+            return None;
+        }
+
+        let t = -o.z() / d.z();
+        if t < t_min || t_max < t {
+            return None;
+        }
+
+        let p = o + t * d;
+        let distance = (p.x() * p.x() + p.y() * p.y()).sqrt();
+        if distance > self.radius {
+            return None;
+        }
+
+        let u = (p.y().atan2(p.x()) + PI) / (2.0 * PI);
+
+        Some(HitRecord::new(
+            r,
+            self.frame.w,
+            t,
+            (u, distance / self.radius),
+            Arc::clone(&self.material),
+        ))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::from_disk(self.center, self.frame.w, self.radius))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::color::Color;
+    use crate::material::lambertian::Lambertian;
+
+    /// A disk of radius 1.0 facing the Z axis
+    fn disk() -> Disk {
+        let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        Disk::new(Point3::zero(), Vec3::new(0.0, 0.0, 1.0), 1.0, material)
+    }
+
+    #[test]
+    fn hit_works() {
+        let r = Ray::new(Point3::new(0.0, 0.5, 3.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_record = disk().hit(&r, 0.0, f64::INFINITY).unwrap();
+
+        assert!((hit_record.t - 3.0).abs() < 1e-9);
+        assert!((hit_record.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-9);
+        assert!(hit_record.front_face);
+        assert!((hit_record.u - 0.75).abs() < 1e-9);
+        assert!((hit_record.v - 0.5).abs() < 1e-9);
+
+        // From below
+        let r = Ray::new(Point3::new(0.5, 0.0, -3.0), Vec3::new(0.0, 0.0, 1.0));
+        let hit_record = disk().hit(&r, 0.0, f64::INFINITY).unwrap();
+        assert!(!hit_record.front_face);
+        assert!((hit_record.u - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn miss_works() {
+        let r = Ray::new(Point3::new(1.5, 0.0, 3.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(disk().hit(&r, 0.0, f64::INFINITY).is_none());
+
+        // Along the plane of the disk
+        let r = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(disk().hit(&r, 0.0, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn bounding_box_works() {
+        let bounding_box = disk().bounding_box().unwrap();
+        assert!((bounding_box.min - Point3::new(-1.0, -1.0, 0.0)).length() < 1e-3);
+        assert!((bounding_box.max - Point3::new(1.0, 1.0, 0.0)).length() < 1e-3);
+        assert!(bounding_box.min.z() < bounding_box.max.z());
+    }
+}
@@ -0,0 +1,280 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::hittable::aabb::Aabb;
+use crate::hittable::triangle_mesh::intersect_triangle;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+
+use image::ImageResult;
+
+/// A terrain given by a grid of heights.
+///
+/// Each cell of the grid is made of two triangles, whose normals are
+/// interpolated from the normals at the samples. Rays walk through the
+/// cells they cross (DDA), so that only a few triangles are tested.
+pub struct Heightfield {
+    heights: Vec<f64>,
+    normals: Vec<Vec3>,
+    width: usize,
+    depth: usize,
+    origin: Point3,
+    size: Vec3,
+    bounding_box: Aabb,
+    material: Arc<dyn Material + Sync + Send>,
+}
+
+impl Heightfield {
+    /// Constructs a new heightfield from rows of heights in [0.0, 1.0].
+    /// Rows go along the Z axis and samples of a row along the X axis.
+    ///
+    /// The terrain lies in the box of corner `origin` and of given `size`:
+    /// the grid spans `size.x()` and `size.z()`, and a height of 1.0 is
+    /// `size.y()` above the origin.
+    ///
+    /// # Panics
+    ///
+    /// The `new` function will panic if there are less than two rows or two
+    /// samples by row, or if rows are not all the same length.
+    pub fn new(
+        heights: Vec<Vec<f64>>,
+        origin: Point3,
+        size: Vec3,
+        material: Arc<dyn Material + Sync + Send>,
+    ) -> Heightfield {
+        let depth = heights.len();
+        assert!(depth >= 2);
+        let width = heights[0].len();
+        assert!(width >= 2);
+        assert!(heights.iter().all(|row| row.len() == width));
+
+        let heights: Vec<f64> = heights.into_iter().flatten().collect();
+
+        let (min, max) = heights
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), h| {
+                (min.min(*h), max.max(*h))
+            });
+        let bounding_box = Aabb::new(
+            Point3::new(origin.x(), origin.y() + min * size.y(), origin.z()),
+            Point3::new(
+                origin.x() + size.x(),
+                origin.y() + max * size.y(),
+                origin.z() + size.z(),
+            ),
+        )
+        .padded();
+
+        let mut heightfield = Heightfield {
+            heights,
+            normals: Vec::new(),
+            width,
+            depth,
+            origin,
+            size,
+            bounding_box,
+            material,
+        };
+        heightfield.normals = heightfield.compute_normals();
+
+        heightfield
+    }
+
+    /// Constructs a new heightfield from a grayscale image: black is a
+    /// height of 0.0 and white a height of 1.0 (see [`Heightfield::new`])
+    pub fn from_image(
+        path: &Path,
+        origin: Point3,
+        size: Vec3,
+        material: Arc<dyn Material + Sync + Send>,
+    ) -> ImageResult<Heightfield> {
+        let img = image::open(path)?.to_luma8();
+
+        let heights = img
+            .rows()
+            .map(|row| row.map(|pixel| pixel.0[0] as f64 / 255.0).collect())
+            .collect();
+
+        Ok(Heightfield::new(heights, origin, size, material))
+    }
+
+    /// Returns the height of the sample at column `i` and row `j`
+    fn height(&self, i: usize, j: usize) -> f64 {
+        self.heights[j * self.width + i]
+    }
+
+    /// Returns the position of the sample at column `i` and row `j`
+    fn vertex(&self, i: usize, j: usize) -> Point3 {
+        self.origin
+            + Vec3::new(
+                i as f64 / (self.width - 1) as f64 * self.size.x(),
+                self.height(i, j) * self.size.y(),
+                j as f64 / (self.depth - 1) as f64 * self.size.z(),
+            )
+    }
+
+    /// Computes the normal at each sample from the slopes between its
+    /// neighbours
+    fn compute_normals(&self) -> Vec<Vec3> {
+        let dx = self.size.x() / (self.width - 1) as f64;
+        let dz = self.size.z() / (self.depth - 1) as f64;
+
+        let mut normals = Vec::with_capacity(self.heights.len());
+        for j in 0..self.depth {
+            for i in 0..self.width {
+                let (i0, i1) = (i.saturating_sub(1), (i + 1).min(self.width - 1));
+                let (j0, j1) = (j.saturating_sub(1), (j + 1).min(self.depth - 1));
+
+                let slope_x = (self.height(i1, j) - self.height(i0, j)) * self.size.y()
+                    / ((i1 - i0) as f64 * dx);
+                let slope_z = (self.height(i, j1) - self.height(i, j0)) * self.size.y()
+                    / ((j1 - j0) as f64 * dz);
+
+                normals.push(Vec3::new(-slope_x, 1.0, -slope_z).normalized());
+            }
+        }
+
+        normals
+    }
+
+    /// Tries to hit the two triangles of the cell at column `i` and row `j`
+    fn hit_cell(&self, r: &Ray, i: usize, j: usize, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let corners = [(i, j), (i + 1, j), (i + 1, j + 1), (i, j + 1)];
+        let triangles = [
+            [corners[0], corners[1], corners[2]],
+            [corners[0], corners[2], corners[3]],
+        ];
+
+        let mut hit = None;
+        let mut closest_so_far = t_max;
+
+        for triangle in triangles {
+            let [p0, p1, p2] = triangle.map(|(i, j)| self.vertex(i, j));
+            if let Some((t, b1, b2)) = intersect_triangle(r, p0, p1, p2, t_min, closest_so_far) {
+                closest_so_far = t;
+                hit = Some((t, triangle, b1, b2));
+            }
+        }
+
+        let (t, triangle, b1, b2) = hit?;
+        let [n0, n1, n2] = triangle.map(|(i, j)| self.normals[j * self.width + i]);
+        let normal = ((1.0 - b1 - b2) * n0 + b1 * n1 + b2 * n2).normalized();
+
+        let p = r.at(t) - self.origin;
+        let uv = (p.x() / self.size.x(), p.z() / self.size.z());
+
+        Some(HitRecord::new(r, normal, t, uv, Arc::clone(&self.material)))
+    }
+}
+
+impl Hittable for Heightfield {
+    /// Tries to hit the terrain, walking through the cells crossed by the
+    /// ray from the nearest one.
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (start, end) = self.bounding_box.intersect(r, t_min, t_max)?;
+
+        // Work in grid coordinates: one unit per cell
+        let cells_x = (self.width - 1) as f64;
+        let cells_z = (self.depth - 1) as f64;
+        let scale_x = cells_x / self.size.x();
+        let scale_z = cells_z / self.size.z();
+
+        let p = r.at(start) - self.origin;
+        let (gx, gz) = (p.x() * scale_x, p.z() * scale_z);
+        let (dx, dz) = (r.direction().x() * scale_x, r.direction().z() * scale_z);
+
+        let mut i = (gx.floor().max(0.0) as usize).min(self.width - 2);
+        let mut j = (gz.floor().max(0.0) as usize).min(self.depth - 2);
+
+        // Distance along the ray to the next cell boundary on each axis, and
+        // between two boundaries
+        let next_boundary = |g: f64, d: f64, cell: usize| -> (f64, f64) {
+            if d > 0.0 {
+                (start + (cell as f64 + 1.0 - g) / d, 1.0 / d)
+            } else if d < 0.0 {
+                (start + (cell as f64 - g) / d, -1.0 / d)
+            } else {
+                (f64::INFINITY, f64::INFINITY)
+            }
+        };
+        let (mut t_next_x, t_delta_x) = next_boundary(gx, dx, i);
+        let (mut t_next_z, t_delta_z) = next_boundary(gz, dz, j);
+
+        loop {
+            if let Some(hit_record) = self.hit_cell(r, i, j, t_min, t_max) {
+                return Some(hit_record);
+            }
+
+            if t_next_x < t_next_z {
+                if t_next_x > end {
+                    return None;
+                }
+                t_next_x += t_delta_x;
+                if dx > 0.0 && i + 2 < self.width {
+                    i += 1;
+                } else if dx < 0.0 && i > 0 {
+                    i -= 1;
+                } else {
+                    return None;
+                }
+            } else {
+                if t_next_z > end {
+                    return None;
+                }
+                t_next_z += t_delta_z;
+                if dz > 0.0 && j + 2 < self.depth {
+                    j += 1;
+                } else if dz < 0.0 && j > 0 {
+                    j -= 1;
+                } else {
+                    return None;
+                }
+            }
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.bounding_box)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::color::Color;
+    use crate::material::lambertian::Lambertian;
+
+    fn ramp() -> Heightfield {
+        // Goes up along the X axis, from 0.0 to 2.0
+        let heights = vec![vec![0.0, 0.5, 1.0]; 3];
+        let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        Heightfield::new(heights, Point3::zero(), Vec3::new(2.0, 2.0, 2.0), material)
+    }
+
+    #[test]
+    fn vertical_ray_hits_the_slope() {
+        let r = Ray::new(Point3::new(0.5, 5.0, 1.5), Vec3::new(0.0, -1.0, 0.0));
+        let hit_record = ramp().hit(&r, 0.0, f64::INFINITY).unwrap();
+
+        assert!((hit_record.t - 4.5).abs() < 1e-9);
+        assert!((hit_record.u - 0.25).abs() < 1e-9);
+        assert!((hit_record.v - 0.75).abs() < 1e-9);
+        assert!(hit_record.front_face);
+    }
+
+    #[test]
+    fn grazing_ray_walks_through_cells() {
+        let r = Ray::new(Point3::new(-1.0, 0.9, 0.3), Vec3::new(1.0, 0.0, 0.1));
+        let hit_record = ramp().hit(&r, 0.0, f64::INFINITY).unwrap();
+
+        assert!((hit_record.intersection.x() - 0.9).abs() < 1e-9);
+    }
+
+    #[test]
+    fn ray_above_misses() {
+        let r = Ray::new(Point3::new(-1.0, 2.5, 1.0), Vec3::new(1.0, 0.0, 0.0));
+        assert!(ramp().hit(&r, 0.0, f64::INFINITY).is_none());
+    }
+}
@@ -1,5 +1,6 @@
 use std::sync::Arc;
 
+use crate::hittable::aabb::Aabb;
 use crate::hittable::{HitRecord, Hittable};
 use crate::ray::Ray;
 
@@ -35,4 +36,16 @@ impl Hittable for HittableList {
 
         record
     }
+
+    /// Returns the box enclosing every object of the list.
+    fn bounding_box(&self) -> Option<Aabb> {
+        let mut objects = self.objects.iter();
+        let mut bounding_box = objects.next()?.bounding_box()?;
+
+        for object in objects {
+            bounding_box = Aabb::surrounding(&bounding_box, &object.bounding_box()?);
+        }
+
+        Some(bounding_box)
+    }
 }
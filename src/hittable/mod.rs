@@ -1,8 +1,21 @@
+pub mod aabb;
+pub mod annulus;
+pub mod cone;
+pub mod csg;
+pub mod cuboid;
+pub mod cylinder;
+pub mod disk;
+pub mod heightfield;
 pub mod hittable_list;
+pub mod sdf;
 pub mod sphere;
+pub mod torus;
+pub mod triangle_mesh;
 
 use std::sync::Arc;
 
+use crate::color::Color;
+use crate::hittable::aabb::Aabb;
 use crate::material::Material;
 use crate::ray::Ray;
 use crate::vec3::{Point3, Vec3};
@@ -12,14 +25,31 @@ pub struct HitRecord {
     pub normal: Vec3,
     pub material: Arc<dyn Material + Sync + Send>,
     pub t: f64,
+    pub u: f64,
+    pub v: f64,
     pub front_face: bool,
+    /// Color interpolated from the vertices of a mesh, if it has any
+    pub vertex_color: Option<Color>,
 }
 
 impl HitRecord {
+    /// Returns the normal pointing outward of the object, whatever the side
+    /// the ray came from
+    pub fn outward_normal(&self) -> Vec3 {
+        if self.front_face {
+            self.normal
+        } else {
+            -self.normal
+        }
+    }
+
+    /// Constructs a new record of a hit at `t` along the ray, with `(u, v)`
+    /// the surface coordinates of the intersection
     pub fn new(
         r: &Ray,
         outward_normal: Vec3,
         t: f64,
+        (u, v): (f64, f64),
         material: Arc<dyn Material + Sync + Send>,
     ) -> HitRecord {
         let front_face = Vec3::dot(&r.direction(), &outward_normal) < 0.0;
@@ -34,7 +64,10 @@ impl HitRecord {
             normal,
             material,
             t,
+            u,
+            v,
             front_face,
+            vertex_color: None,
         }
     }
 }
@@ -45,4 +78,28 @@ pub trait Hittable {
     /// Returns a record of the hit ([`HitRecord`]) in case of success.
     /// Returns [`None`] in case of failure.
     fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord>;
+
+    /// Returns a box enclosing the object, [`None`] if it is unbounded.
+    fn bounding_box(&self) -> Option<Aabb>;
+
+    /// Returns every intersection of the whole ray with the object, sorted
+    /// by increasing `t` (negative values included).
+    ///
+    /// The default implementation repeatedly looks for the next closest hit,
+    /// objects that can compute all their intersections at once should
+    /// override it.
+    fn hit_all(&self, r: &Ray) -> Vec<HitRecord> {
+        let mut records = Vec::new();
+        let mut t_min = f64::NEG_INFINITY;
+
+        while let Some(hit_record) = self.hit(r, t_min, f64::INFINITY) {
+            t_min = hit_record.t + HIT_ALL_EPSILON;
+            records.push(hit_record);
+        }
+
+        records
+    }
 }
+
+/// Minimal distance between two intersections found by [`Hittable::hit_all`]
+const HIT_ALL_EPSILON: f64 = 1e-7;
@@ -0,0 +1,153 @@
+use crate::hittable::aabb::Aabb;
+use crate::hittable::sphere::Sphere;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::sdf::Distance;
+use crate::vec3::{Point3, Vec3};
+use std::sync::Arc;
+
+/// Distance to the surface under which a point is considered on it
+const SURFACE_EPSILON: f64 = 1e-5;
+
+/// Maximum number of steps when marching along a ray
+const MAX_STEPS: usize = 512;
+
+/// An object described by a signed distance function (see [`crate::sdf`]),
+/// rendered by sphere tracing.
+///
+/// Its surface coordinates are those of a [`Sphere`] around the center of
+/// its bounding box, on which the surface is projected.
+pub struct Sdf {
+    distance: Distance,
+    bounding_box: Aabb,
+    material: Arc<dyn Material + Sync + Send>,
+}
+
+impl Sdf {
+    /// Constructs a new object from its `distance` function. The object
+    /// must be inside `bounding_box`, where rays are marched.
+    pub fn new(
+        distance: Distance,
+        bounding_box: Aabb,
+        material: Arc<dyn Material + Sync + Send>,
+    ) -> Sdf {
+        Sdf {
+            distance,
+            bounding_box,
+            material,
+        }
+    }
+
+    /// Returns the outward normal at `p`, the gradient of the distance
+    /// function computed by central differences
+    fn normal(&self, p: Point3) -> Vec3 {
+        let h = 1e-5;
+        let dx = Vec3::new(h, 0.0, 0.0);
+        let dy = Vec3::new(0.0, h, 0.0);
+        let dz = Vec3::new(0.0, 0.0, h);
+
+        Vec3::new(
+            (self.distance)(p + dx) - (self.distance)(p - dx),
+            (self.distance)(p + dy) - (self.distance)(p - dy),
+            (self.distance)(p + dz) - (self.distance)(p - dz),
+        )
+        .normalized()
+    }
+
+    /// Returns the surface coordinates of `p`, projected from the center of
+    /// the bounding box on a sphere
+    fn uv(&self, p: Point3) -> (f64, f64) {
+        let direction = p - (self.bounding_box.min + self.bounding_box.max) / 2.0;
+        if direction.near_zero() {
+            return (0.0, 0.0);
+        }
+
+        Sphere::uv(direction.normalized())
+    }
+}
+
+impl Hittable for Sdf {
+    /// Tries to hit the object by sphere tracing: the ray is marched by the
+    /// distance to the surface, which is a step that cannot go through it,
+    /// until it gets close enough.
+    ///
+    /// Rays starting on the surface (like the ones scattered from it) first
+    /// leave it, so that the same intersection is not found again.
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let length = r.direction().length();
+        let unit_ray = Ray::new(r.origin(), r.direction() / length);
+
+        // Work with distances along the normalized direction
+        let (start, end) =
+            self.bounding_box
+                .intersect(&unit_ray, t_min * length, t_max * length)?;
+
+        let mut s = start;
+        let mut leaving = (self.distance)(unit_ray.at(s)).abs() < SURFACE_EPSILON;
+
+        for _ in 0..MAX_STEPS {
+            if s > end {
+                return None;
+            }
+
+            let d = (self.distance)(unit_ray.at(s)).abs();
+            if d < SURFACE_EPSILON {
+                if !leaving {
+                    let p = unit_ray.at(s);
+                    return Some(HitRecord::new(
+                        r,
+                        self.normal(p),
+                        s / length,
+                        self.uv(p),
+                        Arc::clone(&self.material),
+                    ));
+                }
+            } else {
+                leaving = false;
+            }
+
+            s += d.max(SURFACE_EPSILON);
+        }
+
+        None
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.bounding_box)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::color::Color;
+    use crate::material::lambertian::Lambertian;
+    use crate::sdf;
+
+    #[test]
+    fn uv_works() {
+        // The coordinates of a sphere are the ones of a sphere
+        let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        let center = Point3::new(1.0, 2.0, 0.0);
+        let bounding_box = Aabb::new(
+            center - Vec3::new(1.5, 1.5, 1.5),
+            center + Vec3::new(1.5, 1.5, 1.5),
+        );
+        let object = Sdf::new(sdf::sphere(center, 1.0), bounding_box, material.clone());
+        let sphere = Sphere::new(center, 1.0, material);
+
+        for direction in [
+            Vec3::new(0.0, 0.0, -1.0),
+            Vec3::new(-1.0, -0.3, 0.2),
+            Vec3::new(0.2, -1.0, 0.1),
+        ] {
+            let r = Ray::new(center - 3.0 * direction, direction);
+            let expected = sphere.hit(&r, 0.0, f64::INFINITY).unwrap();
+            let hit_record = object.hit(&r, 0.0, f64::INFINITY).unwrap();
+
+            assert!((hit_record.u - expected.u).abs() < 1e-4);
+            assert!((hit_record.v - expected.v).abs() < 1e-4);
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use crate::hittable::aabb::Aabb;
 use crate::hittable::{HitRecord, Hittable};
 use crate::material::Material;
 use crate::ray::Ray;
@@ -18,6 +19,27 @@ impl Sphere {
             material,
         }
     }
+
+    /// Returns the record of the hit at `t` along the ray
+    fn record(&self, r: &Ray, t: f64) -> HitRecord {
+        let intersection = r.at(t);
+        let normal = (intersection - self.center) / self.radius;
+
+        HitRecord::new(r, normal, t, Sphere::uv(normal), Arc::clone(&self.material))
+    }
+
+    /// Returns the surface coordinates of a point `p` on the unit sphere:
+    /// `u` is the angle around the Y axis from X = -1, `v` the angle from
+    /// Y = -1 to Y = +1, both scaled to [0.0, 1.0]
+    pub fn uv(p: Vec3) -> (f64, f64) {
+        let theta = (-p.y()).clamp(-1.0, 1.0).acos();
+        let phi = (-p.z()).atan2(p.x()) + std::f64::consts::PI;
+
+        (
+            phi / (2.0 * std::f64::consts::PI),
+            theta / std::f64::consts::PI,
+        )
+    }
 }
 
 impl Hittable for Sphere {
@@ -44,10 +66,31 @@ impl Hittable for Sphere {
             }
         }
 
-        let t = root;
-        let intersection = r.at(t);
-        let normal = (intersection - self.center) / self.radius;
+        Some(self.record(r, root))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let extent = Vec3::new(self.radius, self.radius, self.radius);
+        Some(Aabb::new(self.center - extent, self.center + extent))
+    }
+
+    /// Returns both intersections of the ray with the sphere
+    fn hit_all(&self, r: &Ray) -> Vec<HitRecord> {
+        let oc = r.origin() - self.center;
+        let a = r.direction().length_squared();
+        let half_b = Vec3::dot(&oc, &r.direction());
+        let c = oc.length_squared() - self.radius * self.radius;
+
+        let discriminant = half_b * half_b - a * c;
+        if discriminant <= 0.0 {
+            return Vec::new();
+        }
+
+        let sqrtd = discriminant.sqrt();
 
-        Some(HitRecord::new(r, normal, t, Arc::clone(&self.material)))
+        vec![
+            self.record(r, (-half_b - sqrtd) / a),
+            self.record(r, (-half_b + sqrtd) / a),
+        ]
     }
 }
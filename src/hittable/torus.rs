@@ -0,0 +1,295 @@
+use crate::hittable::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// A torus (a ring) around an axis
+pub struct Torus {
+    center: Point3,
+    frame: Onb,
+    major_radius: f64,
+    minor_radius: f64,
+    material: Arc<dyn Material + Sync + Send>,
+}
+
+impl Torus {
+    /// Constructs a new torus around `axis`, with `major_radius` the distance
+    /// from the center to the middle of the tube and `minor_radius` the
+    /// radius of the tube
+    pub fn new(
+        center: Point3,
+        axis: Vec3,
+        major_radius: f64,
+        minor_radius: f64,
+        material: Arc<dyn Material + Sync + Send>,
+    ) -> Torus {
+        Torus {
+            center,
+            frame: Onb::from_w(axis),
+            major_radius,
+            minor_radius,
+            material,
+        }
+    }
+
+    /// Returns the bounding box of the torus in its own frame
+    fn local_box(&self) -> Aabb {
+        let extent = self.major_radius + self.minor_radius;
+        Aabb::new(
+            Point3::new(-extent, -extent, -self.minor_radius),
+            Point3::new(extent, extent, self.minor_radius),
+        )
+    }
+
+    /// Returns the coefficients of the quartic whose roots are the
+    /// distances from `o` to the torus along the unit vector `d`, in its own
+    /// frame, from the highest degree
+    fn quartic(&self, o: Vec3, d: Vec3) -> [f64; 5] {
+        let r2 = self.major_radius * self.major_radius;
+        let b = Vec3::dot(&o, &d);
+        let k = o.length_squared() + r2 - self.minor_radius * self.minor_radius;
+
+        [
+            1.0,
+            4.0 * b,
+            4.0 * b * b + 2.0 * k - 4.0 * r2 * (d.x() * d.x() + d.y() * d.y()),
+            4.0 * b * k - 8.0 * r2 * (o.x() * d.x() + o.y() * d.y()),
+            k * k - 4.0 * r2 * (o.x() * o.x() + o.y() * o.y()),
+        ]
+    }
+}
+
+/// Evaluates a polynomial given by its coefficients from the highest
+/// degree, and its derivative
+fn evaluate(coefficients: &[f64], x: f64) -> (f64, f64) {
+    coefficients
+        .iter()
+        .fold((0.0, 0.0), |(value, slope), coefficient| {
+            (value * x + coefficient, slope * x + value)
+        })
+}
+
+/// Returns the coefficients of the derivative of a polynomial
+fn derivative(coefficients: &[f64]) -> Vec<f64> {
+    let degree = coefficients.len() - 1;
+    coefficients[..degree]
+        .iter()
+        .enumerate()
+        .map(|(k, coefficient)| coefficient * (degree - k) as f64)
+        .collect()
+}
+
+/// Returns the bounds of the intervals of `[start, end]` where a polynomial
+/// is monotonic: the ends and the roots of its derivative in between
+fn monotonic_bounds(coefficients: &[f64], start: f64, end: f64) -> Vec<f64> {
+    let mut bounds = vec![start];
+    bounds.extend(roots(&derivative(coefficients), start, end));
+    bounds.push(end);
+    bounds
+}
+
+/// Returns the roots of a polynomial in `[start, end]` where it changes
+/// sign, sorted. The polynomial is monotonic between the roots of its
+/// derivative, found the same way, so that each interval between them holds
+/// at most one root, however close to another.
+fn roots(coefficients: &[f64], start: f64, end: f64) -> Vec<f64> {
+    match *coefficients {
+        [] | [_] => Vec::new(),
+        [a, b] => vec![-b / a],
+        [a, b, c] if a != 0.0 => {
+            let discriminant = b * b - 4.0 * a * c;
+            if discriminant <= 0.0 {
+                return Vec::new();
+            }
+            // Without cancellation between `b` and the root of the
+            // discriminant
+            let q = -0.5 * (b + discriminant.sqrt().copysign(b));
+            let (r0, r1) = (q / a, c / q);
+            vec![r0.min(r1), r0.max(r1)]
+        }
+        _ => {
+            return monotonic_bounds(coefficients, start, end)
+                .windows(2)
+                .filter_map(|bounds| refine(coefficients, bounds[0], bounds[1]))
+                .collect()
+        }
+    }
+    .into_iter()
+    .filter(|root| (start..=end).contains(root))
+    .collect()
+}
+
+/// Returns the root of a polynomial monotonic in `[a, b]`, if it changes
+/// sign there, by Newton's method kept within the interval by bisection
+fn refine(coefficients: &[f64], mut a: f64, mut b: f64) -> Option<f64> {
+    let negative = evaluate(coefficients, a).0 < 0.0;
+    if negative == (evaluate(coefficients, b).0 < 0.0) {
+        return None;
+    }
+
+    let mut x = 0.5 * (a + b);
+    for _ in 0..64 {
+        let (value, slope) = evaluate(coefficients, x);
+        if (value < 0.0) == negative {
+            a = x;
+        } else {
+            b = x;
+        }
+
+        let newton = x - value / slope;
+        let next = if a < newton && newton < b {
+            newton
+        } else {
+            0.5 * (a + b)
+        };
+        if (next - x).abs() <= 1e-12 * (1.0 + x.abs()) {
+            return Some(next);
+        }
+        x = next;
+    }
+
+    Some(x)
+}
+
+impl Hittable for Torus {
+    /// Tries to hit a torus object.
+    ///
+    /// The intersection is a quartic equation, whose first root inside the
+    /// bounding box of the torus is isolated between the extrema of the
+    /// quartic, then refined by Newton's method.
+    ///
+    /// Surface coordinates are the angles around the axis and around the
+    /// tube.
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let length = r.direction().length();
+        let o = self.frame.local(r.origin() - self.center);
+        let d = self.frame.local(r.direction()) / length;
+        let local_ray = Ray::new(o, d);
+
+        // Work with distances along the normalized direction
+        let (start, end) =
+            self.local_box()
+                .intersect(&local_ray, t_min * length, t_max * length)?;
+
+        // The quartic is taken from where the ray enters the box, for its
+        // coefficients to stay small
+        let entry = local_ray.at(start);
+        let quartic = self.quartic(entry, d);
+        let root = monotonic_bounds(&quartic, 0.0, end - start)
+            .windows(2)
+            .find_map(|bounds| refine(&quartic, bounds[0], bounds[1]))
+            .map(|root| start + root);
+
+        let s = root?;
+        let p = local_ray.at(s);
+
+        let ring_distance = (p.x() * p.x() + p.y() * p.y()).sqrt();
+        let ring_point = self.major_radius * Vec3::new(p.x(), p.y(), 0.0) / ring_distance;
+        let normal = (p - ring_point) / self.minor_radius;
+
+        let u = (p.y().atan2(p.x()) + PI) / (2.0 * PI);
+        let v = (p.z().atan2(ring_distance - self.major_radius) + PI) / (2.0 * PI);
+
+        Some(HitRecord::new(
+            r,
+            self.frame.world(normal),
+            s / length,
+            (u, v),
+            Arc::clone(&self.material),
+        ))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let local_box = self.local_box();
+        let mut corners = Vec::with_capacity(8);
+        for x in [local_box.min.x(), local_box.max.x()] {
+            for y in [local_box.min.y(), local_box.max.y()] {
+                for z in [local_box.min.z(), local_box.max.z()] {
+                    corners.push(self.center + self.frame.world(Vec3::new(x, y, z)));
+                }
+            }
+        }
+
+        Some(Aabb::from_points(&corners))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::color::Color;
+    use crate::material::lambertian::Lambertian;
+
+    /// A ring around the Z axis, whose tube goes from 0.75 to 1.25
+    fn ring() -> Torus {
+        let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        Torus::new(
+            Point3::zero(),
+            Vec3::new(0.0, 0.0, 1.0),
+            1.0,
+            0.25,
+            material,
+        )
+    }
+
+    #[test]
+    fn hit_works() {
+        let r = Ray::new(Point3::new(5.0, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0));
+        let hit_record = ring().hit(&r, 0.0, f64::INFINITY).unwrap();
+
+        assert!((hit_record.t - 3.75).abs() < 1e-9);
+        assert!((hit_record.normal - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-9);
+        assert!(hit_record.front_face);
+        assert!((hit_record.u - 0.5).abs() < 1e-9);
+        assert!((hit_record.v - 0.5).abs() < 1e-9);
+
+        // From above, with a direction that is not a unit vector
+        let r = Ray::new(Point3::new(0.0, 1.0, 5.0), Vec3::new(0.0, 0.0, -2.0));
+        let hit_record = ring().hit(&r, 0.0, f64::INFINITY).unwrap();
+
+        assert!((hit_record.t - 2.375).abs() < 1e-9);
+        assert!((hit_record.normal - Vec3::new(0.0, 0.0, 1.0)).length() < 1e-9);
+        assert!((hit_record.u - 0.75).abs() < 1e-9);
+        assert!((hit_record.v - 0.75).abs() < 1e-9);
+    }
+
+    #[test]
+    fn grazing_ray_works() {
+        // Tangent to a circle just inside the outer edge of the tube, so that
+        // the chord through the tube is 0.01 long
+        let grazing = |distance: f64| {
+            let (sin, cos) = 0.5f64.sin_cos();
+            let direction = Vec3::new(-sin, cos, 0.0);
+            Ray::new(
+                distance * Point3::new(cos, sin, 0.0) - 5.0 * direction,
+                direction,
+            )
+        };
+
+        let hit_record = ring().hit(&grazing(1.24999), 0.0, f64::INFINITY).unwrap();
+        assert!((hit_record.t - 4.995).abs() < 1e-3);
+        assert!(ring().hit(&grazing(1.25001), 0.0, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn miss_works() {
+        // Through the hole
+        let r = Ray::new(Point3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(ring().hit(&r, 0.0, f64::INFINITY).is_none());
+
+        // Beyond `t_max`
+        let r = Ray::new(Point3::new(5.0, 0.0, 0.0), Vec3::new(-1.0, 0.0, 0.0));
+        assert!(ring().hit(&r, 0.0, 3.0).is_none());
+    }
+
+    #[test]
+    fn bounding_box_works() {
+        let bounding_box = ring().bounding_box().unwrap();
+        assert!((bounding_box.min - Point3::new(-1.25, -1.25, -0.25)).length() < 1e-3);
+        assert!((bounding_box.max - Point3::new(1.25, 1.25, 0.25)).length() < 1e-3);
+    }
+}
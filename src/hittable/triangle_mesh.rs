@@ -0,0 +1,323 @@
+use std::sync::Arc;
+
+use crate::hittable::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::mesh::{MeshData, MeshError};
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+
+/// Maximum number of triangles in a leaf of the hierarchy
+const LEAF_SIZE: usize = 4;
+
+/// Node of the bounding volume hierarchy of a mesh
+enum Node {
+    /// Triangles `start..end` of the mesh
+    Leaf {
+        bounding_box: Aabb,
+        start: usize,
+        end: usize,
+    },
+    /// Two children nodes, given by their index
+    Inner {
+        bounding_box: Aabb,
+        left: usize,
+        right: usize,
+    },
+}
+
+impl Node {
+    fn bounding_box(&self) -> &Aabb {
+        match self {
+            Node::Leaf { bounding_box, .. } | Node::Inner { bounding_box, .. } => bounding_box,
+        }
+    }
+}
+
+/// A mesh of triangles.
+///
+/// Triangles are sorted in a bounding volume hierarchy, so that a ray only
+/// tests the few triangles whose boxes it goes through. Normals, surface
+/// coordinates and colors of the vertices are interpolated on the triangles
+/// when the mesh has them.
+pub struct TriangleMesh {
+    mesh: MeshData,
+    nodes: Vec<Node>,
+    material: Arc<dyn Material + Sync + Send>,
+}
+
+impl TriangleMesh {
+    /// Constructs a new mesh and its hierarchy, or returns an error if its
+    /// data is invalid (see [`MeshData::validate`]).
+    pub fn new(
+        mut mesh: MeshData,
+        material: Arc<dyn Material + Sync + Send>,
+    ) -> Result<TriangleMesh, MeshError> {
+        mesh.validate()?;
+
+        let mut nodes = Vec::new();
+        if !mesh.triangles.is_empty() {
+            let count = mesh.triangles.len();
+            TriangleMesh::build(&mut nodes, &mesh.positions, &mut mesh.triangles, 0, count);
+        }
+
+        Ok(TriangleMesh {
+            mesh,
+            nodes,
+            material,
+        })
+    }
+
+    /// Builds the node containing triangles `start..end` and its children,
+    /// returns its index
+    fn build(
+        nodes: &mut Vec<Node>,
+        positions: &[Point3],
+        triangles: &mut [[usize; 3]],
+        start: usize,
+        end: usize,
+    ) -> usize {
+        let points: Vec<Point3> = triangles[start..end]
+            .iter()
+            .flat_map(|triangle| triangle.map(|i| positions[i]))
+            .collect();
+        let bounding_box = Aabb::from_points(&points).padded();
+
+        let index = nodes.len();
+        if end - start <= LEAF_SIZE {
+            nodes.push(Node::Leaf {
+                bounding_box,
+                start,
+                end,
+            });
+            return index;
+        }
+
+        // Split at the median along the largest dimension of the box
+        let extent = bounding_box.max - bounding_box.min;
+        let axis = |p: Point3| {
+            if extent.x() >= extent.y() && extent.x() >= extent.z() {
+                p.x()
+            } else if extent.y() >= extent.z() {
+                p.y()
+            } else {
+                p.z()
+            }
+        };
+        let centroid = |triangle: &[usize; 3]| {
+            axis(positions[triangle[0]] + positions[triangle[1]] + positions[triangle[2]])
+        };
+        triangles[start..end].sort_by(|a, b| centroid(a).total_cmp(&centroid(b)));
+
+        // Reserve the node before its children
+        nodes.push(Node::Leaf {
+            bounding_box,
+            start,
+            end,
+        });
+        let middle = (start + end) / 2;
+        let left = TriangleMesh::build(nodes, positions, triangles, start, middle);
+        let right = TriangleMesh::build(nodes, positions, triangles, middle, end);
+        nodes[index] = Node::Inner {
+            bounding_box,
+            left,
+            right,
+        };
+
+        index
+    }
+
+    /// Returns the record of the hit of triangle `index` at `t`, with `b1`
+    /// and `b2` the barycentric coordinates of the hit
+    fn record(&self, r: &Ray, index: usize, t: f64, b1: f64, b2: f64) -> HitRecord {
+        let [i0, i1, i2] = self.mesh.triangles[index];
+        let b0 = 1.0 - b1 - b2;
+
+        let normal = match &self.mesh.normals {
+            Some(normals) => (b0 * normals[i0] + b1 * normals[i1] + b2 * normals[i2]).normalized(),
+            None => {
+                let p = &self.mesh.positions;
+                Vec3::cross(&(p[i1] - p[i0]), &(p[i2] - p[i0])).normalized()
+            }
+        };
+
+        let uv = match &self.mesh.uvs {
+            Some(uvs) => (
+                b0 * uvs[i0].0 + b1 * uvs[i1].0 + b2 * uvs[i2].0,
+                b0 * uvs[i0].1 + b1 * uvs[i1].1 + b2 * uvs[i2].1,
+            ),
+            None => (b1, b2),
+        };
+
+        let mut hit_record = HitRecord::new(r, normal, t, uv, Arc::clone(&self.material));
+        hit_record.vertex_color = self
+            .mesh
+            .colors
+            .as_ref()
+            .map(|colors| b0 * colors[i0] + b1 * colors[i1] + b2 * colors[i2]);
+
+        hit_record
+    }
+}
+
+/// Intersects the ray with the triangle `p0`, `p1`, `p2` (Möller-Trumbore).
+/// Returns the distance along the ray and the barycentric coordinates of the
+/// hit relative to `p1` and `p2`.
+pub fn intersect_triangle(
+    r: &Ray,
+    p0: Point3,
+    p1: Point3,
+    p2: Point3,
+    t_min: f64,
+    t_max: f64,
+) -> Option<(f64, f64, f64)> {
+    let edge1 = p1 - p0;
+    let edge2 = p2 - p0;
+    let h = Vec3::cross(&r.direction(), &edge2);
+    let a = Vec3::dot(&edge1, &h);
+    if a.abs() < 1e-12 {
+        return None;
+    }
+
+    let f = 1.0 / a;
+    let s = r.origin() - p0;
+    let b1 = f * Vec3::dot(&s, &h);
+    if !(0.0..=1.0).contains(&b1) {
+        return None;
+    }
+
+    let q = Vec3::cross(&s, &edge1);
+    let b2 = f * Vec3::dot(&r.direction(), &q);
+    if b2 < 0.0 || b1 + b2 > 1.0 {
+        return None;
+    }
+
+    let t = f * Vec3::dot(&edge2, &q);
+    if t < t_min || t_max < t {
+        return None;
+    }
+
+    Some((t, b1, b2))
+}
+
+impl Hittable for TriangleMesh {
+    /// Tries to hit a triangle of the mesh, walking down the hierarchy.
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let mut hit = None;
+        let mut closest_so_far = t_max;
+
+        let mut stack = Vec::new();
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !node.bounding_box().hit(r, t_min, closest_so_far) {
+                continue;
+            }
+
+            match node {
+                Node::Inner { left, right, .. } => {
+                    stack.push(*left);
+                    stack.push(*right);
+                }
+                Node::Leaf { start, end, .. } => {
+                    for triangle in *start..*end {
+                        let [p0, p1, p2] =
+                            self.mesh.triangles[triangle].map(|i| self.mesh.positions[i]);
+                        if let Some((t, b1, b2)) =
+                            intersect_triangle(r, p0, p1, p2, t_min, closest_so_far)
+                        {
+                            closest_so_far = t;
+                            hit = Some((triangle, t, b1, b2));
+                        }
+                    }
+                }
+            }
+        }
+
+        hit.map(|(triangle, t, b1, b2)| self.record(r, triangle, t, b1, b2))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.nodes.first().map(|node| *node.bounding_box())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::color::Color;
+    use crate::material::lambertian::Lambertian;
+
+    /// A grid of `n` by `n` squares in the XY plane, colored along X
+    fn grid(n: usize) -> TriangleMesh {
+        let mut positions = Vec::new();
+        let mut colors = Vec::new();
+        for j in 0..=n {
+            for i in 0..=n {
+                positions.push(Point3::new(i as f64, j as f64, 0.0));
+                colors.push(Color::new(i as f64 / n as f64, 0.0, 0.0));
+            }
+        }
+
+        let mut triangles = Vec::new();
+        for j in 0..n {
+            for i in 0..n {
+                let k = j * (n + 1) + i;
+                triangles.push([k, k + 1, k + n + 2]);
+                triangles.push([k, k + n + 2, k + n + 1]);
+            }
+        }
+
+        let mesh = MeshData {
+            positions,
+            normals: None,
+            uvs: None,
+            colors: Some(colors),
+            triangles,
+        };
+        let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+        TriangleMesh::new(mesh, material).unwrap()
+    }
+
+    #[test]
+    fn invalid_index_fails() {
+        let mesh = MeshData {
+            positions: vec![
+                Point3::zero(),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(0.0, 1.0, 0.0),
+            ],
+            normals: None,
+            uvs: None,
+            colors: None,
+            triangles: vec![[0, 1, 2], [1, 2, 3]],
+        };
+        let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+
+        assert!(matches!(
+            TriangleMesh::new(mesh, material),
+            Err(MeshError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn hit_works() {
+        let mesh = grid(10);
+        let r = Ray::new(Point3::new(2.5, 7.25, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_record = mesh.hit(&r, 0.0, f64::INFINITY).unwrap();
+
+        assert!((hit_record.t - 5.0).abs() < 1e-9);
+        assert!(hit_record.front_face);
+        assert!((hit_record.vertex_color.unwrap().x() - 0.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn miss_works() {
+        let mesh = grid(10);
+        let r = Ray::new(Point3::new(12.0, 5.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(mesh.hit(&r, 0.0, f64::INFINITY).is_none());
+    }
+}
@@ -1,116 +1,856 @@
-use std::sync::{Arc, Mutex};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
 
+use crate::adaptive::{AdaptiveSampling, Estimate};
 use crate::camera::Camera;
 use crate::color::Color;
-use crate::hittable::Hittable;
-use crate::parallel::ThreadPool;
-use crate::random::canonical_random;
+use crate::distributed::Workers;
+use crate::filter::{BoxFilter, Filter};
+use crate::framebuffer::{Framebuffer, Pixel};
+use crate::hittable::{HitRecord, Hittable};
+use crate::light::Light;
+use crate::parallel::{self, ThreadPool};
 use crate::ray::Ray;
+use crate::sampler::independent::IndependentSampler;
+use crate::sampler::Sampler;
+use crate::tile::{self, Tile, TileOrder};
+use crate::vec3::Vec3;
 
-use image::DynamicImage;
 use pbr::ProgressBar;
 
-/// Represent an image to be rendered
-pub struct Image {
+/// Samples per pixel of the passes of renders needing passes, such as
+/// time-limited ones, unless given
+pub const DEFAULT_PASS_SAMPLES: u32 = 4;
+
+/// Steps of the random walks inside translucent objects a path may take,
+/// apart from its bounces on surfaces: walks in dense media take many more
+/// steps than paths take bounces
+pub const MAX_WALK_STEPS: u32 = 1024;
+
+/// A tile to be rendered, with the estimates of its pixels, row by row, and
+/// the samples per pixel it must have at most once rendered
+pub struct TileJob {
+    pub tile: Tile,
+    pub estimates: Vec<Estimate>,
+    pub max_samples: u32,
+    pub max_depth: i32,
+}
+
+/// The samples taken in a tile, splatted into the pixels of a region around
+/// it, and the estimates of the pixels of the tile once rendered
+pub struct RenderedTile {
+    pub region: Tile,
+    pub pixels: Vec<Pixel>,
+    pub estimates: Vec<Estimate>,
+}
+
+/// What becomes of a tile given to a thread or a worker
+pub enum TileOutcome {
+    Rendered(RenderedTile),
+    /// Not rendered, the rendering being cancelled or having failed
+    Skipped,
+    /// The rendering of the tile panicked, with the message of the panic
+    Failed(String),
+}
+
+/// Error stopping the rendering of an image
+#[derive(Debug)]
+pub enum RenderError {
+    /// The rendering of a tile panicked: the tiles under way were finished,
+    /// but no other was started
+    TileFailed { tile: Tile, message: String },
+    /// Every remote worker disconnected before all the tiles were rendered
+    WorkersDisconnected,
+}
+
+impl Display for RenderError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenderError::TileFailed { tile, message } => write!(
+                f,
+                "the rendering of the {}x{} tile at ({}, {}) failed: {}",
+                tile.width, tile.height, tile.x, tile.y, message
+            ),
+            RenderError::WorkersDisconnected => write!(
+                f,
+                "the workers were disconnected before rendering every tile"
+            ),
+        }
+    }
+}
+
+impl Error for RenderError {}
+
+/// Everything the rendering of a tile needs, shared by the threads
+struct TileRenderer {
     image_width: u32,
     image_height: u32,
     world: Arc<dyn Hittable + Sync + Send>,
+    lights: Arc<Vec<Arc<dyn Light + Sync + Send>>>,
     camera: Arc<Camera>,
+    filter: Arc<dyn Filter + Sync + Send>,
+    adaptive_sampling: Option<AdaptiveSampling>,
+}
+
+impl TileRenderer {
+    /// Renders a tile into its own buffer, covering the tile and the pixels
+    /// around it within the radius of the filter
+    fn render(&self, job: TileJob, sampler: &mut dyn Sampler) -> RenderedTile {
+        let TileJob {
+            tile,
+            mut estimates,
+            max_samples,
+            max_depth,
+        } = job;
+        let (image_width, image_height) = (self.image_width, self.image_height);
+
+        let margin = (self.filter.radius() - 0.5).ceil().max(0.0) as u32;
+        let region = tile.expanded(margin, image_width, image_height);
+        let mut pixels = vec![Pixel::default(); (region.width * region.height) as usize];
+
+        // Pixels are sampled in rounds, adaptive sampling choosing after each
+        // one the pixels needing more samples
+        let next_batches = |estimates: &[Estimate]| match self.adaptive_sampling {
+            Some(adaptive_sampling) => {
+                adaptive_sampling.next_batches(estimates, tile.width, max_samples)
+            }
+            None => estimates
+                .iter()
+                .map(|estimate| max_samples.saturating_sub(estimate.count()))
+                .collect(),
+        };
+
+        let mut batches = next_batches(&estimates);
+        while batches.iter().any(|&batch| batch > 0) {
+            for (k, (estimate, &batch)) in estimates.iter_mut().zip(&batches).enumerate() {
+                if batch == 0 {
+                    continue;
+                }
+
+                let i = tile.x + k as u32 % tile.width;
+                let j = tile.y + k as u32 / tile.width;
+                estimate.start_batch();
+                let start = estimate.count();
+                for sample in start..start + batch {
+                    sampler.start_pixel_sample(i, j, sample);
+
+                    let (dx, dy) = sampler.get_2d();
+                    let (x, y) = (i as f64 + dx, j as f64 + dy);
+                    let u = x / (image_width - 1) as f64;
+                    let v = y / (image_height - 1) as f64;
+                    let r = self.camera.get_ray(u, v, sampler);
+                    let color = Image::ray_color(&*self.world, &self.lights, r, max_depth, sampler);
+                    estimate.add(color.luminance());
+                    Image::splat(&self.filter, &region, &mut pixels, (x, y), color);
+                }
+            }
+
+            batches = next_batches(&estimates);
+        }
+
+        RenderedTile {
+            region,
+            pixels,
+            estimates,
+        }
+    }
+}
+
+/// Represent an image to be rendered
+pub struct Image {
+    renderer: TileRenderer,
+    sampler: Box<dyn Sampler + Send + Sync>,
+    workers: Option<Workers>,
+    pass_samples: Option<u32>,
+    time_limit: Option<Duration>,
+    resumed: Option<Framebuffer>,
+    cancelled: Option<&'static AtomicBool>,
 }
 
 impl Image {
     /// Constructs a new image (height is calculated with `aspect_ratio` and `image_width`)
-    /// with its camera
+    /// with its lights and camera, a box filter keeping the samples inside
+    /// each pixel and an independent sampler
     pub fn new(
         aspect_ratio: f64,
         image_width: u32,
         world: Arc<dyn Hittable + Sync + Send>,
+        lights: Vec<Arc<dyn Light + Sync + Send>>,
         camera: Arc<Camera>,
     ) -> Image {
-        let image_height = (image_width as f64 / aspect_ratio) as u32;
+        let image_height = (image_width as f64 / aspect_ratio).round() as u32;
 
         Image {
-            image_width,
-            image_height,
-            world,
-            camera,
+            renderer: TileRenderer {
+                image_width,
+                image_height,
+                world,
+                lights: Arc::new(lights),
+                camera,
+                filter: Arc::new(BoxFilter::new(0.5)),
+                adaptive_sampling: None,
+            },
+            sampler: Box::new(IndependentSampler::new(0)),
+            workers: None,
+            pass_samples: None,
+            time_limit: None,
+            resumed: None,
+            cancelled: None,
         }
     }
 
-    /// Computes the color rendered for a given ray `r` with a maximum
-    /// recursion depth of `depth`
-    fn ray_color(world: Arc<dyn Hittable + Sync + Send>, r: Ray, depth: i32) -> Color {
-        // If we've exceeded the ray bounce limit, no more light is gathered.
-        if depth <= 0 {
-            return Color::new(0.0, 0.0, 0.0);
-        }
+    pub fn width(&self) -> u32 {
+        self.renderer.image_width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.renderer.image_height
+    }
+
+    /// Sets the filter reconstructing pixels from the samples around them
+    pub fn with_filter(mut self, filter: Arc<dyn Filter + Sync + Send>) -> Self {
+        self.renderer.filter = filter;
+        self
+    }
 
-        if let Some(hit_record) = world.hit(&r, 0.001, f64::INFINITY) {
-            return match hit_record.material.scatter(&r, &hit_record) {
+    /// Sets the sampler giving the samples of each pixel, cloned for each tile
+    pub fn with_sampler(mut self, sampler: Box<dyn Sampler + Send + Sync>) -> Self {
+        self.sampler = sampler;
+        self
+    }
+
+    /// Samples noisy pixels more than smooth ones, the samples per pixel
+    /// given to the rendering becoming a maximum
+    pub fn with_adaptive_sampling(mut self, adaptive_sampling: AdaptiveSampling) -> Self {
+        self.renderer.adaptive_sampling = Some(adaptive_sampling);
+        self
+    }
+
+    /// Has the tiles rendered by remote workers, which must render the same
+    /// image, instead of local threads
+    pub fn with_workers(mut self, workers: Workers) -> Self {
+        self.workers = Some(workers);
+        self
+    }
+
+    /// Renders the image progressively, in passes adding at most
+    /// `pass_samples` samples to every pixel, instead of a single pass
+    pub fn with_passes(mut self, pass_samples: u32) -> Self {
+        self.pass_samples = Some(pass_samples);
+        self
+    }
+
+    /// Stops adding passes once the rendering has lasted `time_limit`, the
+    /// pass under way being finished. Without passes of their own,
+    /// time-limited renders use passes of `DEFAULT_PASS_SAMPLES`.
+    pub fn with_time_limit(mut self, time_limit: Duration) -> Self {
+        self.time_limit = Some(time_limit);
+        self
+    }
+
+    /// Continues the rendering of a framebuffer holding samples already,
+    /// such as one read from a checkpoint, instead of an empty one. The
+    /// passes it was given are not rendered again.
+    pub fn with_framebuffer(mut self, framebuffer: Framebuffer) -> Self {
+        assert_eq!(
+            (self.width(), self.height()),
+            (framebuffer.width(), framebuffer.height())
+        );
+        self.resumed = Some(framebuffer);
+        self
+    }
+
+    /// Stops the rendering once `cancelled` is set: the tiles under way are
+    /// finished, but no other is started, so that the framebuffer keeps the
+    /// samples of the passes before for the pixels of the others.
+    pub fn with_cancellation(mut self, cancelled: &'static AtomicBool) -> Self {
+        self.cancelled = Some(cancelled);
+        self
+    }
+
+    /// Renders a tile, by a thread of the pool or a worker of a distributed
+    /// rendering. A panic of the rendering is caught and its message
+    /// returned, so that the thread can go on with other tiles.
+    pub fn render_tile(&self, job: TileJob) -> Result<RenderedTile, String> {
+        panic::catch_unwind(AssertUnwindSafe(|| {
+            self.renderer
+                .render(job, &mut *self.sampler.clone_sampler())
+        }))
+        .map_err(|payload| parallel::panic_message(&*payload))
+    }
+
+    /// Computes the color rendered for a given ray `r` with at most `depth`
+    /// bounces on surfaces, and `MAX_WALK_STEPS` steps inside translucent
+    /// objects
+    fn ray_color(
+        world: &dyn Hittable,
+        lights: &[Arc<dyn Light + Sync + Send>],
+        mut r: Ray,
+        mut depth: i32,
+        sampler: &mut dyn Sampler,
+    ) -> Color {
+        let mut color = Color::new(0.0, 0.0, 0.0);
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut walk_steps = MAX_WALK_STEPS;
+
+        // Paths are followed in a loop rather than recursively, as walks
+        // would overflow the stack
+        loop {
+            // If we've exceeded the ray bounce limit, no more light is gathered.
+            if depth <= 0 {
+                return color;
+            }
+
+            let hit_record = match world.hit(&r, 0.001, f64::INFINITY) {
+                Some(hit_record) => hit_record,
+                None => {
+                    let unit_direction = r.direction().normalized();
+                    let t = 0.5 * (unit_direction.y() + 1.0);
+                    let sky = (1.0 - t) * Color::new(1.0, 1.0, 1.0) + t * Color::new(0.5, 0.7, 1.0);
+                    return color + throughput * sky;
+                }
+            };
+
+            let emitted = hit_record.material.emitted(&hit_record);
+            let direct = match hit_record.material.diffuse_albedo(&r, &hit_record) {
+                Some(albedo) => albedo * Image::direct_light(world, lights, &hit_record),
                 None => Color::new(0.0, 0.0, 0.0),
-                Some((scattered, attenuation)) => {
-                    attenuation * Image::ray_color(world, scattered, depth - 1)
+            };
+            color += throughput * (emitted + direct);
+
+            let (scattered, attenuation) =
+                match hit_record.material.scatter(&r, &hit_record, sampler) {
+                    Some(scattered) => scattered,
+                    None => return color,
+                };
+            throughput = throughput * attenuation;
+            r = scattered;
+
+            if hit_record.material.walks_inside(&hit_record) {
+                if walk_steps == 0 {
+                    return color;
                 }
+                walk_steps -= 1;
+            } else {
+                depth -= 1;
+            }
+        }
+    }
+
+    /// Computes the light reflected towards the viewer by a white diffuse
+    /// surface at the hit, coming straight from the lights it sees
+    fn direct_light(
+        world: &dyn Hittable,
+        lights: &[Arc<dyn Light + Sync + Send>],
+        hit_record: &HitRecord,
+    ) -> Color {
+        let mut direct = Color::new(0.0, 0.0, 0.0);
+
+        for light in lights {
+            let (direction, distance, received) = match light.illuminate(hit_record.intersection) {
+                Some(illumination) => illumination,
+                None => continue,
             };
+
+            let cosine = Vec3::dot(&direction, &hit_record.normal);
+            if cosine <= 0.0 {
+                continue;
+            }
+
+            let shadow = Ray::new(hit_record.intersection, direction);
+            if world.hit(&shadow, 0.001, distance - 0.001).is_none() {
+                direct += cosine / std::f64::consts::PI * received;
+            }
         }
 
-        let unit_direction = r.direction().normalized();
-        let t = 0.5 * (unit_direction.y() + 1.0);
-        (1.0 - t) * Color::new(1.0, 1.0, 1.0) + t * Color::new(0.5, 0.7, 1.0)
+        direct
     }
 
-    /// Renders the image to the PPM format to the specified stream
-    /// (may be a file or just standard output)
+    /// Adds a sample at `(x, y)`, in pixels, to the pixels of a buffer
+    /// covering `region` within the radius of the filter
+    fn splat(
+        filter: &Arc<dyn Filter + Sync + Send>,
+        region: &Tile,
+        buffer: &mut [Pixel],
+        (x, y): (f64, f64),
+        color: Color,
+    ) {
+        let radius = filter.radius();
+
+        // Pixels whose centers are within the radius
+        let first_column = ((x - 0.5 - radius).ceil().max(region.x as f64)) as u32;
+        let last_column = (x - 0.5 + radius)
+            .floor()
+            .min((region.x + region.width - 1) as f64);
+        let first_row = ((y - 0.5 - radius).ceil().max(region.y as f64)) as u32;
+        let last_row = (y - 0.5 + radius)
+            .floor()
+            .min((region.y + region.height - 1) as f64);
+
+        for row in first_row..=last_row as u32 {
+            for column in first_column..=last_column as u32 {
+                let weight = filter.evaluate(x - (column as f64 + 0.5), y - (row as f64 + 0.5));
+                if weight != 0.0 {
+                    let k = (row - region.y) * region.width + column - region.x;
+                    buffer[k as usize].add_sample(color, weight);
+                }
+            }
+        }
+    }
+
+    /// Renders the image, tile by tile, into a framebuffer. Each tile is
+    /// rendered in its own buffer by a thread of the pool, or by a remote
+    /// worker, then sent back to be merged into the framebuffer, so that
+    /// threads never wait for each other. Samples are splatted into the
+    /// pixels around them, so that the buffer of a tile overlaps its
+    /// neighbours by the radius of the filter.
+    ///
+    /// The pool is created once for all the passes, its jobs borrowing the
+    /// scene rather than sharing it.
+    ///
+    /// The whole image is rendered in passes, each adding samples to every
+    /// pixel, `after_pass` being given the framebuffer and the number of
+    /// passes done after each one but the last.
+    ///
+    /// If the rendering of a tile panics, the threads of the pool start no
+    /// other tile, and the first tile that failed, in the order of the list,
+    /// is returned once the tiles under way are finished.
     pub fn render_image(
         &self,
         samples_per_pixel: i32,
         max_depth: i32,
         thread_number: usize,
-    ) -> DynamicImage {
-        let mut pool = ThreadPool::new(thread_number);
+        tile_size: u32,
+        tile_order: TileOrder,
+        mut after_pass: impl FnMut(&Framebuffer, u32),
+    ) -> Result<Framebuffer, RenderError> {
+        let pool = ThreadPool::new(thread_number);
 
-        let img = Arc::new(Mutex::new(DynamicImage::new_rgb8(
-            self.image_width,
-            self.image_height,
-        )));
-        let pb = Arc::new(Mutex::new(ProgressBar::new(self.image_height as u64)));
-
-        for j in (0..self.image_height).rev() {
-            let pb = Arc::clone(&pb);
-            let world = Arc::clone(&self.world);
-            let img = Arc::clone(&img);
-            let camera = Arc::clone(&self.camera);
-
-            let image_width = self.image_width;
-            let image_height = self.image_height;
-
-            // Each line rendering is sent to the thread pool
-            pool.execute(move || {
-                for i in 0..image_width {
-                    let mut pixel_color = Color::new(0.0, 0.0, 0.0);
-                    for _ in 0..samples_per_pixel {
-                        let u = (i as f64 + canonical_random()) / (image_width - 1) as f64;
-                        let v = (j as f64 + canonical_random()) / (image_height - 1) as f64;
-                        let r = camera.get_ray(u, v);
-                        pixel_color += Image::ray_color(Arc::clone(&world), r, max_depth);
+        let samples_per_pixel = samples_per_pixel as u32;
+        let pass_samples = match (self.pass_samples, self.time_limit) {
+            (Some(pass_samples), _) => pass_samples,
+            (None, Some(_)) => DEFAULT_PASS_SAMPLES,
+            (None, None) => samples_per_pixel,
+        }
+        .max(1);
+        let max_passes = samples_per_pixel.div_ceil(pass_samples).max(1);
+
+        let mut framebuffer = match &self.resumed {
+            Some(framebuffer) => framebuffer.clone(),
+            None => Framebuffer::new(self.width(), self.height()),
+        };
+
+        // The passes of a resumed framebuffer are done from the first one
+        // leaving pixels short of samples, which may have been cancelled
+        // halfway
+        let first_pass = (framebuffer.min_samples() / pass_samples).min(max_passes);
+
+        // Time-limited renders estimate their number of passes as they go
+        let mut passes = match self.time_limit {
+            Some(_) => (first_pass + 1).min(max_passes),
+            None => max_passes,
+        };
+
+        let tiles = tile::tiles(self.width(), self.height(), tile_size, tile_order);
+        let mut pb = ProgressBar::new((passes - first_pass) as u64 * tiles.len() as u64);
+
+        let start = Instant::now();
+
+        for pass in first_pass..max_passes {
+            if max_passes > 1 {
+                pb.message(&format!("Pass {}/{} ", pass + 1, passes));
+            }
+
+            // Samples every pixel has at the end of the pass, at most
+            let max_samples = samples_per_pixel.min((pass + 1) * pass_samples);
+
+            let (sender, receiver) = mpsc::channel();
+
+            // Once a tile fails, the others are skipped
+            let failed = AtomicBool::new(false);
+
+            let (next, failure) = pool.scope(|scope| {
+                for (index, &tile) in tiles.iter().enumerate() {
+                    let sender = sender.clone();
+                    let job = TileJob {
+                        tile,
+                        estimates: framebuffer.estimates(&tile),
+                        max_samples,
+                        max_depth,
+                    };
+                    let cancelled = self.cancelled;
+
+                    if let Some(workers) = &self.workers {
+                        workers.execute(index, job, cancelled, sender);
+                        continue;
                     }
-                    pixel_color.write(
-                        img.lock().unwrap().as_mut_rgb8().unwrap(),
-                        i,
-                        j,
-                        samples_per_pixel,
-                    )
+
+                    // Each tile rendering is spawned in the thread pool
+                    let failed = &failed;
+                    scope.spawn(move || {
+                        if cancelled.is_some_and(|cancelled| cancelled.load(Ordering::SeqCst))
+                            || failed.load(Ordering::SeqCst)
+                        {
+                            sender.send((index, TileOutcome::Skipped)).unwrap();
+                            return;
+                        }
+
+                        let outcome = match self.render_tile(job) {
+                            Ok(rendered) => TileOutcome::Rendered(rendered),
+                            Err(message) => TileOutcome::Failed(message),
+                        };
+                        sender.send((index, outcome)).unwrap();
+                    });
                 }
 
-                pb.lock().unwrap().inc();
+                // The channel closes once every job of the pass has sent its
+                // tile
+                drop(sender);
+
+                // Regions overlap when the filter is wider than a pixel, and
+                // float additions depend on their order: tiles are merged in
+                // the order of the list, whatever the order they finish in,
+                // for the result not to depend on the scheduling of the
+                // threads
+                let mut pending = BTreeMap::new();
+                let mut next = 0;
+                let mut failure = None;
+                for (index, outcome) in receiver {
+                    if let TileOutcome::Failed(_) = outcome {
+                        failed.store(true, Ordering::SeqCst);
+                    }
+
+                    pending.insert(index, outcome);
+                    while let Some(outcome) = pending.remove(&next) {
+                        match outcome {
+                            TileOutcome::Rendered(rendered) => {
+                                framebuffer.merge(&rendered.region, &rendered.pixels);
+                                framebuffer.set_estimates(&tiles[next], &rendered.estimates);
+                            }
+                            // Skipped tiles are left as they were
+                            TileOutcome::Skipped => (),
+                            TileOutcome::Failed(message) => {
+                                failure.get_or_insert(RenderError::TileFailed {
+                                    tile: tiles[next],
+                                    message,
+                                });
+                            }
+                        }
+                        next += 1;
+                    }
+                    pb.inc();
+                }
+
+                (next, failure)
             });
+
+            if let Some(failure) = failure {
+                pb.finish_print("Failed");
+                return Err(failure);
+            }
+
+            // Workers drop the tiles they cannot render once none is left
+            if next < tiles.len() {
+                pb.finish_print("Failed");
+                return Err(RenderError::WorkersDisconnected);
+            }
+
+            if self
+                .cancelled
+                .is_some_and(|cancelled| cancelled.load(Ordering::SeqCst))
+            {
+                pb.finish_print("Cancelled");
+                return Ok(framebuffer);
+            }
+
+            let done = pass + 1;
+            let elapsed = start.elapsed();
+            if done == max_passes || self.time_limit.is_some_and(|limit| elapsed >= limit) {
+                break;
+            }
+
+            if let Some(limit) = self.time_limit {
+                let pass_time = elapsed.as_secs_f64() / (done - first_pass) as f64;
+                passes = first_pass
+                    + ((limit.as_secs_f64() / pass_time).ceil() as u32)
+                        .clamp(done - first_pass + 1, max_passes - first_pass);
+                pb.total = (passes - first_pass) as u64 * tiles.len() as u64;
+            }
+
+            after_pass(&framebuffer, done);
         }
 
-        pool.wait_all();
+        pb.finish_print("Done!");
+
+        Ok(framebuffer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::checkpoint;
+    use crate::filter::MitchellFilter;
+    use crate::hittable::aabb::Aabb;
+    use crate::hittable::hittable_list::HittableList;
+    use crate::hittable::sphere::Sphere;
+    use crate::material::lambertian::Lambertian;
+    use crate::material::subsurface::Subsurface;
+    use crate::material::Material;
+    use crate::sampler::sobol::SobolSampler;
+    use crate::vec3::Point3;
+
+    /// A small image of a sphere on the ground, whose pixels overlap
+    fn image() -> Image {
+        image_of(Arc::new(world()))
+    }
+
+    fn world() -> HittableList {
+        let mut world = HittableList::new();
+        let material = Arc::new(Lambertian::new(Color::new(0.5, 0.2, 0.1)));
+        world.add(Arc::new(Sphere::new(
+            Point3::new(0.0, 0.0, -1.0),
+            0.5,
+            material.clone(),
+        )));
+        world.add(Arc::new(Sphere::new(
+            Point3::new(0.0, -100.5, -1.0),
+            100.0,
+            material,
+        )));
+
+        world
+    }
+
+    fn image_of(world: Arc<dyn Hittable + Sync + Send>) -> Image {
+        let camera = Camera::new(
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.0, 0.0, -1.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            90.0,
+            1.5,
+            0.0,
+            1.0,
+        );
+
+        Image::new(1.5, 24, world, Vec::new(), Arc::new(camera))
+            .with_filter(Arc::new(MitchellFilter::new(2.0, 1.0 / 3.0, 1.0 / 3.0)))
+            .with_sampler(Box::new(SobolSampler::new(7)))
+    }
 
-        pb.lock().unwrap().finish_print("Done!");
+    fn assert_same(expected: &Framebuffer, actual: &Framebuffer) {
+        for y in 0..expected.height() {
+            for x in 0..expected.width() {
+                assert_eq!(expected.color(x, y), actual.color(x, y));
+                assert_eq!(expected.weight(x, y), actual.weight(x, y));
+                assert_eq!(expected.samples(x, y), actual.samples(x, y));
+            }
+        }
+    }
 
-        let img = img.lock().unwrap().clone();
-        img
+    #[test]
+    fn resume_works() {
+        static CANCELLED: AtomicBool = AtomicBool::new(false);
+        let path = std::env::temp_dir().join(format!("resume_works_{}.ckpt", std::process::id()));
+
+        for adaptive_sampling in [
+            None,
+            Some(AdaptiveSampling {
+                min_samples: 2,
+                target_error: 0.05,
+            }),
+        ] {
+            let image = || {
+                let image = image().with_passes(2);
+                match adaptive_sampling {
+                    Some(adaptive_sampling) => image.with_adaptive_sampling(adaptive_sampling),
+                    None => image,
+                }
+            };
+            let whole = image()
+                .render_image(8, 5, 2, 8, TileOrder::Spiral, |_, _| ())
+                .unwrap();
+
+            // Stopped after two passes of four, checkpointed, then resumed
+            CANCELLED.store(false, Ordering::SeqCst);
+            let stopped = image()
+                .with_cancellation(&CANCELLED)
+                .render_image(8, 5, 2, 8, TileOrder::Spiral, |_, passes| {
+                    if passes == 2 {
+                        CANCELLED.store(true, Ordering::SeqCst);
+                    }
+                })
+                .unwrap();
+            assert!(stopped.total_samples() > 0);
+            assert!(stopped.total_samples() < whole.total_samples());
+
+            checkpoint::save(&stopped, &path, 0, 7).unwrap();
+            let resumed = image()
+                .with_framebuffer(checkpoint::load(&path).unwrap().framebuffer)
+                .render_image(8, 5, 2, 8, TileOrder::Spiral, |_, _| ())
+                .unwrap();
+
+            // As if never stopped
+            assert_same(&whole, &resumed);
+        }
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn time_limit_works() {
+        // A limit already reached still lets the first pass finish
+        let framebuffer = image()
+            .with_passes(2)
+            .with_time_limit(Duration::ZERO)
+            .render_image(8, 5, 1, 8, TileOrder::Spiral, |_, _| panic!("no pass left"))
+            .unwrap();
+        assert_eq!(2, framebuffer.min_samples());
+        assert_eq!(2.0, framebuffer.samples_per_pixel());
+
+        let framebuffer = image()
+            .with_passes(2)
+            .with_time_limit(Duration::from_secs(3600))
+            .render_image(8, 5, 1, 8, TileOrder::Spiral, |_, _| ())
+            .unwrap();
+        assert_eq!(8.0, framebuffer.samples_per_pixel());
+    }
+
+    #[test]
+    fn splat_works() {
+        // A sample on the edge of two pixels only counts in the right one
+        let filter: Arc<dyn Filter + Sync + Send> = Arc::new(BoxFilter::new(0.5));
+        let region = Tile {
+            x: 0,
+            y: 0,
+            width: 2,
+            height: 2,
+        };
+        let mut buffer = vec![Pixel::default(); 4];
+        Image::splat(
+            &filter,
+            &region,
+            &mut buffer,
+            (1.0, 1.0),
+            Color::new(1.0, 1.0, 1.0),
+        );
+
+        for pixel in &buffer[..3] {
+            assert_eq!(Pixel::default().to_bytes(), pixel.to_bytes());
+        }
+        assert_eq!(Color::new(1.0, 1.0, 1.0), buffer[3].color());
+    }
+
+    /// A material emitting white light and absorbing every ray
+    struct Emitter;
+
+    impl Material for Emitter {
+        fn scatter(&self, _: &Ray, _: &HitRecord, _: &mut dyn Sampler) -> Option<(Ray, Color)> {
+            None
+        }
+
+        fn emitted(&self, _record: &HitRecord) -> Color {
+            Color::new(1.0, 1.0, 1.0)
+        }
+    }
+
+    #[test]
+    fn walk_works() {
+        // A dense translucent sphere absorbing no light, lit by an enclosing
+        // sphere: every path leaves it, however many steps its walk takes
+        let mut world = HittableList::new();
+        world.add(Arc::new(Sphere::new(
+            Point3::zero(),
+            1.0,
+            Arc::new(Subsurface::new(
+                1.3,
+                Color::new(10.0, 10.0, 10.0),
+                Color::new(0.0, 0.0, 0.0),
+            )),
+        )));
+        world.add(Arc::new(Sphere::new(
+            Point3::zero(),
+            10.0,
+            Arc::new(Emitter),
+        )));
+
+        let mut sampler = SobolSampler::new(5);
+        let count = 2000;
+        let mut sum = Color::new(0.0, 0.0, 0.0);
+        for index in 0..count {
+            sampler.start_pixel_sample(0, 0, index);
+            let r = Ray::new(Point3::new(0.3, 0.2, 5.0), Vec3::new(0.0, 0.0, -1.0));
+            sum += Image::ray_color(&world, &[], r, 5, &mut sampler);
+        }
+
+        let mean = sum / count as f64;
+        assert!((1.0 - mean.x()).abs() < 0.02);
+        assert!((1.0 - mean.y()).abs() < 0.02);
+        assert!((1.0 - mean.z()).abs() < 0.02);
+    }
+
+    /// A world cancelling the rendering once it is first hit
+    struct CancellingWorld {
+        world: HittableList,
+        cancelled: &'static AtomicBool,
+    }
+
+    impl Hittable for CancellingWorld {
+        fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+            self.cancelled.store(true, Ordering::SeqCst);
+            self.world.hit(r, t_min, t_max)
+        }
+
+        fn bounding_box(&self) -> Option<Aabb> {
+            self.world.bounding_box()
+        }
+    }
+
+    #[test]
+    fn cancellation_works() {
+        static CANCELLED_BEFORE: AtomicBool = AtomicBool::new(true);
+        static CANCELLED_WHILE: AtomicBool = AtomicBool::new(false);
+
+        // Cancelled before rendering, no tile is started
+        let framebuffer = image()
+            .with_cancellation(&CANCELLED_BEFORE)
+            .render_image(8, 5, 2, 8, TileOrder::Spiral, |_, _| panic!("no pass done"))
+            .unwrap();
+        assert_eq!(0, framebuffer.total_samples());
+
+        // Cancelled by the first tile, which is finished while the others
+        // are skipped
+        let world = CancellingWorld {
+            world: world(),
+            cancelled: &CANCELLED_WHILE,
+        };
+        let framebuffer = image_of(Arc::new(world))
+            .with_cancellation(&CANCELLED_WHILE)
+            .render_image(8, 5, 1, 8, TileOrder::Spiral, |_, _| panic!("no pass done"))
+            .unwrap();
+
+        let tiles = tile::tiles(
+            framebuffer.width(),
+            framebuffer.height(),
+            8,
+            TileOrder::Spiral,
+        );
+        let samples = |tile: &Tile| {
+            let mut samples = (tile.y..tile.y + tile.height)
+                .flat_map(|y| (tile.x..tile.x + tile.width).map(move |x| (x, y)))
+                .map(|(x, y)| framebuffer.samples(x, y));
+            let first = samples.next().unwrap();
+            assert!(samples.all(|other| other == first));
+            first
+        };
+        assert_eq!(8, samples(&tiles[0]));
+        for tile in &tiles[1..] {
+            assert_eq!(0, samples(tile));
+        }
     }
 }
@@ -0,0 +1,30 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set once the user asks the render to stop, with Ctrl-C
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Catches the first Ctrl-C, for the render to stop after the tiles under
+/// way and be saved; the second one exits at once. Does nothing on systems
+/// without signals.
+pub fn catch_interrupt() {
+    #[cfg(unix)]
+    unsafe {
+        libc::signal(
+            libc::SIGINT,
+            on_interrupt as *const () as libc::sighandler_t,
+        );
+    }
+}
+
+#[cfg(unix)]
+extern "C" fn on_interrupt(_: libc::c_int) {
+    // Only async-signal-safe functions may be called here
+    if INTERRUPTED.swap(true, Ordering::SeqCst) {
+        unsafe { libc::_exit(130) };
+    }
+}
+
+/// Returns the flag set by Ctrl-C once `catch_interrupt` is called
+pub fn interrupted() -> &'static AtomicBool {
+    &INTERRUPTED
+}
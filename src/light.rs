@@ -0,0 +1,155 @@
+use crate::color::Color;
+use crate::vec3::{Point3, Vec3};
+
+/// Represents a light which cannot be hit by rays (a point or a light
+/// infinitely far away), so that it is sampled explicitly at each hit
+pub trait Light {
+    /// Returns the unit direction from `point` towards the light, the
+    /// distance to the light, and the light received at `point` by a
+    /// surface facing the light, or `None` if the light does not reach
+    /// `point`
+    fn illuminate(&self, point: Point3) -> Option<(Vec3, f64, Color)>;
+}
+
+/// A light shining equally in every direction from a point
+pub struct PointLight {
+    position: Point3,
+    intensity: Color,
+}
+
+impl PointLight {
+    pub fn new(position: Point3, intensity: Color) -> PointLight {
+        PointLight {
+            position,
+            intensity,
+        }
+    }
+}
+
+impl Light for PointLight {
+    fn illuminate(&self, point: Point3) -> Option<(Vec3, f64, Color)> {
+        let to_light = self.position - point;
+        let distance = to_light.length();
+
+        Some((
+            to_light / distance,
+            distance,
+            self.intensity / (distance * distance),
+        ))
+    }
+}
+
+/// A point light shining in a cone around `direction`: fully inside the
+/// inner angle, fading out up to the outer angle
+pub struct SpotLight {
+    position: Point3,
+    direction: Vec3,
+    intensity: Color,
+    cos_inner: f64,
+    cos_outer: f64,
+}
+
+impl SpotLight {
+    /// Constructs a new spot light, angles are in radians from the
+    /// direction
+    pub fn new(
+        position: Point3,
+        direction: Vec3,
+        intensity: Color,
+        inner_angle: f64,
+        outer_angle: f64,
+    ) -> SpotLight {
+        SpotLight {
+            position,
+            direction: direction.normalized(),
+            intensity,
+            cos_inner: inner_angle.cos(),
+            cos_outer: outer_angle.cos(),
+        }
+    }
+}
+
+impl Light for SpotLight {
+    fn illuminate(&self, point: Point3) -> Option<(Vec3, f64, Color)> {
+        let to_light = self.position - point;
+        let distance = to_light.length();
+        let to_light = to_light / distance;
+
+        let cos = Vec3::dot(&-to_light, &self.direction);
+        if cos <= self.cos_outer {
+            return None;
+        }
+
+        // Smooth falloff between the outer and the inner cones
+        let falloff = if cos >= self.cos_inner {
+            1.0
+        } else {
+            let x = (cos - self.cos_outer) / (self.cos_inner - self.cos_outer);
+            x * x * (3.0 - 2.0 * x)
+        };
+
+        Some((
+            to_light,
+            distance,
+            falloff * self.intensity / (distance * distance),
+        ))
+    }
+}
+
+/// A light infinitely far away, whose rays are all parallel (like the sun)
+pub struct DirectionalLight {
+    direction: Vec3,
+    irradiance: Color,
+}
+
+impl DirectionalLight {
+    /// Constructs a new directional light, whose rays travel along
+    /// `direction`
+    pub fn new(direction: Vec3, irradiance: Color) -> DirectionalLight {
+        DirectionalLight {
+            direction: direction.normalized(),
+            irradiance,
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn illuminate(&self, _point: Point3) -> Option<(Vec3, f64, Color)> {
+        Some((-self.direction, f64::INFINITY, self.irradiance))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn point_light_works() {
+        let light = PointLight::new(Point3::new(0.0, 2.0, 0.0), Color::new(4.0, 4.0, 4.0));
+        let (direction, distance, color) = light.illuminate(Point3::zero()).unwrap();
+
+        assert_eq!(Vec3::new(0.0, 1.0, 0.0), direction);
+        assert_eq!(2.0, distance);
+        assert_eq!(Color::new(1.0, 1.0, 1.0), color);
+    }
+
+    #[test]
+    fn spot_light_works() {
+        let light = SpotLight::new(
+            Point3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, -1.0, 0.0),
+            Color::new(1.0, 1.0, 1.0),
+            0.2,
+            0.4,
+        );
+
+        // Inside, on the edge and outside of the cone
+        assert_eq!(
+            Color::new(1.0, 1.0, 1.0),
+            light.illuminate(Point3::zero()).unwrap().2
+        );
+        let edge = light.illuminate(Point3::new(0.3, 0.0, 0.0)).unwrap().2;
+        assert!(0.0 < edge.x() && edge.x() < 1.0);
+        assert!(light.illuminate(Point3::new(1.0, 0.0, 0.0)).is_none());
+    }
+}
@@ -1,28 +1,77 @@
+use crate::adaptive::AdaptiveSampling;
+use crate::checkpoint::SceneHasher;
 use crate::color::Color;
+use crate::distributed::{Setup, Workers};
+use crate::filter::{BoxFilter, Filter, GaussianFilter, LanczosFilter, MitchellFilter, TentFilter};
+use crate::framebuffer::Framebuffer;
+use crate::hittable::aabb::Aabb;
+use crate::hittable::annulus::Annulus;
+use crate::hittable::cone::Cone;
+use crate::hittable::csg::{Csg, CsgOperation};
+use crate::hittable::cuboid::Cuboid;
+use crate::hittable::cylinder::Cylinder;
+use crate::hittable::disk::Disk;
+use crate::hittable::heightfield::Heightfield;
 use crate::hittable::hittable_list::HittableList;
+use crate::hittable::sdf::Sdf;
 use crate::hittable::sphere::Sphere;
+use crate::hittable::torus::Torus;
+use crate::hittable::triangle_mesh::TriangleMesh;
 use crate::hittable::Hittable;
-use crate::image::Image;
+use crate::image::{Image, DEFAULT_PASS_SAMPLES};
 use crate::material::dielectric::Dielectric;
 use crate::material::lambertian::Lambertian;
 use crate::material::metal::Metal;
+use crate::material::subsurface::Subsurface;
+use crate::output::{OutputFormat, Precision};
 use crate::random::{canonical_random, random_range};
+use crate::sampler::halton::HaltonSampler;
+use crate::sampler::independent::IndependentSampler;
+use crate::sampler::sobol::SobolSampler;
+use crate::sampler::stratified::StratifiedSampler;
+use crate::sampler::Sampler;
+use crate::scene::{Scene, SceneError, View};
+use crate::texture::vertex_color::VertexColor;
+use crate::tile::TileOrder;
+use crate::tone_mapping::{ToneMapper, ToneMapping};
 use crate::vec3::{Point3, Vec3};
 
-use std::path::PathBuf;
+use std::net::TcpListener;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use camera::Camera;
+use structopt::clap;
 use structopt::StructOpt;
 
+mod adaptive;
 mod camera;
+mod checkpoint;
 mod color;
+mod distributed;
+mod filter;
+mod framebuffer;
 mod hittable;
 mod image;
+mod interrupt;
+mod light;
 mod material;
+mod mesh;
+mod onb;
+mod output;
 mod parallel;
 mod random;
 mod ray;
+mod sampler;
+mod scene;
+mod sdf;
+mod texture;
+mod tile;
+mod tone_mapping;
+mod transform;
 mod vec3;
 
 #[derive(StructOpt)]
@@ -37,11 +86,253 @@ struct Opt {
     )]
     thread_number: Option<usize>,
 
-    #[structopt(parse(from_os_str), help = "Where to save the result (BMP file)")]
-    output: PathBuf,
+    #[structopt(
+        parse(from_os_str),
+        help = "Where to save the result, in the format given by its extension. Required but with --serve"
+    )]
+    output: Option<PathBuf>,
 
     #[structopt(short, long, help = "Print debug information")]
     debug: bool,
+
+    #[structopt(
+        long,
+        possible_values = &["bmp", "png", "png16", "ppm", "tga", "exr", "hdr", "pfm"],
+        help = "Format of the output, instead of guessing it from its extension. EXR, HDR and PFM are linear, png16 is 16-bit"
+    )]
+    format: Option<OutputFormat>,
+
+    #[structopt(
+        long,
+        default_value = "sobol",
+        possible_values = &["independent", "stratified", "halton", "sobol"],
+        help = "Sampler giving the samples of each pixel to the camera and materials"
+    )]
+    sampler: String,
+
+    #[structopt(
+        long,
+        help = "Seed of the random scene and of the samplers, making renders reproducible. Default is a random seed"
+    )]
+    seed: Option<u64>,
+
+    #[structopt(
+        long,
+        help = "Samples per pixel, the maximum with adaptive sampling or a time limit. Default is 100, or the one of the pbrt scene, or 65536 with a time limit"
+    )]
+    samples: Option<u32>,
+
+    #[structopt(
+        long,
+        parse(try_from_str = parse_duration),
+        help = "Time after which no more pass is started, such as 30s or 5m, rendering progressively"
+    )]
+    time_limit: Option<Duration>,
+
+    #[structopt(
+        long,
+        help = "Relative error of the pixels at which they stop being sampled, enabling adaptive sampling"
+    )]
+    target_error: Option<f64>,
+
+    #[structopt(
+        long,
+        default_value = "16",
+        help = "Samples taken in every pixel before estimating its error, with adaptive sampling"
+    )]
+    min_samples: u32,
+
+    #[structopt(
+        long,
+        parse(from_os_str),
+        help = "Where to save an image of the samples taken in each pixel, from black for the fewest to yellow for the most"
+    )]
+    spp_heatmap: Option<PathBuf>,
+
+    #[structopt(
+        long,
+        help = "Samples per pixel of each pass over the image, rendering progressively and saving the image as it goes"
+    )]
+    pass_samples: Option<u32>,
+
+    #[structopt(
+        long,
+        help = "Passes between two saves of the image, rendering progressively. Default is every pass, unless --snapshot-interval is given"
+    )]
+    snapshot_passes: Option<u32>,
+
+    #[structopt(
+        long,
+        parse(try_from_str = parse_duration),
+        help = "Time between two saves of the image, rendering progressively, such as 30s or 5m"
+    )]
+    snapshot_interval: Option<Duration>,
+
+    #[structopt(
+        long,
+        parse(from_os_str),
+        help = "Where to save the state of the render every --checkpoint-interval, rendering progressively, to resume it with --resume if it is interrupted"
+    )]
+    checkpoint: Option<PathBuf>,
+
+    #[structopt(
+        long,
+        default_value = "1m",
+        parse(try_from_str = parse_duration),
+        help = "Time between two checkpoints, taken between passes"
+    )]
+    checkpoint_interval: Duration,
+
+    #[structopt(
+        long,
+        requires = "checkpoint",
+        help = "Resume the render saved in the checkpoint file, which must be of the same scene and settings, with its seed"
+    )]
+    resume: bool,
+
+    #[structopt(
+        long,
+        use_delimiter = true,
+        number_of_values = 1,
+        help = "Addresses of workers started with --serve rendering the tiles, such as host:7878, separated by commas"
+    )]
+    workers: Vec<String>,
+
+    #[structopt(
+        long,
+        help = "Render the tiles of the coordinators connecting to this address, such as 0.0.0.0:7878, instead of an image"
+    )]
+    serve: Option<String>,
+
+    #[structopt(
+        long,
+        default_value = "box",
+        possible_values = &["box", "tent", "gaussian", "mitchell", "lanczos"],
+        help = "Filter reconstructing pixels from the samples around them"
+    )]
+    filter: String,
+
+    #[structopt(
+        long,
+        help = "Radius of the filter, in pixels. Default is 0.5 for box, 1 for tent, 1.5 for gaussian, 2 for mitchell and 3 for lanczos"
+    )]
+    filter_radius: Option<f64>,
+
+    #[structopt(
+        long,
+        default_value = "0",
+        allow_hyphen_values = true,
+        help = "Exposure compensation of integer formats, in stops"
+    )]
+    exposure: f64,
+
+    #[structopt(
+        long,
+        default_value = "clamp",
+        possible_values = &["clamp", "reinhard", "extended-reinhard", "aces", "agx"],
+        help = "Tone mapping operator of integer formats, applied before sRGB encoding"
+    )]
+    tone_mapper: ToneMapper,
+
+    #[structopt(
+        long,
+        default_value = "4",
+        help = "Luminance mapped to white by the extended-reinhard tone mapper"
+    )]
+    white_point: f64,
+
+    #[structopt(
+        long,
+        default_value = "half",
+        possible_values = &["half", "float"],
+        help = "Type of the samples of OpenEXR files"
+    )]
+    exr_precision: Precision,
+
+    #[structopt(
+        long,
+        default_value = "32",
+        parse(try_from_str = parse_tile_size),
+        help = "Size of the square tiles rendered by threads, in pixels"
+    )]
+    tile_size: u32,
+
+    #[structopt(
+        long,
+        default_value = "spiral",
+        possible_values = &["spiral", "hilbert"],
+        help = "Order in which tiles are rendered"
+    )]
+    tile_order: TileOrder,
+
+    #[structopt(
+        long,
+        default_value = "random",
+        possible_values = &["random", "subsurface", "csg", "quadrics", "sdf", "terrain", "mesh", "gltf", "pbrt"],
+        help = "Scene to render"
+    )]
+    scene: String,
+
+    #[structopt(
+        long,
+        parse(from_os_str),
+        help = "Grayscale image used as height map by the terrain scene"
+    )]
+    heightmap: Option<PathBuf>,
+
+    #[structopt(
+        long,
+        parse(from_os_str),
+        help = "PLY or STL file rendered by the mesh scene"
+    )]
+    mesh: Option<PathBuf>,
+
+    #[structopt(
+        long,
+        parse(from_os_str),
+        help = "glTF 2.0 file (.gltf or .glb) rendered by the gltf scene, with its camera"
+    )]
+    gltf: Option<PathBuf>,
+
+    #[structopt(
+        long,
+        parse(from_os_str),
+        help = "pbrt-v3 file rendered by the pbrt scene, with its camera, film and sampler settings"
+    )]
+    pbrt: Option<PathBuf>,
+}
+
+/// Parses a duration given in seconds, or with a unit among `ms`, `s`, `m`
+/// and `h`
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let (number, unit) = match s.find(|c: char| c.is_ascii_alphabetic()) {
+        Some(index) => s.split_at(index),
+        None => (s, "s"),
+    };
+    let seconds = match unit {
+        "ms" => 0.001,
+        "s" => 1.0,
+        "m" => 60.0,
+        "h" => 3600.0,
+        _ => return Err(format!("unknown unit of duration: {}", unit)),
+    };
+
+    // Durations too long for a `Duration` are invalid too
+    number
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .and_then(|number| Duration::try_from_secs_f64(number * seconds).ok())
+        .ok_or_else(|| format!("invalid duration: {}", s))
+}
+
+/// Parses the size of the tiles, which must hold a pixel at least
+fn parse_tile_size(s: &str) -> Result<u32, String> {
+    match s.parse::<u32>() {
+        Ok(0) => Err("the tile size must be at least 1".to_string()),
+        Ok(size) => Ok(size),
+        Err(err) => Err(err.to_string()),
+    }
 }
 
 /// Generate a scene with random small spheres and three big spheres
@@ -109,6 +400,566 @@ fn random_scene() -> Arc<dyn Hittable + Sync + Send> {
     Arc::new(world)
 }
 
+/// Generate a scene with three big translucent spheres (wax, jade and skin)
+fn subsurface_scene() -> Arc<dyn Hittable + Sync + Send> {
+    let mut world = HittableList::new();
+
+    let ground_material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        ground_material,
+    )));
+
+    let wax = Arc::new(Subsurface::new(
+        1.45,
+        Color::new(9.0, 8.5, 7.0),
+        Color::new(0.02, 0.05, 0.2),
+    ));
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, 1.0, 0.0), 1.0, wax)));
+
+    let jade = Arc::new(Subsurface::new(
+        1.66,
+        Color::new(4.0, 4.0, 4.0),
+        Color::new(1.2, 0.1, 0.8),
+    ));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(-4.0, 1.0, 0.0),
+        1.0,
+        jade,
+    )));
+
+    let skin = Arc::new(Subsurface::new(
+        1.4,
+        Color::new(7.4, 8.8, 10.0),
+        Color::new(0.3, 1.2, 2.0),
+    ));
+    world.add(Arc::new(Sphere::new(Point3::new(4.0, 1.0, 0.0), 1.0, skin)));
+
+    Arc::new(world)
+}
+
+/// Generate a scene with objects made by constructive solid geometry
+fn csg_scene() -> Arc<dyn Hittable + Sync + Send> {
+    let mut world = HittableList::new();
+
+    let ground_material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        ground_material,
+    )));
+
+    // A sphere with a box bitten out of it
+    let red = Arc::new(Lambertian::new(Color::new(0.7, 0.1, 0.1)));
+    world.add(Arc::new(Csg::new(
+        CsgOperation::Difference,
+        Arc::new(Sphere::new(Point3::new(0.0, 1.0, 0.0), 1.0, red.clone())),
+        Arc::new(Cuboid::new(
+            Point3::new(0.0, 1.0, 0.0),
+            Point3::new(2.0, 3.0, 2.0),
+            red,
+        )),
+    )));
+
+    // A lens made of two intersecting spheres
+    let glass = Arc::new(Dielectric::new(1.5));
+    world.add(Arc::new(Csg::new(
+        CsgOperation::Intersection,
+        Arc::new(Sphere::new(
+            Point3::new(-4.0, 1.0, -0.7),
+            1.0,
+            glass.clone(),
+        )),
+        Arc::new(Sphere::new(Point3::new(-4.0, 1.0, 0.7), 1.0, glass)),
+    )));
+
+    // A glass peanut: no inner surfaces where both spheres overlap
+    let glass = Arc::new(Dielectric::new(1.5));
+    world.add(Arc::new(Csg::new(
+        CsgOperation::Union,
+        Arc::new(Sphere::new(Point3::new(1.0, 0.5, 2.5), 0.5, glass.clone())),
+        Arc::new(Sphere::new(Point3::new(1.0, 0.5, 3.2), 0.5, glass)),
+    )));
+
+    // A box with rounded corners cut in a sphere, minus a smaller sphere
+    let metal = Arc::new(Metal::new(Color::new(0.7, 0.6, 0.5), 0.1));
+    let rounded = Arc::new(Csg::new(
+        CsgOperation::Intersection,
+        Arc::new(Cuboid::new(
+            Point3::new(3.2, 0.2, -0.8),
+            Point3::new(4.8, 1.8, 0.8),
+            metal.clone(),
+        )),
+        Arc::new(Sphere::new(Point3::new(4.0, 1.0, 0.0), 1.05, metal.clone())),
+    ));
+    world.add(Arc::new(Csg::new(
+        CsgOperation::Difference,
+        rounded,
+        Arc::new(Sphere::new(Point3::new(4.8, 1.0, 0.0), 0.5, metal)),
+    )));
+
+    Arc::new(world)
+}
+
+/// Generate a scene with pipes, lamp shades and rings
+fn quadrics_scene() -> Arc<dyn Hittable + Sync + Send> {
+    let mut world = HittableList::new();
+
+    let ground_material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        ground_material,
+    )));
+
+    // An open pipe lying on the ground
+    let copper = Arc::new(Metal::new(Color::new(0.8, 0.5, 0.3), 0.2));
+    world.add(Arc::new(Cylinder::new(
+        Point3::new(-4.0, 0.5, -2.0),
+        Point3::new(-4.0, 0.5, 2.0),
+        0.5,
+        false,
+        copper,
+    )));
+
+    // A closed cylinder and a lamp shade above it
+    let blue = Arc::new(Lambertian::new(Color::new(0.1, 0.2, 0.6)));
+    world.add(Arc::new(Cylinder::new(
+        Point3::new(0.0, 0.0, 0.0),
+        Point3::new(0.0, 1.2, 0.0),
+        0.15,
+        true,
+        blue,
+    )));
+    let shade = Arc::new(Lambertian::new(Color::new(0.9, 0.8, 0.5)));
+    world.add(Arc::new(Cone::new(
+        Point3::new(0.0, 1.0, 0.0),
+        Point3::new(0.0, 1.8, 0.0),
+        0.8,
+        0.4,
+        false,
+        shade,
+    )));
+
+    // A cone standing on its base
+    let green = Arc::new(Lambertian::new(Color::new(0.2, 0.6, 0.2)));
+    world.add(Arc::new(Cone::new(
+        Point3::new(2.0, 0.0, -2.0),
+        Point3::new(2.0, 1.2, -2.0),
+        0.5,
+        0.0,
+        true,
+        green,
+    )));
+
+    // A golden ring facing the camera
+    let gold = Arc::new(Metal::new(Color::new(0.9, 0.7, 0.2), 0.05));
+    world.add(Arc::new(Torus::new(
+        Point3::new(4.0, 1.0, 0.0),
+        Vec3::new(1.0, 0.3, 0.2),
+        0.8,
+        0.2,
+        gold,
+    )));
+
+    // A mirror disk on the ground and a ring floating above it
+    let mirror = Arc::new(Metal::new(Color::new(0.9, 0.9, 0.9), 0.0));
+    world.add(Arc::new(Disk::new(
+        Point3::new(2.0, 0.01, 2.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        0.7,
+        mirror,
+    )));
+    let red = Arc::new(Lambertian::new(Color::new(0.7, 0.1, 0.1)));
+    world.add(Arc::new(Annulus::new(
+        Point3::new(2.0, 1.0, 2.0),
+        Vec3::new(1.0, 0.0, 0.0),
+        0.3,
+        0.6,
+        red,
+    )));
+
+    Arc::new(world)
+}
+
+/// Generate a scene with procedural objects described by signed distance
+/// functions
+fn sdf_scene() -> Arc<dyn Hittable + Sync + Send> {
+    let mut world = HittableList::new();
+
+    let ground_material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        ground_material,
+    )));
+
+    // A fractal
+    let metal = Arc::new(Metal::new(Color::new(0.7, 0.6, 0.5), 0.05));
+    world.add(Arc::new(Sdf::new(
+        sdf::menger_sponge(Point3::new(-4.0, 1.0, 0.0), 1.0, 4),
+        Aabb::new(Point3::new(-5.0, 0.0, -1.0), Point3::new(-3.0, 2.0, 1.0)).padded(),
+        metal,
+    )));
+
+    // Blobs melting together
+    let blob = sdf::smooth_union(
+        sdf::smooth_union(
+            sdf::sphere(Point3::new(0.0, 0.7, -0.4), 0.7),
+            sdf::sphere(Point3::new(0.0, 0.7, 0.5), 0.6),
+            0.4,
+        ),
+        sdf::sphere(Point3::new(0.0, 1.5, 0.0), 0.5),
+        0.4,
+    );
+    let blue = Arc::new(Lambertian::new(Color::new(0.1, 0.2, 0.6)));
+    world.add(Arc::new(Sdf::new(
+        blob,
+        Aabb::new(Point3::new(-1.0, -0.2, -1.4), Point3::new(1.0, 2.2, 1.4)),
+        blue,
+    )));
+
+    // A rounded box cut by a sphere, with a hole
+    let rounded = sdf::difference(
+        sdf::intersection(
+            sdf::rounded_box(Point3::new(4.0, 1.0, 0.0), Vec3::new(0.8, 0.8, 0.8), 0.1),
+            sdf::sphere(Point3::new(4.0, 1.0, 0.0), 1.05),
+        ),
+        sdf::sphere(Point3::new(4.8, 1.0, 0.0), 0.5),
+    );
+    let red = Arc::new(Lambertian::new(Color::new(0.7, 0.1, 0.1)));
+    world.add(Arc::new(Sdf::new(
+        rounded,
+        Aabb::new(Point3::new(3.2, 0.2, -0.8), Point3::new(4.8, 1.8, 0.8)).padded(),
+        red,
+    )));
+
+    // A ring around a ball
+    let ring = sdf::union(
+        sdf::torus(Point3::new(2.0, 0.5, 2.5), 0.5, 0.1),
+        sdf::sphere(Point3::new(2.0, 0.5, 2.5), 0.25),
+    );
+    let glass = Arc::new(Dielectric::new(1.5));
+    world.add(Arc::new(Sdf::new(
+        ring,
+        Aabb::new(Point3::new(1.4, 0.2, 1.9), Point3::new(2.6, 0.8, 3.1)),
+        glass,
+    )));
+
+    Arc::new(world)
+}
+
+/// Generate a scene with a terrain, either from the given height map or
+/// made of a few hills
+fn terrain_scene(heightmap: Option<&Path>) -> Arc<dyn Hittable + Sync + Send> {
+    let mut world = HittableList::new();
+
+    let origin = Point3::new(-10.0, 0.0, -10.0);
+    let size = Vec3::new(20.0, 2.0, 20.0);
+    let ground_material = Arc::new(Lambertian::new(Color::new(0.4, 0.5, 0.3)));
+
+    let terrain = match heightmap {
+        Some(path) => Heightfield::from_image(path, origin, size, ground_material)
+            .expect("An error occurred while reading the height map."),
+        None => {
+            let resolution = 200;
+            let heights = (0..resolution)
+                .map(|j| {
+                    let z = j as f64 / (resolution - 1) as f64 * 20.0;
+                    (0..resolution)
+                        .map(|i| {
+                            let x = i as f64 / (resolution - 1) as f64 * 20.0;
+                            let hills = (0.6 * x).sin() * (0.4 * z).cos()
+                                + 0.3 * (1.7 * x + 0.5).sin() * (1.3 * z).sin();
+                            0.5 + 0.35 * hills
+                        })
+                        .collect()
+                })
+                .collect();
+            Heightfield::new(heights, origin, size, ground_material)
+        }
+    };
+    world.add(Arc::new(terrain));
+
+    let glass = Arc::new(Dielectric::new(1.5));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, 2.5, 0.0),
+        1.0,
+        glass,
+    )));
+
+    Arc::new(world)
+}
+
+/// Generate a scene with the mesh of the given file, colored by its vertex
+/// colors if it has some
+fn mesh_scene(path: Option<&Path>) -> Arc<dyn Hittable + Sync + Send> {
+    let mut world = HittableList::new();
+
+    let ground_material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        ground_material,
+    )));
+
+    let path = path.expect("The mesh scene needs a mesh file (--mesh).");
+    let mut data = mesh::load(path).expect("An error occurred while reading the mesh.");
+    data.fit(Point3::new(0.0, 1.0, 0.0), 2.0);
+
+    let material = Arc::new(Lambertian::from_texture(Arc::new(VertexColor::new(
+        Color::new(0.7, 0.7, 0.7),
+    ))));
+    world.add(Arc::new(
+        TriangleMesh::new(data, material).expect("An error occurred while reading the mesh."),
+    ));
+
+    Arc::new(world)
+}
+
+/// Builds the image of the scene given by the options, with its filter,
+/// sampler and adaptive sampling, and returns it with its samples per pixel.
+/// Workers build theirs the same way, from the options of the coordinator.
+fn build_image(opt: &Opt, seed: u64) -> (Image, i32) {
+    // Everything random derives from the seed
+    random::seed(seed);
+
+    let scene = match opt.scene.as_str() {
+        "gltf" => scene::gltf::load(
+            opt.gltf
+                .as_deref()
+                .expect("The gltf scene needs a glTF file (--gltf)."),
+        )
+        .expect("An error occurred while reading the glTF file."),
+        "pbrt" => scene::pbrt::load(
+            opt.pbrt
+                .as_deref()
+                .expect("The pbrt scene needs a pbrt file (--pbrt)."),
+        )
+        .expect("An error occurred while reading the pbrt file."),
+        name => Scene {
+            world: match name {
+                "subsurface" => subsurface_scene(),
+                "csg" => csg_scene(),
+                "quadrics" => quadrics_scene(),
+                "sdf" => sdf_scene(),
+                "terrain" => terrain_scene(opt.heightmap.as_deref()),
+                "mesh" => mesh_scene(opt.mesh.as_deref()),
+                _ => random_scene(),
+            },
+            lights: Vec::new(),
+            view: None,
+            resolution: None,
+            samples_per_pixel: None,
+        },
+    };
+
+    let view = scene.view.unwrap_or(View {
+        lookfrom: Point3::new(13.0, 2.0, 3.0),
+        lookat: Point3::new(0.0, 0.0, 0.0),
+        vup: Vec3::new(0.0, 1.0, 0.0),
+        vertical_fov: 20.0,
+        aperture: 0.1,
+        focus_dist: 10.0,
+        aspect_ratio: None,
+    });
+
+    // Image
+    let (aspect_ratio, image_width) = match scene.resolution {
+        Some((width, height)) => (width as f64 / height as f64, width),
+        None => (view.aspect_ratio.unwrap_or(3.0 / 2.0), 500), // 1200
+    };
+
+    let camera = Arc::new(Camera::new(
+        view.lookfrom,
+        view.lookat,
+        view.vup,
+        view.vertical_fov,
+        aspect_ratio,
+        view.aperture,
+        view.focus_dist,
+    ));
+
+    let filter: Arc<dyn Filter + Sync + Send> = match opt.filter.as_str() {
+        "tent" => Arc::new(TentFilter::new(opt.filter_radius.unwrap_or(1.0))),
+        "gaussian" => Arc::new(GaussianFilter::new(opt.filter_radius.unwrap_or(1.5), 2.0)),
+        "mitchell" => Arc::new(MitchellFilter::new(
+            opt.filter_radius.unwrap_or(2.0),
+            1.0 / 3.0,
+            1.0 / 3.0,
+        )),
+        "lanczos" => Arc::new(LanczosFilter::new(opt.filter_radius.unwrap_or(3.0))),
+        _ => Arc::new(BoxFilter::new(opt.filter_radius.unwrap_or(0.5))),
+    };
+
+    let samples_per_pixel = match (opt.samples, opt.time_limit) {
+        (Some(samples), _) => samples as i32,
+        (None, Some(_)) => 1 << 16,
+        (None, None) => scene.samples_per_pixel.unwrap_or(100), // 500
+    };
+
+    let sampler: Box<dyn Sampler + Send + Sync> = match opt.sampler.as_str() {
+        "stratified" => Box::new(StratifiedSampler::new(samples_per_pixel as u32, seed)),
+        "halton" => Box::new(HaltonSampler::new(seed)),
+        "sobol" => Box::new(SobolSampler::new(seed)),
+        _ => Box::new(IndependentSampler::new(seed)),
+    };
+
+    let mut image = Image::new(
+        aspect_ratio,
+        image_width,
+        Arc::clone(&scene.world),
+        scene.lights,
+        Arc::clone(&camera),
+    )
+    .with_filter(filter)
+    .with_sampler(sampler);
+    if let Some(target_error) = opt.target_error {
+        image = image.with_adaptive_sampling(AdaptiveSampling {
+            min_samples: opt.min_samples,
+            target_error,
+        });
+    }
+
+    (image, samples_per_pixel)
+}
+
+/// Returns the options workers need to build the same image as the
+/// coordinator, with its seed and samples per pixel
+fn worker_setup(opt: &Opt, seed: u64, samples_per_pixel: i32) -> Result<Setup, SceneError> {
+    let mut args = vec![
+        format!("--scene={}", opt.scene),
+        format!("--seed={}", seed),
+        format!("--samples={}", samples_per_pixel),
+        format!("--sampler={}", opt.sampler),
+        format!("--filter={}", opt.filter),
+        format!("--min-samples={}", opt.min_samples),
+    ];
+    if let Some(filter_radius) = opt.filter_radius {
+        args.push(format!("--filter-radius={}", filter_radius));
+    }
+    if let Some(target_error) = opt.target_error {
+        args.push(format!("--target-error={}", target_error));
+    }
+
+    // Files are sent along with the options, with the meshes of pbrt scenes
+    // and the buffers and images of glTF ones. Files given by absolute paths
+    // are left to the workers.
+    let mut files = Vec::new();
+    let paths = [
+        ("--gltf", &opt.gltf),
+        ("--pbrt", &opt.pbrt),
+        ("--heightmap", &opt.heightmap),
+        ("--mesh", &opt.mesh),
+    ];
+    for (option, path) in paths {
+        if let Some(path) = path {
+            let dependencies = match option {
+                "--pbrt" => scene::pbrt::dependencies(path)?,
+                "--gltf" => scene::gltf::dependencies(path)?,
+                _ => Vec::new(),
+            }
+            .into_iter()
+            .filter(|dependency| Path::new(dependency).is_relative())
+            .collect();
+            args.push(option.to_string());
+            files.push((args.len(), dependencies));
+            args.push(path.to_string_lossy().into_owned());
+        }
+    }
+
+    Ok(Setup { args, files })
+}
+
+/// Renders the tiles of the coordinators connecting to the address, building
+/// their images from the options they send
+fn serve(address: &str, thread_number: usize) {
+    let listener = TcpListener::bind(address).unwrap_or_else(|err| {
+        clap::Error::with_description(
+            &format!("cannot listen to {}: {}", address, err),
+            clap::ErrorKind::Io,
+        )
+        .exit()
+    });
+
+    distributed::serve(listener, thread_number, |args| {
+        let opt =
+            Opt::from_iter_safe(std::iter::once("worker").chain(args.iter().map(String::as_str)))
+                .map_err(|err| err.message)?;
+        let seed = opt.seed.ok_or("the coordinator sent no seed")?;
+
+        // Scenes failing to load panic
+        panic::catch_unwind(AssertUnwindSafe(|| build_image(&opt, seed).0)).map_err(|payload| {
+            format!(
+                "the scene cannot be built: {}",
+                parallel::panic_message(&*payload)
+            )
+        })
+    })
+    .expect("An error occurred while serving coordinators.");
+}
+
+/// Hashes what the samples of a render depend on: the scene, with the files
+/// it is read from, the seed and the settings of the render, down to the
+/// tiles and passes. The samples per pixel are left out, for a render to be
+/// resumed with more samples, unless the sampler depends on them.
+fn scene_hash(
+    opt: &Opt,
+    seed: u64,
+    (width, height): (u32, u32),
+    samples_per_pixel: i32,
+    max_depth: i32,
+) -> Result<u64, SceneError> {
+    let mut hasher = SceneHasher::new();
+    let mut description = format!(
+        "scene {}\nseed {}\nresolution {}x{}\nfilter {} {:?}\nsampler {}\nmax depth {}\n",
+        opt.scene, seed, width, height, opt.filter, opt.filter_radius, opt.sampler, max_depth
+    );
+    if opt.sampler == "stratified" {
+        description += &format!("samples {}\n", samples_per_pixel);
+    }
+    if let Some(target_error) = opt.target_error {
+        description += &format!("adaptive sampling {} {}\n", target_error, opt.min_samples);
+    }
+
+    // Tiles and passes set the order samples are added to pixels in, which
+    // float sums depend on
+    description += &format!(
+        "tiles {} {:?}\npass samples {}\n",
+        opt.tile_size,
+        opt.tile_order,
+        opt.pass_samples.unwrap_or(DEFAULT_PASS_SAMPLES)
+    );
+    hasher.write(description.as_bytes());
+
+    let files = match opt.scene.as_str() {
+        "gltf" => opt.gltf.as_deref(),
+        "pbrt" => opt.pbrt.as_deref(),
+        "terrain" => opt.heightmap.as_deref(),
+        "mesh" => opt.mesh.as_deref(),
+        _ => None,
+    };
+    if let Some(path) = files {
+        hasher.write_file(path)?;
+
+        // With the meshes, buffers and images the scene file refers to,
+        // relative to its directory
+        let dependencies = match opt.scene.as_str() {
+            "gltf" => scene::gltf::dependencies(path)?,
+            "pbrt" => scene::pbrt::dependencies(path)?,
+            _ => Vec::new(),
+        };
+        let directory = path.parent().unwrap_or_else(|| Path::new(""));
+        for dependency in dependencies {
+            hasher.write(dependency.as_bytes());
+            hasher.write_file(&directory.join(dependency))?;
+        }
+    }
+
+    Ok(hasher.finish())
+}
+
 fn main() {
     let opt = Opt::from_args();
 
@@ -121,50 +972,296 @@ fn main() {
         num_cpus::get()
     };
 
+    if let Some(address) = opt.serve.as_deref() {
+        serve(address, thread_number);
+        return;
+    }
+
+    let output = opt.output.as_deref().unwrap_or_else(|| {
+        clap::Error::with_description(
+            "the output is required, unless serving with --serve",
+            clap::ErrorKind::MissingRequiredArgument,
+        )
+        .exit()
+    });
+
+    // Check the output format before rendering
+    let format = match opt.format {
+        Some(format) => format,
+        None => OutputFormat::from_path(output).unwrap_or_else(|message| {
+            clap::Error::with_description(
+                &format!("{} (use --format to choose one)", message),
+                clap::ErrorKind::InvalidValue,
+            )
+            .exit()
+        }),
+    };
+
+    if let Some(path) = opt.spp_heatmap.as_deref() {
+        match OutputFormat::from_path(path) {
+            Ok(OutputFormat::Bmp | OutputFormat::Png | OutputFormat::Ppm | OutputFormat::Tga) => (),
+            _ => clap::Error::with_description(
+                "the heatmap of the samples is saved as BMP, PNG, PPM or TGA",
+                clap::ErrorKind::InvalidValue,
+            )
+            .exit(),
+        }
+    }
+
+    let resumed = opt
+        .checkpoint
+        .as_deref()
+        .filter(|_| opt.resume)
+        .map(|path| {
+            checkpoint::load(path).unwrap_or_else(|err| {
+                clap::Error::with_description(&err.to_string(), clap::ErrorKind::Io).exit()
+            })
+        });
+
+    // The seed of the checkpoint is kept when resuming
+    let seed = opt
+        .seed
+        .or(resumed.as_ref().map(|checkpoint| checkpoint.seed))
+        .unwrap_or_else(rand::random);
+
     if opt.debug {
         eprintln!("--- DEBUG ---");
         eprintln!("Thread number: {}", thread_number);
-        eprintln!("Output file: {:?}", opt.output);
+        eprintln!("Output file: {:?} ({:?})", output, format);
+        eprintln!("Scene: {}", opt.scene);
+        eprintln!("Tiles: {0}x{0}, {1:?} order", opt.tile_size, opt.tile_order);
+        eprintln!("Filter: {}", opt.filter);
+        eprintln!("Sampler: {}", opt.sampler);
+        eprintln!("Seed: {}", seed);
+        if !opt.workers.is_empty() {
+            eprintln!("Workers: {}", opt.workers.join(", "));
+        }
         eprintln!();
     }
 
-    // Image
-    let aspect_ratio = 3.0 / 2.0;
-    let image_width = 500; // 1200
-
-    let lookfrom = Point3::new(13.0, 2.0, 3.0);
-    let lookat = Point3::new(0.0, 0.0, 0.0);
-    let vup = Vec3::new(0.0, 1.0, 0.0);
-    let focus_dist = 10.0;
-    let aperture = 0.1;
-    let vertical_fov = 20.0;
+    let (mut image, samples_per_pixel) = build_image(&opt, seed);
+    let max_depth = 50;
 
-    let world = random_scene();
+    // Resuming another scene or other settings would mix their samples
+    let scene_hash = scene_hash(
+        &opt,
+        seed,
+        (image.width(), image.height()),
+        samples_per_pixel,
+        max_depth,
+    )
+    .expect("An error occurred while reading the scene files.");
+    if let Some(checkpoint) = &resumed {
+        if checkpoint.scene_hash != scene_hash {
+            clap::Error::with_description(
+                "the checkpoint is of another scene or other settings, refusing to resume it",
+                clap::ErrorKind::InvalidValue,
+            )
+            .exit()
+        }
+    }
 
-    let camera = Arc::new(Camera::new(
-        lookfrom,
-        lookat,
-        vup,
-        vertical_fov,
-        aspect_ratio,
-        aperture,
-        focus_dist,
-    ));
+    // Snapshots and checkpoints are taken between passes
+    let progressive = opt.checkpoint.is_some()
+        || opt.snapshot_passes.is_some()
+        || opt.snapshot_interval.is_some();
+    match opt.pass_samples {
+        Some(pass_samples) => image = image.with_passes(pass_samples),
+        None if progressive => image = image.with_passes(DEFAULT_PASS_SAMPLES),
+        None => (),
+    }
+    if let Some(time_limit) = opt.time_limit {
+        image = image.with_time_limit(time_limit);
+    }
+    if !opt.workers.is_empty() {
+        let setup = worker_setup(&opt, seed, samples_per_pixel)
+            .expect("An error occurred while reading the scene files.");
+        let workers = Workers::connect(&opt.workers, &setup, image.width(), image.height())
+            .unwrap_or_else(|err| {
+                clap::Error::with_description(
+                    &format!("cannot connect to worker {}", err),
+                    clap::ErrorKind::Io,
+                )
+                .exit()
+            });
+        image = image.with_workers(workers);
+    }
+    interrupt::catch_interrupt();
+    image = image.with_cancellation(interrupt::interrupted());
+    if let Some(checkpoint) = resumed {
+        if opt.debug {
+            eprintln!(
+                "Resuming with {:.1} samples per pixel",
+                checkpoint.framebuffer.samples_per_pixel()
+            );
+        }
+        image = image.with_framebuffer(checkpoint.framebuffer);
+    }
 
-    let image = Image::new(
-        aspect_ratio,
-        image_width,
-        Arc::clone(&world),
-        Arc::clone(&camera),
-    );
+    let tone_mapping = ToneMapping {
+        exposure: opt.exposure,
+        tone_mapper: opt.tone_mapper,
+        white_point: opt.white_point,
+    };
 
     // Rendering
-    let samples_per_pixel = 100; // 500
-    let max_depth = 50;
+    let save_checkpoint = |framebuffer: &Framebuffer| {
+        if let Some(path) = opt.checkpoint.as_deref() {
+            checkpoint::save(framebuffer, path, scene_hash, seed)
+                .expect("An error occurred while writing the checkpoint.");
+        }
+    };
+
+    // Progressive renders are saved every few passes, the last one being
+    // saved like any render, and checkpointed at intervals
+    let mut last_snapshot = (0, Instant::now());
+    let mut last_checkpoint = Instant::now();
+    let after_pass = |framebuffer: &Framebuffer, passes: u32| {
+        let by_passes = match (opt.snapshot_passes, opt.snapshot_interval) {
+            (None, None) => Some(1),
+            (snapshot_passes, _) => snapshot_passes,
+        };
+        let due = by_passes.is_some_and(|every| passes - last_snapshot.0 >= every)
+            || opt
+                .snapshot_interval
+                .is_some_and(|interval| last_snapshot.1.elapsed() >= interval);
+        if due {
+            output::save(
+                framebuffer,
+                output,
+                format,
+                &tone_mapping,
+                opt.exr_precision,
+            )
+            .expect("An error occurred while writing the image to the file.");
+            last_snapshot = (passes, Instant::now());
+        }
+
+        if last_checkpoint.elapsed() >= opt.checkpoint_interval {
+            save_checkpoint(framebuffer);
+            last_checkpoint = Instant::now();
+        }
+    };
+
+    let start = Instant::now();
+    let mut rendered = image
+        .render_image(
+            samples_per_pixel,
+            max_depth,
+            thread_number,
+            opt.tile_size,
+            opt.tile_order,
+            after_pass,
+        )
+        .unwrap_or_else(|err| {
+            // The last checkpoint, if any, is kept for the render to be resumed
+            eprintln!("error: {}", err);
+            std::process::exit(1);
+        });
+
+    if opt.debug {
+        let elapsed = start.elapsed().as_secs_f64();
+        let samples = rendered.total_samples() as f64;
+        eprintln!(
+            "Rendered in {:.2} s ({:.0} samples/s)",
+            elapsed,
+            samples / elapsed
+        );
+        eprintln!(
+            "Samples per pixel: {:.1} on average",
+            rendered.samples_per_pixel()
+        );
+    }
+
+    // The finished render can still be resumed with more samples, and the
+    // interrupted one from where it stopped
+    save_checkpoint(&rendered);
+
+    let interrupted = interrupt::interrupted().load(Ordering::SeqCst);
+    if interrupted {
+        eprintln!("Interrupted: saving the partial render, unrendered pixels in magenta");
+        rendered.mark_unrendered();
+    }
 
-    image
-        .render_image(samples_per_pixel, max_depth, thread_number)
-        .flipv()
-        .save_with_format(opt.output, ::image::ImageFormat::Bmp)
+    output::save(&rendered, output, format, &tone_mapping, opt.exr_precision)
         .expect("An error occurred while writing the image to the file.");
+
+    if let Some(path) = opt.spp_heatmap.as_deref() {
+        output::save_heatmap(&rendered, path)
+            .expect("An error occurred while writing the heatmap to the file.");
+    }
+
+    if interrupted {
+        std::process::exit(130);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn scene_hash_works() {
+        let directory =
+            std::env::temp_dir().join(format!("scene_hash_works_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("scene.gltf");
+        std::fs::write(
+            &path,
+            r#"{
+                "asset": { "version": "2.0" },
+                "buffers": [{ "byteLength": 4, "uri": "mesh.bin" }]
+            }"#,
+        )
+        .unwrap();
+
+        let opt = Opt::from_iter_safe(&[
+            "raytracing",
+            "out.png",
+            "--scene",
+            "gltf",
+            "--gltf",
+            path.to_str().unwrap(),
+        ])
+        .unwrap();
+        let hash = || scene_hash(&opt, 7, (32, 24), 16, 50).unwrap();
+
+        std::fs::write(directory.join("mesh.bin"), [0, 0, 0, 0]).unwrap();
+        let before = hash();
+        assert_eq!(before, hash());
+
+        // The buffer the scene file refers to changes
+        std::fs::write(directory.join("mesh.bin"), [0, 0, 128, 63]).unwrap();
+        assert_ne!(before, hash());
+
+        std::fs::remove_file(directory.join("mesh.bin")).unwrap();
+        assert!(scene_hash(&opt, 7, (32, 24), 16, 50).is_err());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn parse_tile_size_works() {
+        assert_eq!(Ok(16), parse_tile_size("16"));
+        assert!(parse_tile_size("0").is_err());
+        assert!(parse_tile_size("-1").is_err());
+        assert!(parse_tile_size("large").is_err());
+
+        assert!(Opt::from_iter_safe(&["raytracing", "out.png", "--tile-size", "0"]).is_err());
+    }
+
+    #[test]
+    fn parse_duration_works() {
+        assert_eq!(Ok(Duration::from_secs(30)), parse_duration("30"));
+        assert_eq!(Ok(Duration::from_secs(30)), parse_duration("30s"));
+        assert_eq!(Ok(Duration::from_millis(1500)), parse_duration("1.5s"));
+        assert_eq!(Ok(Duration::from_millis(250)), parse_duration("250ms"));
+        assert_eq!(Ok(Duration::from_secs(300)), parse_duration("5m"));
+        assert_eq!(Ok(Duration::from_secs(7200)), parse_duration("2h"));
+        assert_eq!(Ok(Duration::ZERO), parse_duration("0"));
+
+        for invalid in ["", "s", "-1s", "NaN", "inf", "1e300h", "1e20h", "5d"] {
+            assert!(parse_duration(invalid).is_err(), "{}", invalid);
+        }
+    }
 }
@@ -1,8 +1,8 @@
 use crate::color::Color;
 use crate::hittable::HitRecord;
 use crate::material::Material;
-use crate::random::canonical_random;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::vec3::Vec3;
 
 /// Represents a dielectric material (a material that might refract)
@@ -18,7 +18,7 @@ impl Dielectric {
     }
 
     /// Use Schlick's approximation for reflectance
-    fn reflectance(cosine: f64, ref_idx: f64) -> f64 {
+    pub fn reflectance(cosine: f64, ref_idx: f64) -> f64 {
         let r0 = (1.0 - ref_idx) / (1.0 + ref_idx);
         let r0 = r0 * r0;
         r0 + (1.0 - r0) * f64::powi(1.0 - cosine, 5)
@@ -26,9 +26,18 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, ray_in: &Ray, record: &HitRecord) -> Option<(Ray, Color)> {
+    fn scatter(
+        &self,
+        ray_in: &Ray,
+        record: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<(Ray, Color)> {
         let attenuation = Color::new(1.0, 1.0, 1.0);
 
+        // Drawn even when the ray cannot refract, for the next bounces to
+        // take the same dimensions of the sampler whatever the path
+        let sample = sampler.get_1d();
+
         let refraction_ratio = if record.front_face {
             1.0 / self.ir
         } else {
@@ -41,16 +50,72 @@ impl Material for Dielectric {
 
         let cannot_refract = refraction_ratio * sin_theta > 1.0;
 
-        let direction = if cannot_refract
-            || Dielectric::reflectance(cos_theta, refraction_ratio) > canonical_random()
-        {
-            Vec3::reflect(unit_direction, record.normal)
-        } else {
-            Vec3::refract(unit_direction, record.normal, refraction_ratio)
-        };
+        let direction =
+            if cannot_refract || Dielectric::reflectance(cos_theta, refraction_ratio) > sample {
+                Vec3::reflect(unit_direction, record.normal)
+            } else {
+                Vec3::refract(unit_direction, record.normal, refraction_ratio)
+            };
 
         let scattered = Ray::new(record.intersection, direction);
 
         Some((scattered, attenuation))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::vec3::Point3;
+    use std::sync::Arc;
+
+    /// A sampler counting the dimensions taken from it
+    #[derive(Clone, Default)]
+    struct CountingSampler {
+        dimensions: usize,
+    }
+
+    impl Sampler for CountingSampler {
+        fn clone_sampler(&self) -> Box<dyn Sampler + Send> {
+            Box::new(self.clone())
+        }
+
+        fn start_pixel_sample(&mut self, _x: u32, _y: u32, _index: u32) {
+            self.dimensions = 0;
+        }
+
+        fn get_1d(&mut self) -> f64 {
+            self.dimensions += 1;
+            0.5
+        }
+
+        fn get_2d(&mut self) -> (f64, f64) {
+            self.dimensions += 2;
+            (0.5, 0.5)
+        }
+    }
+
+    #[test]
+    fn scatter_works() {
+        let material = Arc::new(Dielectric::new(1.5));
+        let normal = Vec3::new(0.0, 0.0, 1.0);
+
+        // Refracted when entering, totally reflected when leaving at a
+        // grazing angle: both take a dimension
+        for (direction, reflected) in [
+            (Vec3::new(0.0, 0.0, -1.0), false),
+            (Vec3::new(0.9, 0.0, 0.1), true),
+        ] {
+            let ray = Ray::new(Point3::zero(), direction);
+            let record = HitRecord::new(&ray, normal, 1.0, (0.0, 0.0), material.clone());
+            let mut sampler = CountingSampler::default();
+            let (scattered, _) = material.scatter(&ray, &record, &mut sampler).unwrap();
+
+            assert_eq!(1, sampler.dimensions);
+            assert_eq!(
+                reflected,
+                Vec3::dot(&scattered.direction(), &record.normal) > 0.0
+            );
+        }
+    }
+}
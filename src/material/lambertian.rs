@@ -1,22 +1,39 @@
+use std::sync::Arc;
+
 use crate::color::Color;
 use crate::hittable::HitRecord;
 use crate::material::Material;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::texture::solid_color::SolidColor;
+use crate::texture::Texture;
 use crate::vec3::Vec3;
 
 pub struct Lambertian {
-    albedo: Color,
+    albedo: Arc<dyn Texture + Sync + Send>,
 }
 
 impl Lambertian {
     pub fn new(albedo: Color) -> Lambertian {
+        Lambertian {
+            albedo: Arc::new(SolidColor::new(albedo)),
+        }
+    }
+
+    /// Returns a new Lambertian material whose albedo varies on the surface
+    pub fn from_texture(albedo: Arc<dyn Texture + Sync + Send>) -> Lambertian {
         Lambertian { albedo }
     }
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, _ray_in: &Ray, record: &HitRecord) -> Option<(Ray, Color)> {
-        let mut scatter_direction = record.normal + Vec3::random_unit_vector();
+    fn scatter(
+        &self,
+        _ray_in: &Ray,
+        record: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<(Ray, Color)> {
+        let mut scatter_direction = record.normal + Vec3::sample_unit_vector(sampler.get_2d());
 
         // Catch degenerate scatter direction
         if scatter_direction.near_zero() {
@@ -24,8 +41,12 @@ impl Material for Lambertian {
         }
 
         let scattered = Ray::new(record.intersection, scatter_direction);
-        let attenuation = self.albedo;
+        let attenuation = self.albedo.value(record);
 
         Some((scattered, attenuation))
     }
+
+    fn diffuse_albedo(&self, _ray_in: &Ray, record: &HitRecord) -> Option<Color> {
+        Some(self.albedo.value(record))
+    }
 }
@@ -2,6 +2,7 @@ use crate::color::Color;
 use crate::hittable::HitRecord;
 use crate::material::Material;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 use crate::vec3::Vec3;
 
 pub struct Metal {
@@ -16,11 +17,16 @@ impl Metal {
 }
 
 impl Material for Metal {
-    fn scatter(&self, ray_in: &Ray, record: &HitRecord) -> Option<(Ray, Color)> {
+    fn scatter(
+        &self,
+        ray_in: &Ray,
+        record: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<(Ray, Color)> {
         let reflected = Vec3::reflect(ray_in.direction().normalized(), record.normal);
         let scattered = Ray::new(
             record.intersection,
-            reflected + self.fuzz * Vec3::random_in_unit_sphere(),
+            reflected + self.fuzz * Vec3::sample_in_unit_sphere(sampler.get_2d(), sampler.get_1d()),
         );
         let attenuation = self.albedo;
 
@@ -0,0 +1,152 @@
+use std::sync::Arc;
+
+use crate::color::Color;
+use crate::hittable::HitRecord;
+use crate::material::dielectric::Dielectric;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::texture::solid_color::SolidColor;
+use crate::texture::Texture;
+use crate::vec3::Vec3;
+
+/// Index of refraction of the coating of non-metals (reflecting 4% of light
+/// at normal incidence)
+const COATING_IR: f64 = 1.5;
+
+/// The physically based material of glTF: a blend of a metal and of a
+/// diffuse surface under a glossy coating.
+///
+/// The base color is the albedo of the metal or of the diffuse surface, and
+/// is multiplied by the vertex colors of meshes. Roughness blurs
+/// reflections like the fuzz of [`Metal`](super::metal::Metal). The
+/// metallic and roughness textures give these factors in their blue and
+/// green channels, the emissive texture the light emitted by the surface.
+pub struct MetallicRoughness {
+    base_color: Arc<dyn Texture + Sync + Send>,
+    metallic: f64,
+    roughness: f64,
+    metallic_roughness: Option<Arc<dyn Texture + Sync + Send>>,
+    emissive: Arc<dyn Texture + Sync + Send>,
+}
+
+impl MetallicRoughness {
+    /// Constructs a new material without textures
+    pub fn new(base_color: Color, metallic: f64, roughness: f64) -> MetallicRoughness {
+        MetallicRoughness {
+            base_color: Arc::new(SolidColor::new(base_color)),
+            metallic,
+            roughness,
+            metallic_roughness: None,
+            emissive: Arc::new(SolidColor::new(Color::new(0.0, 0.0, 0.0))),
+        }
+    }
+
+    /// Sets the texture of the base color
+    pub fn with_base_color(mut self, base_color: Arc<dyn Texture + Sync + Send>) -> Self {
+        self.base_color = base_color;
+        self
+    }
+
+    /// Sets the texture whose blue and green channels scale the metallic and
+    /// roughness factors
+    pub fn with_metallic_roughness(
+        mut self,
+        metallic_roughness: Arc<dyn Texture + Sync + Send>,
+    ) -> Self {
+        self.metallic_roughness = Some(metallic_roughness);
+        self
+    }
+
+    /// Sets the texture of the emitted light
+    pub fn with_emissive(mut self, emissive: Arc<dyn Texture + Sync + Send>) -> Self {
+        self.emissive = emissive;
+        self
+    }
+
+    /// Returns the base color, metallic and roughness factors at the point
+    /// of the hit
+    fn factors(&self, record: &HitRecord) -> (Color, f64, f64) {
+        let base_color = match record.vertex_color {
+            Some(color) => color * self.base_color.value(record),
+            None => self.base_color.value(record),
+        };
+
+        match &self.metallic_roughness {
+            Some(texture) => {
+                let value = texture.value(record);
+                (
+                    base_color,
+                    self.metallic * value.z(),
+                    self.roughness * value.y(),
+                )
+            }
+            None => (base_color, self.metallic, self.roughness),
+        }
+    }
+
+    /// Returns the part of the light reflected by the coating of the
+    /// non-metallic part
+    fn coating_reflectance(ray_in: &Ray, record: &HitRecord) -> f64 {
+        let cosine = Vec3::dot(&-ray_in.direction().normalized(), &record.normal);
+        Dielectric::reflectance(cosine.clamp(0.0, 1.0), COATING_IR)
+    }
+}
+
+impl Material for MetallicRoughness {
+    fn scatter(
+        &self,
+        ray_in: &Ray,
+        record: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<(Ray, Color)> {
+        let (base_color, metallic, roughness) = self.factors(record);
+
+        // Pick the metal, the coating or the diffuse surface below it
+        let (choice, direction, distance) = (sampler.get_1d(), sampler.get_2d(), sampler.get_1d());
+        let reflectance = MetallicRoughness::coating_reflectance(ray_in, record);
+        let (glossy, attenuation) = if choice < metallic {
+            (true, base_color)
+        } else if (choice - metallic) < (1.0 - metallic) * reflectance {
+            (true, Color::new(1.0, 1.0, 1.0))
+        } else {
+            (false, base_color)
+        };
+
+        if glossy {
+            let reflected = Vec3::reflect(ray_in.direction().normalized(), record.normal);
+            let scattered = Ray::new(
+                record.intersection,
+                reflected + roughness * Vec3::sample_in_unit_sphere(direction, distance),
+            );
+
+            return if Vec3::dot(&scattered.direction(), &record.normal) > 0.0 {
+                Some((scattered, attenuation))
+            } else {
+                None
+            };
+        }
+
+        let mut scatter_direction = record.normal + Vec3::sample_unit_vector(direction);
+        if scatter_direction.near_zero() {
+            scatter_direction = record.normal;
+        }
+
+        Some((
+            Ray::new(record.intersection, scatter_direction),
+            attenuation,
+        ))
+    }
+
+    fn emitted(&self, record: &HitRecord) -> Color {
+        self.emissive.value(record)
+    }
+
+    fn diffuse_albedo(&self, ray_in: &Ray, record: &HitRecord) -> Option<Color> {
+        let (base_color, metallic, _) = self.factors(record);
+        let diffuse =
+            (1.0 - metallic) * (1.0 - MetallicRoughness::coating_reflectance(ray_in, record));
+
+        Some(diffuse * base_color)
+    }
+}
@@ -1,14 +1,41 @@
 pub mod dielectric;
 pub mod lambertian;
 pub mod metal;
+pub mod metallic_roughness;
+pub mod subsurface;
 
 use crate::color::Color;
 use crate::hittable::HitRecord;
 use crate::ray::Ray;
+use crate::sampler::Sampler;
 
 /// Represents a material
 pub trait Material {
     /// Produces a scattered ray ([`Ray`] in return value), if scattered
-    /// say how much the ray should be attenuated ([`Color`] in return value)
-    fn scatter(&self, ray_in: &Ray, record: &HitRecord) -> Option<(Ray, Color)>;
+    /// say how much the ray should be attenuated ([`Color`] in return value).
+    /// Random choices take the next dimensions of the `sampler`.
+    fn scatter(
+        &self,
+        ray_in: &Ray,
+        record: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<(Ray, Color)>;
+
+    /// Returns the light emitted by the material at the point of the hit
+    fn emitted(&self, _record: &HitRecord) -> Color {
+        Color::new(0.0, 0.0, 0.0)
+    }
+
+    /// Returns the albedo of the diffuse part of the material, lit directly
+    /// by the lights of the scene, or `None` if the material has no diffuse
+    /// part
+    fn diffuse_albedo(&self, _ray_in: &Ray, _record: &HitRecord) -> Option<Color> {
+        None
+    }
+
+    /// Returns whether scattering at the hit is a step of a random walk
+    /// inside the object, counted apart from the bounces on surfaces
+    fn walks_inside(&self, _record: &HitRecord) -> bool {
+        false
+    }
 }
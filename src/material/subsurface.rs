@@ -0,0 +1,228 @@
+use crate::color::Color;
+use crate::hittable::HitRecord;
+use crate::material::dielectric::Dielectric;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::sampler::Sampler;
+use crate::vec3::Vec3;
+
+/// Represents a translucent material (wax, jade, skin, marble...) where light
+/// enters the object, scatters several times beneath its surface and leaves
+/// it at another point.
+///
+/// Light transport inside the object is simulated with a random walk: a ray
+/// travelling inside the object is either scattered in a random direction
+/// before reaching the boundary, or goes through the boundary. Scattering and
+/// absorption coefficients are given per unit of length of the scene.
+/// The steps of the walk are not counted as bounces, but have a budget of
+/// their own (see [`crate::image::MAX_WALK_STEPS`]).
+pub struct Subsurface {
+    pub ir: f64,
+    pub sigma_s: Color,
+    pub sigma_a: Color,
+}
+
+impl Subsurface {
+    /// Returns a new Subsurface material with `ir` the index of refraction
+    /// of its boundary, `sigma_s` its scattering coefficients and `sigma_a`
+    /// its absorption coefficients
+    pub fn new(ir: f64, sigma_s: Color, sigma_a: Color) -> Subsurface {
+        Subsurface {
+            ir,
+            sigma_s,
+            sigma_a,
+        }
+    }
+
+    /// Returns the extinction coefficients (scattering and absorption)
+    fn sigma_t(&self) -> Color {
+        self.sigma_s + self.sigma_a
+    }
+
+    /// Returns the transmittance through `distance` units of the medium
+    fn transmittance(&self, distance: f64) -> Color {
+        let sigma_t = self.sigma_t();
+        Color::new(
+            (-sigma_t.x() * distance).exp(),
+            (-sigma_t.y() * distance).exp(),
+            (-sigma_t.z() * distance).exp(),
+        )
+    }
+
+    /// Reflects or refracts the ray on the boundary of the object, the same
+    /// way a [`Dielectric`] does, choosing with the `sample`
+    fn cross_boundary(&self, unit_direction: Vec3, record: &HitRecord, sample: f64) -> Ray {
+        let refraction_ratio = if record.front_face {
+            1.0 / self.ir
+        } else {
+            self.ir
+        };
+
+        let cos_theta = Vec3::dot(&(-unit_direction), &record.normal).min(1.0);
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+
+        let cannot_refract = refraction_ratio * sin_theta > 1.0;
+
+        let direction =
+            if cannot_refract || Dielectric::reflectance(cos_theta, refraction_ratio) > sample {
+                Vec3::reflect(unit_direction, record.normal)
+            } else {
+                Vec3::refract(unit_direction, record.normal, refraction_ratio)
+            };
+
+        Ray::new(record.intersection, direction)
+    }
+}
+
+impl Material for Subsurface {
+    fn scatter(
+        &self,
+        ray_in: &Ray,
+        record: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<(Ray, Color)> {
+        let unit_direction = ray_in.direction().normalized();
+        let (crossing, flight, direction) = (sampler.get_1d(), sampler.get_1d(), sampler.get_2d());
+
+        // Entering the object
+        if record.front_face {
+            let attenuation = Color::new(1.0, 1.0, 1.0);
+            return Some((
+                self.cross_boundary(unit_direction, record, crossing),
+                attenuation,
+            ));
+        }
+
+        // The ray travels inside the object up to its boundary: sample a free
+        // flight distance with the extinction coefficient of a channel picked
+        // at random, and weight each channel by its own transmittance over
+        // the average density of the three channels. The weights stay below
+        // 3, unlike those of a distance sampled with one coefficient for all.
+        let sigma_t = self.sigma_t();
+        if sigma_t.x() + sigma_t.y() + sigma_t.z() <= 0.0 {
+            let attenuation = Color::new(1.0, 1.0, 1.0);
+            return Some((
+                self.cross_boundary(unit_direction, record, crossing),
+                attenuation,
+            ));
+        }
+
+        let channel = ((3.0 * flight) as usize).min(2);
+        let flight = 3.0 * flight - channel as f64;
+        let sigma = [sigma_t.x(), sigma_t.y(), sigma_t.z()][channel];
+        let distance = if sigma > 0.0 {
+            -(1.0 - flight).ln() / sigma
+        } else {
+            f64::INFINITY
+        };
+        let boundary_distance = record.t * ray_in.direction().length();
+
+        if distance < boundary_distance {
+            // Scattering event inside the object, isotropic phase function
+            let scattered = Ray::new(
+                ray_in.origin() + distance * unit_direction,
+                Vec3::sample_unit_vector(direction),
+            );
+            let transmittance = self.transmittance(distance);
+            let density = Vec3::dot(&sigma_t, &transmittance) / 3.0;
+            Some((scattered, self.sigma_s * transmittance / density))
+        } else {
+            // The ray reaches the boundary and may leave the object
+            let transmittance = self.transmittance(boundary_distance);
+            let probability = (transmittance.x() + transmittance.y() + transmittance.z()) / 3.0;
+            let attenuation = transmittance / probability;
+            Some((
+                self.cross_boundary(unit_direction, record, crossing),
+                attenuation,
+            ))
+        }
+    }
+
+    /// Hits from inside the object are steps of its random walk
+    fn walks_inside(&self, record: &HitRecord) -> bool {
+        !record.front_face
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::sampler::independent::IndependentSampler;
+    use crate::vec3::Point3;
+    use std::sync::Arc;
+
+    /// Scatters rays travelling inside a slab of the material of the given
+    /// thickness, returning their attenuations
+    fn attenuations(sigma_s: Color, sigma_a: Color, thickness: f64) -> Vec<Color> {
+        let material = Arc::new(Subsurface::new(1.5, sigma_s, sigma_a));
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        let record = HitRecord::new(
+            &ray,
+            Vec3::new(0.0, 0.0, 1.0),
+            thickness,
+            (0.0, 0.0),
+            material.clone(),
+        );
+        let mut sampler = IndependentSampler::new(3);
+
+        (0..200_000)
+            .map(|index| {
+                sampler.start_pixel_sample(0, 0, index);
+                material.scatter(&ray, &record, &mut sampler).unwrap().1
+            })
+            .collect()
+    }
+
+    fn mean(attenuations: &[Color]) -> Color {
+        attenuations
+            .iter()
+            .fold(Color::new(0.0, 0.0, 0.0), |sum, &attenuation| {
+                sum + attenuation
+            })
+            / attenuations.len() as f64
+    }
+
+    #[test]
+    fn energy_conservation_works() {
+        let sigma_s = Color::new(8.0, 1.0, 0.05);
+        let sigma_a = Color::new(2.0, 0.5, 0.05);
+        let attenuations = attenuations(sigma_s, sigma_a, 1.0);
+
+        // Light is either scattered, with the albedo, or transmitted
+        let sigma_t = sigma_s + sigma_a;
+        let expected = |sigma_s: f64, sigma_t: f64| {
+            let transmittance = (-sigma_t).exp();
+            sigma_s / sigma_t * (1.0 - transmittance) + transmittance
+        };
+        let mean = mean(&attenuations);
+        assert!((expected(sigma_s.x(), sigma_t.x()) - mean.x()).abs() < 0.02);
+        assert!((expected(sigma_s.y(), sigma_t.y()) - mean.y()).abs() < 0.02);
+        assert!((expected(sigma_s.z(), sigma_t.z()) - mean.z()).abs() < 0.02);
+
+        // No single path gains much energy
+        for attenuation in attenuations {
+            assert!(attenuation.x().max(attenuation.y()).max(attenuation.z()) <= 3.0);
+        }
+    }
+
+    #[test]
+    fn albedo_limit_works() {
+        // Without absorption, no energy is lost on average
+        let mean = mean(&attenuations(
+            Color::new(8.0, 1.0, 0.05),
+            Color::new(0.0, 0.0, 0.0),
+            1.0,
+        ));
+        assert!((1.0 - mean.x()).abs() < 0.02);
+        assert!((1.0 - mean.y()).abs() < 0.02);
+        assert!((1.0 - mean.z()).abs() < 0.02);
+
+        // nor on any path when the channels are alike
+        let sigma_s = Color::new(2.0, 2.0, 2.0);
+        for attenuation in attenuations(sigma_s, Color::new(0.0, 0.0, 0.0), 1.0) {
+            assert!((1.0 - attenuation.x()).abs() < 1e-12);
+            assert!((1.0 - attenuation.y()).abs() < 1e-12);
+            assert!((1.0 - attenuation.z()).abs() < 1e-12);
+        }
+    }
+}
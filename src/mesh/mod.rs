@@ -0,0 +1,131 @@
+pub mod ply;
+pub mod stl;
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::path::Path;
+
+use crate::color::Color;
+use crate::vec3::{Point3, Vec3};
+
+/// Vertices and triangles of a mesh, as read from a file
+pub struct MeshData {
+    pub positions: Vec<Point3>,
+    pub normals: Option<Vec<Vec3>>,
+    pub uvs: Option<Vec<(f64, f64)>>,
+    pub colors: Option<Vec<Color>>,
+    /// Indices of the vertices of each triangle, counter-clockwise when seen
+    /// from the outside
+    pub triangles: Vec<[usize; 3]>,
+}
+
+impl MeshData {
+    /// Moves and uniformly scales the mesh so that its bounding box is
+    /// centered on `center` and its largest dimension is `size`
+    pub fn fit(&mut self, center: Point3, size: f64) {
+        let mut min = Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+        let mut max = -min;
+        for p in &self.positions {
+            min = Point3::new(min.x().min(p.x()), min.y().min(p.y()), min.z().min(p.z()));
+            max = Point3::new(max.x().max(p.x()), max.y().max(p.y()), max.z().max(p.z()));
+        }
+
+        let extent = max - min;
+        let largest = extent.x().max(extent.y()).max(extent.z());
+        if !largest.is_finite() || largest <= 0.0 {
+            return;
+        }
+
+        let scale = size / largest;
+        let middle = (min + max) / 2.0;
+        for p in &mut self.positions {
+            *p = center + scale * (*p - middle);
+        }
+    }
+
+    /// Checks that every triangle refers to existing vertices and that
+    /// vertex attributes are given for every vertex
+    pub fn validate(&self) -> Result<(), MeshError> {
+        let count = self.positions.len();
+
+        if let Some((i, _)) = self
+            .triangles
+            .iter()
+            .enumerate()
+            .find(|(_, triangle)| triangle.iter().any(|&index| index >= count))
+        {
+            return Err(MeshError::Malformed(format!(
+                "triangle {} refers to a vertex out of the {} vertices",
+                i, count
+            )));
+        }
+
+        let attributes = [
+            self.normals.as_ref().map(Vec::len),
+            self.uvs.as_ref().map(Vec::len),
+            self.colors.as_ref().map(Vec::len),
+        ];
+        if attributes.iter().flatten().any(|&len| len != count) {
+            return Err(MeshError::Malformed(
+                "vertex attributes are not given for every vertex".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Error occurring while loading a mesh
+#[derive(Debug)]
+pub enum MeshError {
+    /// The file could not be read
+    Io(io::Error),
+    /// The file is not in a supported format
+    UnsupportedFormat(String),
+    /// The content of the file is invalid or truncated
+    Malformed(String),
+}
+
+impl Display for MeshError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MeshError::Io(err) => write!(f, "cannot read mesh file: {}", err),
+            MeshError::UnsupportedFormat(format) => {
+                write!(f, "unsupported mesh format: {}", format)
+            }
+            MeshError::Malformed(message) => write!(f, "malformed mesh file: {}", message),
+        }
+    }
+}
+
+impl Error for MeshError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MeshError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for MeshError {
+    fn from(err: io::Error) -> Self {
+        MeshError::Io(err)
+    }
+}
+
+/// Loads a mesh from a file, whose format is given by its extension
+/// (`.ply` or `.stl`)
+pub fn load(path: &Path) -> Result<MeshData, MeshError> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_lowercase)
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "ply" => ply::parse(&std::fs::read(path)?),
+        "stl" => stl::parse(&std::fs::read(path)?),
+        _ => Err(MeshError::UnsupportedFormat(format!("{:?}", path))),
+    }
+}
@@ -0,0 +1,459 @@
+//! Reader of PLY (Stanford polygon) files, in ASCII or binary encoding.
+//!
+//! Vertices may have normals (`nx`, `ny`, `nz`), texture coordinates (`u`,
+//! `v` or `s`, `t`) and colors (`red`, `green`, `blue`). Polygonal faces
+//! are split into triangles. Other elements and properties are skipped.
+
+use std::str::SplitAsciiWhitespace;
+
+use crate::color::Color;
+use crate::mesh::{MeshData, MeshError};
+use crate::vec3::{Point3, Vec3};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Encoding {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Result<Scalar, MeshError> {
+        Ok(match name {
+            "char" | "int8" => Scalar::I8,
+            "uchar" | "uint8" => Scalar::U8,
+            "short" | "int16" => Scalar::I16,
+            "ushort" | "uint16" => Scalar::U16,
+            "int" | "int32" => Scalar::I32,
+            "uint" | "uint32" => Scalar::U32,
+            "float" | "float32" => Scalar::F32,
+            "double" | "float64" => Scalar::F64,
+            _ => return Err(malformed(format!("unknown property type {:?}", name))),
+        })
+    }
+
+    /// Returns the factor bringing a color stored with this type to [0.0, 1.0]
+    fn color_scale(&self) -> f64 {
+        match self {
+            Scalar::U8 => 1.0 / 255.0,
+            Scalar::U16 => 1.0 / 65535.0,
+            _ => 1.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Property {
+    Scalar(String, Scalar),
+    List(String, Scalar, Scalar),
+}
+
+impl Property {
+    fn name(&self) -> &str {
+        match self {
+            Property::Scalar(name, _) | Property::List(name, _, _) => name,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+fn malformed(message: String) -> MeshError {
+    MeshError::Malformed(message)
+}
+
+/// Parses the header, returns the encoding, the elements and the offset of
+/// the body
+fn parse_header(bytes: &[u8]) -> Result<(Encoding, Vec<Element>, usize), MeshError> {
+    let mut offset = 0;
+    let mut lines = Vec::new();
+
+    loop {
+        let end = bytes[offset..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .ok_or_else(|| malformed("unexpected end of file in the header".to_string()))?;
+        let line = std::str::from_utf8(&bytes[offset..offset + end])
+            .map_err(|_| malformed("the header is not valid text".to_string()))?
+            .trim();
+        offset += end + 1;
+
+        if line == "end_header" {
+            break;
+        }
+        lines.push(line);
+    }
+
+    if lines.first() != Some(&"ply") {
+        return Err(malformed("missing \"ply\" magic number".to_string()));
+    }
+
+    let mut encoding = None;
+    let mut elements: Vec<Element> = Vec::new();
+
+    for line in &lines[1..] {
+        let words: Vec<&str> = line.split_ascii_whitespace().collect();
+        match words.as_slice() {
+            ["format", format, _version] => {
+                encoding = Some(match *format {
+                    "ascii" => Encoding::Ascii,
+                    "binary_little_endian" => Encoding::BinaryLittleEndian,
+                    "binary_big_endian" => Encoding::BinaryBigEndian,
+                    _ => return Err(malformed(format!("unknown format {:?}", format))),
+                });
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| malformed(format!("invalid element count {:?}", count)))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, item, name] => elements
+                .last_mut()
+                .ok_or_else(|| malformed("property outside of an element".to_string()))?
+                .properties
+                .push(Property::List(
+                    name.to_string(),
+                    Scalar::parse(count)?,
+                    Scalar::parse(item)?,
+                )),
+            ["property", kind, name] => elements
+                .last_mut()
+                .ok_or_else(|| malformed("property outside of an element".to_string()))?
+                .properties
+                .push(Property::Scalar(name.to_string(), Scalar::parse(kind)?)),
+            ["comment", ..] | ["obj_info", ..] | [] => (),
+            _ => return Err(malformed(format!("invalid header line {:?}", line))),
+        }
+    }
+
+    let encoding = encoding.ok_or_else(|| malformed("missing format".to_string()))?;
+
+    Ok((encoding, elements, offset))
+}
+
+/// Values of the body of the file
+enum Body<'a> {
+    Ascii(SplitAsciiWhitespace<'a>),
+    Binary { bytes: &'a [u8], big_endian: bool },
+}
+
+impl<'a> Body<'a> {
+    fn new(encoding: Encoding, bytes: &'a [u8]) -> Result<Body<'a>, MeshError> {
+        Ok(match encoding {
+            Encoding::Ascii => Body::Ascii(
+                std::str::from_utf8(bytes)
+                    .map_err(|_| malformed("the body is not valid text".to_string()))?
+                    .split_ascii_whitespace(),
+            ),
+            Encoding::BinaryLittleEndian => Body::Binary {
+                bytes,
+                big_endian: false,
+            },
+            Encoding::BinaryBigEndian => Body::Binary {
+                bytes,
+                big_endian: true,
+            },
+        })
+    }
+
+    /// Reads the next value of the body, of given type
+    fn read(&mut self, kind: Scalar) -> Result<f64, MeshError> {
+        let truncated = || malformed("unexpected end of file".to_string());
+
+        match self {
+            Body::Ascii(words) => {
+                let word = words.next().ok_or_else(truncated)?;
+                word.parse()
+                    .map_err(|_| malformed(format!("invalid number {:?}", word)))
+            }
+            Body::Binary { bytes, big_endian } => {
+                let size = match kind {
+                    Scalar::I8 | Scalar::U8 => 1,
+                    Scalar::I16 | Scalar::U16 => 2,
+                    Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+                    Scalar::F64 => 8,
+                };
+                if bytes.len() < size {
+                    return Err(truncated());
+                }
+
+                let mut buffer = [0; 8];
+                buffer[..size].copy_from_slice(&bytes[..size]);
+                if *big_endian {
+                    buffer[..size].reverse();
+                }
+                *bytes = &bytes[size..];
+
+                let b = buffer;
+                Ok(match kind {
+                    Scalar::I8 => b[0] as i8 as f64,
+                    Scalar::U8 => b[0] as f64,
+                    Scalar::I16 => i16::from_le_bytes([b[0], b[1]]) as f64,
+                    Scalar::U16 => u16::from_le_bytes([b[0], b[1]]) as f64,
+                    Scalar::I32 => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                    Scalar::U32 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                    Scalar::F32 => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                    Scalar::F64 => f64::from_le_bytes(b),
+                })
+            }
+        }
+    }
+}
+
+/// Returns the index of the first property with one of the given names
+fn find(element: &Element, names: &[&str]) -> Option<usize> {
+    element
+        .properties
+        .iter()
+        .position(|property| names.contains(&property.name()))
+}
+
+/// Parses the content of a PLY file
+pub fn parse(bytes: &[u8]) -> Result<MeshData, MeshError> {
+    let (encoding, elements, offset) = parse_header(bytes)?;
+    let mut body = Body::new(encoding, &bytes[offset..])?;
+
+    let mut mesh = MeshData {
+        positions: Vec::new(),
+        normals: None,
+        uvs: None,
+        colors: None,
+        triangles: Vec::new(),
+    };
+
+    for element in &elements {
+        let is_vertex = element.name == "vertex";
+        let is_face = element.name == "face";
+
+        let position = [
+            find(element, &["x"]),
+            find(element, &["y"]),
+            find(element, &["z"]),
+        ];
+        let normal = [
+            find(element, &["nx"]),
+            find(element, &["ny"]),
+            find(element, &["nz"]),
+        ];
+        let uv = [
+            find(element, &["u", "s", "texture_u", "texture_s"]),
+            find(element, &["v", "t", "texture_v", "texture_t"]),
+        ];
+        let color = [
+            find(element, &["red", "r"]),
+            find(element, &["green", "g"]),
+            find(element, &["blue", "b"]),
+        ];
+        let indices = find(element, &["vertex_indices", "vertex_index"]);
+
+        if is_vertex {
+            let [x, y, z] = position;
+            if x.is_none() || y.is_none() || z.is_none() {
+                return Err(malformed("vertices have no position".to_string()));
+            }
+            if normal.iter().all(Option::is_some) {
+                mesh.normals = Some(Vec::new());
+            }
+            if uv.iter().all(Option::is_some) {
+                mesh.uvs = Some(Vec::new());
+            }
+            if color.iter().all(Option::is_some) {
+                mesh.colors = Some(Vec::new());
+            }
+        }
+        if is_face && indices.is_none() {
+            return Err(malformed("faces have no vertex indices".to_string()));
+        }
+
+        for _ in 0..element.count {
+            let mut values = Vec::with_capacity(element.properties.len());
+            let mut list = Vec::new();
+
+            for (i, property) in element.properties.iter().enumerate() {
+                match property {
+                    Property::Scalar(_, kind) => values.push(body.read(*kind)?),
+                    Property::List(_, count, item) => {
+                        values.push(0.0);
+                        let count = body.read(*count)?;
+                        if count < 0.0 {
+                            return Err(malformed("negative list length".to_string()));
+                        }
+                        for _ in 0..count as usize {
+                            let value = body.read(*item)?;
+                            if Some(i) == indices {
+                                list.push(value);
+                            }
+                        }
+                    }
+                }
+            }
+
+            let get = |index: Option<usize>| index.map(|i| values[i]).unwrap_or(0.0);
+
+            if is_vertex {
+                mesh.positions.push(Point3::new(
+                    get(position[0]),
+                    get(position[1]),
+                    get(position[2]),
+                ));
+                if let Some(normals) = &mut mesh.normals {
+                    normals.push(Vec3::new(get(normal[0]), get(normal[1]), get(normal[2])));
+                }
+                if let Some(uvs) = &mut mesh.uvs {
+                    uvs.push((get(uv[0]), get(uv[1])));
+                }
+                if let Some(colors) = &mut mesh.colors {
+                    let scale = match &element.properties[color[0].unwrap()] {
+                        Property::Scalar(_, kind) => kind.color_scale(),
+                        Property::List(..) => 1.0,
+                    };
+                    colors.push(scale * Color::new(get(color[0]), get(color[1]), get(color[2])));
+                }
+            } else if is_face {
+                if list.iter().any(|&index| index < 0.0) {
+                    return Err(malformed("negative vertex index".to_string()));
+                }
+                // Split the polygon into a fan of triangles
+                for k in 1..list.len().saturating_sub(1) {
+                    mesh.triangles
+                        .push([list[0] as usize, list[k] as usize, list[k + 1] as usize]);
+                }
+            }
+        }
+    }
+
+    mesh.validate()?;
+    Ok(mesh)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ASCII: &str = "ply
+format ascii 1.0
+comment a square with colors
+element vertex 4
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0 255 0 0
+1 0 0 0 255 0
+1 1 0 0 0 255
+0 1 0 255 255 255
+4 0 1 2 3
+";
+
+    #[test]
+    fn ascii_works() {
+        let mesh = parse(ASCII.as_bytes()).unwrap();
+
+        assert_eq!(4, mesh.positions.len());
+        assert_eq!(Point3::new(1.0, 1.0, 0.0), mesh.positions[2]);
+        assert_eq!(vec![[0, 1, 2], [0, 2, 3]], mesh.triangles);
+        assert_eq!(Color::new(0.0, 1.0, 0.0), mesh.colors.unwrap()[1]);
+        assert!(mesh.normals.is_none());
+    }
+
+    fn binary(big_endian: bool) -> Vec<u8> {
+        let format = if big_endian {
+            "binary_big_endian"
+        } else {
+            "binary_little_endian"
+        };
+        let mut bytes = format!(
+            "ply\nformat {} 1.0\nelement vertex 3\nproperty float x\nproperty float y\n\
+             property float z\nelement face 1\nproperty list uchar uint vertex_index\nend_header\n",
+            format
+        )
+        .into_bytes();
+
+        for value in [0.0f32, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0, 2.0, 0.0] {
+            if big_endian {
+                bytes.extend(value.to_be_bytes());
+            } else {
+                bytes.extend(value.to_le_bytes());
+            }
+        }
+        bytes.push(3);
+        for index in [0u32, 1, 2] {
+            if big_endian {
+                bytes.extend(index.to_be_bytes());
+            } else {
+                bytes.extend(index.to_le_bytes());
+            }
+        }
+
+        bytes
+    }
+
+    #[test]
+    fn binary_works() {
+        for big_endian in [false, true] {
+            let mesh = parse(&binary(big_endian)).unwrap();
+
+            assert_eq!(Point3::new(0.0, 2.0, 0.0), mesh.positions[2]);
+            assert_eq!(vec![[0, 1, 2]], mesh.triangles);
+        }
+    }
+
+    #[test]
+    fn truncated_file_fails() {
+        let bytes = binary(false);
+        assert!(matches!(
+            parse(&bytes[..bytes.len() - 2]),
+            Err(MeshError::Malformed(_))
+        ));
+        assert!(matches!(
+            parse(&ASCII.as_bytes()[..ASCII.len() - 4]),
+            Err(MeshError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn huge_count_fails() {
+        // Announced vertices are read, not allocated up front
+        let bytes = "ply\nformat ascii 1.0\nelement vertex 1000000000000\nproperty float x\n\
+                     property float y\nproperty float z\nproperty float nx\nproperty float ny\n\
+                     property float nz\nproperty float u\nproperty float v\nproperty uchar red\n\
+                     property uchar green\nproperty uchar blue\nend_header\n0 0 0 0 0 1 0 0 0 0 0\n";
+        assert!(matches!(
+            parse(bytes.as_bytes()),
+            Err(MeshError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_index_fails() {
+        let bytes = ASCII.replace("4 0 1 2 3", "3 0 1 7");
+        assert!(matches!(
+            parse(bytes.as_bytes()),
+            Err(MeshError::Malformed(_))
+        ));
+    }
+}
@@ -0,0 +1,209 @@
+//! Reader of STL (stereolithography) files, in ASCII or binary encoding.
+//!
+//! STL files are a list of independent triangles: vertices are not shared,
+//! and the normals of the file are ignored in favor of the winding order.
+
+use crate::mesh::{MeshData, MeshError};
+use crate::vec3::Point3;
+
+/// Size of the header of a binary file
+const HEADER_SIZE: usize = 80;
+
+/// Size of a triangle in a binary file: normal, three vertices and
+/// attributes
+const TRIANGLE_SIZE: usize = 50;
+
+fn malformed(message: String) -> MeshError {
+    MeshError::Malformed(message)
+}
+
+/// Parses the content of a STL file
+pub fn parse(bytes: &[u8]) -> Result<MeshData, MeshError> {
+    // Binary files may also start with "solid", their size tells them apart
+    let binary_size = bytes
+        .get(HEADER_SIZE..HEADER_SIZE + 4)
+        .map(|count| u32::from_le_bytes([count[0], count[1], count[2], count[3]]) as usize)
+        .map(|count| HEADER_SIZE + 4 + count * TRIANGLE_SIZE);
+
+    let ascii = bytes.starts_with(b"solid")
+        && binary_size != Some(bytes.len())
+        && std::str::from_utf8(bytes).is_ok();
+
+    let positions = if ascii {
+        parse_ascii(bytes)?
+    } else {
+        parse_binary(bytes)?
+    };
+
+    let triangles = (0..positions.len() / 3)
+        .map(|i| [3 * i, 3 * i + 1, 3 * i + 2])
+        .collect();
+
+    let mesh = MeshData {
+        positions,
+        normals: None,
+        uvs: None,
+        colors: None,
+        triangles,
+    };
+
+    mesh.validate()?;
+    Ok(mesh)
+}
+
+/// Returns the vertices of the triangles of a binary file
+fn parse_binary(bytes: &[u8]) -> Result<Vec<Point3>, MeshError> {
+    let count = bytes
+        .get(HEADER_SIZE..HEADER_SIZE + 4)
+        .ok_or_else(|| malformed("unexpected end of file in the header".to_string()))?;
+    let count = u32::from_le_bytes([count[0], count[1], count[2], count[3]]) as usize;
+
+    let body = &bytes[HEADER_SIZE + 4..];
+    if body.len() < count * TRIANGLE_SIZE {
+        return Err(malformed(format!(
+            "unexpected end of file, {} triangles announced but only {} found",
+            count,
+            body.len() / TRIANGLE_SIZE
+        )));
+    }
+
+    let read = |offset: usize| {
+        let b = &body[offset..offset + 4];
+        f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64
+    };
+
+    let mut positions = Vec::with_capacity(3 * count);
+    for i in 0..count {
+        // Skip the normal
+        let triangle = i * TRIANGLE_SIZE + 12;
+        for vertex in 0..3 {
+            let offset = triangle + vertex * 12;
+            positions.push(Point3::new(
+                read(offset),
+                read(offset + 4),
+                read(offset + 8),
+            ));
+        }
+    }
+
+    Ok(positions)
+}
+
+/// Returns the vertices of the triangles of an ASCII file
+fn parse_ascii(bytes: &[u8]) -> Result<Vec<Point3>, MeshError> {
+    let text = std::str::from_utf8(bytes)
+        .map_err(|_| malformed("the file is not valid text".to_string()))?;
+
+    let mut positions = Vec::new();
+    let mut vertices_in_facet = 0;
+    let mut ended = false;
+
+    for (number, line) in text.lines().enumerate() {
+        let words: Vec<&str> = line.split_ascii_whitespace().collect();
+        let error = |message: &str| malformed(format!("line {}: {}", number + 1, message));
+
+        match words.as_slice() {
+            ["solid", ..] | ["outer", "loop"] | ["endloop"] | [] => (),
+            ["facet", ..] => vertices_in_facet = 0,
+            ["vertex", x, y, z] => {
+                let parse = |word: &str| {
+                    word.parse::<f64>()
+                        .map_err(|_| error(&format!("invalid number {:?}", word)))
+                };
+                positions.push(Point3::new(parse(x)?, parse(y)?, parse(z)?));
+                vertices_in_facet += 1;
+            }
+            ["endfacet"] => {
+                if vertices_in_facet != 3 {
+                    return Err(error("a facet must have exactly three vertices"));
+                }
+            }
+            ["endsolid", ..] => {
+                ended = true;
+                break;
+            }
+            _ => return Err(error(&format!("unexpected {:?}", line.trim()))),
+        }
+    }
+
+    if !ended || positions.len() % 3 != 0 {
+        return Err(malformed("unexpected end of file".to_string()));
+    }
+
+    Ok(positions)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ASCII: &str = "solid triangle
+  facet normal 0 0 1
+    outer loop
+      vertex 0 0 0
+      vertex 1 0 0
+      vertex 0 1 0
+    endloop
+  endfacet
+endsolid triangle
+";
+
+    #[test]
+    fn ascii_works() {
+        let mesh = parse(ASCII.as_bytes()).unwrap();
+
+        assert_eq!(Point3::new(1.0, 0.0, 0.0), mesh.positions[1]);
+        assert_eq!(vec![[0, 1, 2]], mesh.triangles);
+    }
+
+    fn binary() -> Vec<u8> {
+        // Header starting like an ASCII file
+        let mut bytes = b"solid but binary".to_vec();
+        bytes.resize(HEADER_SIZE, 0);
+        bytes.extend(2u32.to_le_bytes());
+
+        for _ in 0..2 {
+            for value in [
+                0.0f32, 0.0, 1.0, 0.0, 0.0, 0.0, 3.0, 0.0, 0.0, 0.0, 3.0, 0.0,
+            ] {
+                bytes.extend(value.to_le_bytes());
+            }
+            bytes.extend([0, 0]);
+        }
+
+        bytes
+    }
+
+    #[test]
+    fn binary_works() {
+        let mesh = parse(&binary()).unwrap();
+
+        assert_eq!(6, mesh.positions.len());
+        assert_eq!(Point3::new(0.0, 3.0, 0.0), mesh.positions[5]);
+        assert_eq!(vec![[0, 1, 2], [3, 4, 5]], mesh.triangles);
+    }
+
+    #[test]
+    fn truncated_file_fails() {
+        let bytes = binary();
+        assert!(matches!(
+            parse(&bytes[..bytes.len() - 10]),
+            Err(MeshError::Malformed(_))
+        ));
+
+        let text = ASCII.replace("endsolid triangle\n", "");
+        assert!(matches!(
+            parse(text.as_bytes()),
+            Err(MeshError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn malformed_file_fails() {
+        let text = ASCII.replace("vertex 1 0 0", "vertex 1 zero 0");
+        assert!(matches!(
+            parse(text.as_bytes()),
+            Err(MeshError::Malformed(_))
+        ));
+    }
+}
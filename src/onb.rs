@@ -0,0 +1,41 @@
+use crate::vec3::Vec3;
+
+/// An orthonormal basis, used to express points and directions in the
+/// local frame of an object
+#[derive(Debug, Copy, Clone)]
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    /// Constructs an orthonormal basis whose `w` axis points in the direction
+    /// of `n`
+    pub fn from_w(n: Vec3) -> Onb {
+        let w = n.normalized();
+        let a = if w.x().abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let v = Vec3::cross(&w, &a).normalized();
+        let u = Vec3::cross(&v, &w);
+
+        Onb { u, v, w }
+    }
+
+    /// Expresses the world vector `a` in this basis
+    pub fn local(&self, a: Vec3) -> Vec3 {
+        Vec3::new(
+            Vec3::dot(&a, &self.u),
+            Vec3::dot(&a, &self.v),
+            Vec3::dot(&a, &self.w),
+        )
+    }
+
+    /// Expresses the vector `a` given in this basis in the world
+    pub fn world(&self, a: Vec3) -> Vec3 {
+        a.x() * self.u + a.y() * self.v + a.z() * self.w
+    }
+}
@@ -0,0 +1,121 @@
+use std::path::Path;
+
+use crate::framebuffer::Framebuffer;
+use crate::output::{OutputError, Precision};
+
+use ::exr::prelude::{
+    f16, AnyChannel, AnyChannels, AttributeValue, Encoding, FlatSamples, Image, ImageAttributes,
+    IntegerBounds, Layer, LayerAttributes, SmallVec, Text, WritableImage,
+};
+
+/// Writes a framebuffer to an OpenEXR file with two layers: the linear
+/// colors (`beauty`, channels R, G and B) and the sums of the sample
+/// weights of each pixel (`weight`, channel Y). The average number of
+/// samples per pixel is recorded in the `samplesPerPixel` attribute of the
+/// first layer.
+pub fn write(
+    framebuffer: &Framebuffer,
+    path: &Path,
+    precision: Precision,
+) -> Result<(), OutputError> {
+    let (width, height) = (framebuffer.width(), framebuffer.height());
+
+    // OpenEXR files start with the top row
+    let channel = |name: &str, value: &dyn Fn(u32, u32) -> f32| {
+        let values = (0..height)
+            .rev()
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| value(x, y));
+
+        let samples = match precision {
+            Precision::Half => FlatSamples::F16(values.map(f16::from_f32).collect()),
+            Precision::Float => FlatSamples::F32(values.collect()),
+        };
+        AnyChannel::new(name, samples)
+    };
+
+    let size = (width as usize, height as usize);
+    let mut beauty_attributes = LayerAttributes::named("beauty");
+    beauty_attributes.other.insert(
+        Text::from("samplesPerPixel"),
+        AttributeValue::F32(framebuffer.samples_per_pixel() as f32),
+    );
+    let beauty = Layer::new(
+        size,
+        beauty_attributes,
+        Encoding::FAST_LOSSLESS,
+        AnyChannels::sort(SmallVec::from_vec(vec![
+            channel("R", &|x, y| framebuffer.color(x, y).x() as f32),
+            channel("G", &|x, y| framebuffer.color(x, y).y() as f32),
+            channel("B", &|x, y| framebuffer.color(x, y).z() as f32),
+        ])),
+    );
+    let weight = Layer::new(
+        size,
+        LayerAttributes::named("weight"),
+        Encoding::FAST_LOSSLESS,
+        AnyChannels::sort(SmallVec::from_vec(vec![channel("Y", &|x, y| {
+            framebuffer.weight(x, y)
+        })])),
+    );
+
+    let attributes = ImageAttributes::new(IntegerBounds::from_dimensions(size));
+    Image::from_layers(attributes, vec![beauty, weight])
+        .write()
+        .to_file(path)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::adaptive::Estimate;
+    use crate::color::Color;
+    use crate::framebuffer::Pixel;
+    use crate::tile::Tile;
+
+    use ::exr::prelude::read_all_flat_layers_from_file;
+
+    #[test]
+    fn write_works() {
+        let mut framebuffer = Framebuffer::new(3, 2);
+        let mut pixel = Pixel::default();
+        pixel.add_sample(Color::new(4.0, 0.5, 0.25), 2.0);
+        let tile = Tile {
+            x: 0,
+            y: 0,
+            width: 1,
+            height: 1,
+        };
+        framebuffer.merge(&tile, &[pixel]);
+        let mut estimate = Estimate::default();
+        for _ in 0..12 {
+            estimate.add(1.0);
+        }
+        framebuffer.set_estimates(&tile, &[estimate]);
+
+        let path = std::env::temp_dir().join(format!("write_works_{}.exr", std::process::id()));
+        write(&framebuffer, &path, Precision::Half).unwrap();
+        let image = read_all_flat_layers_from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(2, image.layer_data.len());
+        assert_eq!(
+            Some(&AttributeValue::F32(2.0)),
+            image.layer_data[0]
+                .attributes
+                .other
+                .get(&Text::from("samplesPerPixel"))
+        );
+
+        // The bottom left pixel is the first of the last row
+        // Channels are sorted by name: B, G, R
+        let red = &image.layer_data[0].channel_data.list[2];
+        assert_eq!(4.0, red.sample_data.value_by_flat_index(3).to_f32());
+        assert_eq!(0.0, red.sample_data.value_by_flat_index(0).to_f32());
+
+        let weight = &image.layer_data[1].channel_data.list[0];
+        assert_eq!(2.0, weight.sample_data.value_by_flat_index(3).to_f32());
+    }
+}
@@ -0,0 +1,29 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use crate::framebuffer::Framebuffer;
+use crate::output::OutputError;
+
+use image::codecs::hdr::HdrEncoder;
+use image::Rgb;
+
+/// Writes the linear colors of a framebuffer to a Radiance RGBE file
+pub fn write(framebuffer: &Framebuffer, path: &Path) -> Result<(), OutputError> {
+    let (width, height) = (framebuffer.width(), framebuffer.height());
+
+    // Radiance files start with the top row
+    let pixels: Vec<Rgb<f32>> = (0..height)
+        .rev()
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let color = framebuffer.color(x, y);
+            Rgb([color.x() as f32, color.y() as f32, color.z() as f32])
+        })
+        .collect();
+
+    let writer = BufWriter::new(File::create(path)?);
+    HdrEncoder::new(writer).encode(&pixels, width as usize, height as usize)?;
+
+    Ok(())
+}
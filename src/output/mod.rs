@@ -0,0 +1,253 @@
+pub mod exr;
+pub mod hdr;
+pub mod pfm;
+pub mod png;
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::framebuffer::Framebuffer;
+use crate::tone_mapping::ToneMapping;
+
+use ::png::BitDepth;
+use image::codecs::pnm::{PNMSubtype, PnmEncoder, SampleEncoding};
+use image::{ColorType, GenericImageView, ImageError, ImageFormat};
+
+/// File format of a rendered image
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum OutputFormat {
+    /// 8-bit BMP, gamma-corrected
+    Bmp,
+    /// 8-bit PNG, gamma-corrected, recording the samples per pixel
+    Png,
+    /// 16-bit PNG, gamma-corrected, recording the samples per pixel
+    Png16,
+    /// 8-bit binary PPM, gamma-corrected
+    Ppm,
+    /// 8-bit TGA, gamma-corrected
+    Tga,
+    /// Linear OpenEXR, with the sample weights in a second layer, recording
+    /// the samples per pixel
+    Exr,
+    /// Linear Radiance RGBE
+    Hdr,
+    /// Linear portable float map
+    Pfm,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bmp" => Ok(OutputFormat::Bmp),
+            "png" => Ok(OutputFormat::Png),
+            "png16" => Ok(OutputFormat::Png16),
+            "ppm" => Ok(OutputFormat::Ppm),
+            "tga" => Ok(OutputFormat::Tga),
+            "exr" => Ok(OutputFormat::Exr),
+            "hdr" => Ok(OutputFormat::Hdr),
+            "pfm" => Ok(OutputFormat::Pfm),
+            _ => Err(format!("unknown output format: {}", s)),
+        }
+    }
+}
+
+impl OutputFormat {
+    /// Returns the format given by the extension of a path (8-bit for PNG)
+    pub fn from_path(path: &Path) -> Result<OutputFormat, String> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase())
+            .ok_or_else(|| format!("no extension to guess the format of {:?}", path))?;
+
+        match extension.as_str() {
+            "png16" => Err(format!("unknown extension: {}", extension)),
+            _ => extension
+                .parse()
+                .map_err(|_| format!("unknown extension: {}", extension)),
+        }
+    }
+}
+
+/// Type of the samples written in OpenEXR files
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Precision {
+    /// 16-bit floats
+    Half,
+    /// 32-bit floats
+    Float,
+}
+
+impl FromStr for Precision {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "half" => Ok(Precision::Half),
+            "float" => Ok(Precision::Float),
+            _ => Err(format!("unknown precision: {}", s)),
+        }
+    }
+}
+
+/// Error occurring while saving a rendered image
+#[derive(Debug)]
+pub enum OutputError {
+    /// The file could not be written
+    Io(io::Error),
+    /// The image could not be encoded
+    Image(ImageError),
+    /// The OpenEXR file could not be encoded or written
+    Exr(::exr::error::Error),
+    /// The PNG file could not be encoded or written
+    Png(::png::EncodingError),
+}
+
+impl Display for OutputError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputError::Io(err) => write!(f, "cannot write image file: {}", err),
+            OutputError::Image(err) => write!(f, "cannot encode image: {}", err),
+            OutputError::Exr(err) => write!(f, "cannot write OpenEXR file: {}", err),
+            OutputError::Png(err) => write!(f, "cannot write PNG file: {}", err),
+        }
+    }
+}
+
+impl Error for OutputError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            OutputError::Io(err) => Some(err),
+            OutputError::Image(err) => Some(err),
+            OutputError::Exr(err) => Some(err),
+            OutputError::Png(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for OutputError {
+    fn from(err: io::Error) -> Self {
+        OutputError::Io(err)
+    }
+}
+
+impl From<ImageError> for OutputError {
+    fn from(err: ImageError) -> Self {
+        OutputError::Image(err)
+    }
+}
+
+impl From<::exr::error::Error> for OutputError {
+    fn from(err: ::exr::error::Error) -> Self {
+        OutputError::Exr(err)
+    }
+}
+
+impl From<::png::EncodingError> for OutputError {
+    fn from(err: ::png::EncodingError) -> Self {
+        OutputError::Png(err)
+    }
+}
+
+/// Saves a framebuffer to a file in the given format. Integer formats get
+/// the colors given by the tone mapping, floating-point formats the linear
+/// colors, OpenEXR files with the given precision.
+///
+/// The image is written next to the file, then moved over it, so that an
+/// image saved during the rendering is never seen half-written.
+pub fn save(
+    framebuffer: &Framebuffer,
+    path: &Path,
+    format: OutputFormat,
+    tone_mapping: &ToneMapping,
+    precision: Precision,
+) -> Result<(), OutputError> {
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+
+    write(framebuffer, &partial, format, tone_mapping, precision)?;
+    fs::rename(&partial, path)?;
+
+    Ok(())
+}
+
+/// Writes a framebuffer to a file in the given format
+fn write(
+    framebuffer: &Framebuffer,
+    path: &Path,
+    format: OutputFormat,
+    tone_mapping: &ToneMapping,
+    precision: Precision,
+) -> Result<(), OutputError> {
+    // Integer formats start with the top row
+    match format {
+        OutputFormat::Bmp => framebuffer
+            .to_rgb8(tone_mapping)
+            .flipv()
+            .save_with_format(path, ImageFormat::Bmp)?,
+        OutputFormat::Png => png::write(framebuffer, path, tone_mapping, BitDepth::Eight)?,
+        OutputFormat::Png16 => png::write(framebuffer, path, tone_mapping, BitDepth::Sixteen)?,
+        OutputFormat::Ppm => {
+            // The subtype would otherwise be guessed from the extension
+            let img = framebuffer.to_rgb8(tone_mapping).flipv();
+            PnmEncoder::new(BufWriter::new(File::create(path)?))
+                .with_subtype(PNMSubtype::Pixmap(SampleEncoding::Binary))
+                .encode(img.as_bytes(), img.width(), img.height(), ColorType::Rgb8)?;
+        }
+        OutputFormat::Tga => framebuffer
+            .to_rgb8(tone_mapping)
+            .flipv()
+            .save_with_format(path, ImageFormat::Tga)?,
+        OutputFormat::Exr => exr::write(framebuffer, path, precision)?,
+        OutputFormat::Hdr => hdr::write(framebuffer, path)?,
+        OutputFormat::Pfm => pfm::write(framebuffer, path)?,
+    }
+
+    Ok(())
+}
+
+/// Returns the average number of samples per pixel of a framebuffer, as
+/// recorded in the metadata of the formats having some
+fn samples_per_pixel_text(framebuffer: &Framebuffer) -> String {
+    let samples_per_pixel = framebuffer.samples_per_pixel();
+    if samples_per_pixel.fract() == 0.0 {
+        format!("{}", samples_per_pixel)
+    } else {
+        format!("{:.1}", samples_per_pixel)
+    }
+}
+
+/// Saves an image of the number of samples taken in each pixel, in the
+/// 8-bit format given by the extension of the path
+pub fn save_heatmap(framebuffer: &Framebuffer, path: &Path) -> Result<(), OutputError> {
+    framebuffer.to_heatmap().flipv().save(path)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn from_path_works() {
+        assert_eq!(
+            Ok(OutputFormat::Exr),
+            OutputFormat::from_path(Path::new("out/render.EXR"))
+        );
+        assert_eq!(
+            Ok(OutputFormat::Png),
+            OutputFormat::from_path(Path::new("render.png"))
+        );
+        assert!(OutputFormat::from_path(Path::new("render.jpg")).is_err());
+        assert!(OutputFormat::from_path(Path::new("render.png16")).is_err());
+        assert!(OutputFormat::from_path(Path::new("render")).is_err());
+    }
+}
@@ -0,0 +1,32 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::framebuffer::Framebuffer;
+use crate::output::OutputError;
+
+/// Writes the linear colors of a framebuffer to a portable float map
+pub fn write(framebuffer: &Framebuffer, path: &Path) -> Result<(), OutputError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    // A negative scale means little-endian samples
+    write!(
+        writer,
+        "PF\n{} {}\n-1.0\n",
+        framebuffer.width(),
+        framebuffer.height()
+    )?;
+
+    // Portable float maps start with the bottom row, like framebuffers
+    for y in 0..framebuffer.height() {
+        for x in 0..framebuffer.width() {
+            let color = framebuffer.color(x, y);
+            for value in [color.x(), color.y(), color.z()] {
+                writer.write_all(&(value as f32).to_le_bytes())?;
+            }
+        }
+    }
+
+    writer.flush()?;
+    Ok(())
+}
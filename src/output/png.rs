@@ -0,0 +1,104 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use crate::framebuffer::Framebuffer;
+use crate::output::{samples_per_pixel_text, OutputError};
+use crate::tone_mapping::ToneMapping;
+
+use ::png::{BitDepth, ColorType, Encoder};
+
+/// Writes the colors given by the tone mapping of a framebuffer to a PNG
+/// file of the given depth, 8 or 16 bits, recording the samples per pixel
+/// in a text chunk
+pub fn write(
+    framebuffer: &Framebuffer,
+    path: &Path,
+    tone_mapping: &ToneMapping,
+    depth: BitDepth,
+) -> Result<(), OutputError> {
+    let (width, height) = (framebuffer.width(), framebuffer.height());
+
+    // PNG files start with the top row, with big-endian 16-bit samples
+    let colors = (0..height)
+        .rev()
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| tone_mapping.encode(framebuffer.color(x, y)));
+    let data: Vec<u8> = match depth {
+        BitDepth::Sixteen => colors
+            .flat_map(|color| color.to_rgb16().0)
+            .flat_map(u16::to_be_bytes)
+            .collect(),
+        _ => colors.flat_map(|color| color.to_rgb8().0).collect(),
+    };
+
+    let mut encoder = Encoder::new(BufWriter::new(File::create(path)?), width, height);
+    encoder.set_color(ColorType::Rgb);
+    encoder.set_depth(depth);
+    encoder.add_text_chunk(
+        "Samples per pixel".to_string(),
+        samples_per_pixel_text(framebuffer),
+    )?;
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+    writer.finish()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::adaptive::Estimate;
+    use crate::color::Color;
+    use crate::framebuffer::Pixel;
+    use crate::tile::Tile;
+    use crate::tone_mapping::ToneMapper;
+
+    use std::io::BufReader;
+
+    use ::png::Decoder;
+
+    #[test]
+    fn write_works() {
+        let mut framebuffer = Framebuffer::new(3, 2);
+        let mut pixel = Pixel::default();
+        pixel.add_sample(Color::new(1.0, 0.5, 0.0), 1.0);
+        let tile = Tile {
+            x: 0,
+            y: 0,
+            width: 1,
+            height: 1,
+        };
+        framebuffer.merge(&tile, &[pixel]);
+        let mut estimate = Estimate::default();
+        for _ in 0..3 {
+            estimate.add(0.5);
+        }
+        framebuffer.set_estimates(&tile, &[estimate]);
+        let tone_mapping = ToneMapping {
+            exposure: 0.0,
+            tone_mapper: ToneMapper::Clamp,
+            white_point: 4.0,
+        };
+
+        let path = std::env::temp_dir().join(format!("write_works_{}.png", std::process::id()));
+        write(&framebuffer, &path, &tone_mapping, BitDepth::Sixteen).unwrap();
+        let mut reader = Decoder::new(BufReader::new(File::open(&path).unwrap()))
+            .read_info()
+            .unwrap();
+        let mut data = vec![0; reader.output_buffer_size().unwrap()];
+        reader.next_frame(&mut data).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let text = &reader.info().uncompressed_latin1_text[0];
+        assert_eq!("Samples per pixel", text.keyword);
+        assert_eq!("0.5", text.text);
+
+        // The bottom left pixel is the first of the last row
+        let red = |offset: usize| u16::from_be_bytes([data[offset], data[offset + 1]]);
+        assert_eq!(65535, red(3 * 6));
+        assert_eq!(0, red(0));
+    }
+}
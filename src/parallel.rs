@@ -1,12 +1,43 @@
-use std::{
-    sync::{mpsc, Arc, Mutex},
-    thread,
-};
+use std::any::Any;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
 
-/// A basic thread pool.
+/// A job run by a thread of the pool. Jobs borrow from their scope, which
+/// outlives them, but are stored as `'static`.
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// What the threads of a pool share
+struct Shared {
+    /// Jobs of each thread, taken from the front by their thread and stolen
+    /// from the back by the others
+    queues: Vec<Mutex<VecDeque<Job>>>,
+    state: Mutex<State>,
+    /// Signaled when a job is queued or the pool is dropped
+    work: Condvar,
+    /// Thread the next job is given to
+    next_queue: AtomicUsize,
+}
+
+struct State {
+    /// Jobs queued but not taken by a thread yet
+    queued: usize,
+    shutdown: bool,
+}
+
+/// A work-stealing thread pool. Jobs are spawned in scopes, which can be
+/// borrowed from and which return once all their jobs are done, so that a
+/// pool renders all the passes of an image.
+///
+/// Jobs are dealt to the threads in turn, and a thread without jobs left
+/// steals the last ones of another thread, so that threads keep busy when
+/// jobs take unequal times.
 pub struct ThreadPool {
-    workers: Vec<Worker>,
-    sender: mpsc::Sender<Message>,
+    shared: Arc<Shared>,
+    threads: Vec<thread::JoinHandle<()>>,
 }
 
 impl ThreadPool {
@@ -20,69 +51,256 @@ impl ThreadPool {
     pub fn new(size: usize) -> ThreadPool {
         assert!(size > 0);
 
-        let (sender, receiver) = mpsc::channel();
+        let shared = Arc::new(Shared {
+            queues: (0..size).map(|_| Mutex::new(VecDeque::new())).collect(),
+            state: Mutex::new(State {
+                queued: 0,
+                shutdown: false,
+            }),
+            work: Condvar::new(),
+            next_queue: AtomicUsize::new(0),
+        });
+
+        let threads = (0..size)
+            .map(|index| {
+                let shared = Arc::clone(&shared);
+                thread::spawn(move || ThreadPool::run(&shared, index))
+            })
+            .collect();
+
+        ThreadPool { shared, threads }
+    }
 
-        let receiver = Arc::new(Mutex::new(receiver));
+    /// Runs the jobs of the thread `index` until the pool is dropped
+    fn run(shared: &Shared, index: usize) {
+        loop {
+            // A job is reserved before being looked for, so that there is
+            // always one for the threads looking
+            {
+                let mut state = shared.state.lock().unwrap();
+                while state.queued == 0 && !state.shutdown {
+                    state = shared.work.wait(state).unwrap();
+                }
+                if state.queued == 0 {
+                    return;
+                }
+                state.queued -= 1;
+            }
 
-        let mut workers = Vec::with_capacity(size);
+            let count = shared.queues.len();
+            let job = (0..)
+                .map(|k| (index + k) % count)
+                .find_map(|queue| {
+                    let mut jobs = shared.queues[queue].lock().unwrap();
+                    if queue == index {
+                        jobs.pop_front()
+                    } else {
+                        jobs.pop_back()
+                    }
+                })
+                .unwrap();
 
-        for _ in 0..size {
-            workers.push(Worker::new(Arc::clone(&receiver)));
+            job();
         }
+    }
 
-        ThreadPool { workers, sender }
+    /// Queues a job for the next thread in turn
+    fn push(&self, job: Job) {
+        let queue = self.shared.next_queue.fetch_add(1, Ordering::Relaxed) % self.threads.len();
+        self.shared.queues[queue].lock().unwrap().push_back(job);
+
+        self.shared.state.lock().unwrap().queued += 1;
+        self.shared.work.notify_one();
     }
 
-    /// Fires a job to the thread pool.
-    pub fn execute<F>(&self, f: F)
+    /// Runs `f` with a scope spawning jobs in the pool, which may borrow
+    /// anything outliving the scope, and waits for all of them to be done.
+    ///
+    /// # Panics
+    ///
+    /// Panics once every job is done if `f` or any job panicked, with the
+    /// payload of the first panic.
+    pub fn scope<'env, F, T>(&self, f: F) -> T
     where
-        F: FnOnce() + Send + 'static,
+        F: for<'scope> FnOnce(&'scope Scope<'scope, 'env>) -> T,
     {
-        let job = Box::new(f);
+        let scope = Scope {
+            pool: self,
+            state: Arc::new(ScopeState {
+                running: Mutex::new(0),
+                done: Condvar::new(),
+                panic: Mutex::new(None),
+            }),
+            scope: PhantomData,
+            env: PhantomData,
+        };
 
-        self.sender.send(Message::NewJob(job)).unwrap();
-    }
+        let result = panic::catch_unwind(AssertUnwindSafe(|| f(&scope)));
 
-    /// Waits for all jobs to be completed.
-    pub fn wait_all(&mut self) {
-        for _ in &self.workers {
-            self.sender.send(Message::Terminate).unwrap();
+        // Jobs borrow from the scope: it must not end before them
+        let mut running = scope.state.running.lock().unwrap();
+        while *running > 0 {
+            running = scope.state.done.wait(running).unwrap();
         }
+        drop(running);
 
-        for worker in &mut self.workers {
-            if let Some(thread) = worker.thread.take() {
-                thread.join().unwrap();
-            }
+        let panic = scope.state.panic.lock().unwrap().take();
+        match (result, panic) {
+            (Err(payload), _) | (Ok(_), Some(payload)) => panic::resume_unwind(payload),
+            (Ok(result), None) => result,
         }
     }
 }
 
-/// A job to be run in a [`Worker`].
-type Job = Box<dyn FnOnce() + Send + 'static>;
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        self.shared.state.lock().unwrap().shutdown = true;
+        self.shared.work.notify_all();
 
-enum Message {
-    NewJob(Job),
-    Terminate,
+        for thread in self.threads.drain(..) {
+            thread.join().unwrap();
+        }
+    }
 }
 
-/// Represent a worker thread in a thread pool.
-struct Worker {
-    thread: Option<thread::JoinHandle<()>>,
+struct ScopeState {
+    /// Jobs spawned but not done yet
+    running: Mutex<usize>,
+    /// Signaled when the last job is done
+    done: Condvar,
+    /// Payload of the first job that panicked
+    panic: Mutex<Option<Box<dyn Any + Send>>>,
 }
 
-impl Worker {
-    fn new(receiver: Arc<Mutex<mpsc::Receiver<Message>>>) -> Worker {
-        let thread = thread::spawn(move || loop {
-            let message = receiver.lock().unwrap().recv().unwrap();
+/// A scope spawning jobs in a [`ThreadPool`], created by
+/// [`ThreadPool::scope`]
+pub struct Scope<'scope, 'env: 'scope> {
+    pool: &'scope ThreadPool,
+    state: Arc<ScopeState>,
+    scope: PhantomData<&'scope mut &'scope ()>,
+    env: PhantomData<&'env mut &'env ()>,
+}
 
-            match message {
-                Message::NewJob(job) => job(),
-                Message::Terminate => break,
+impl<'scope, 'env> Scope<'scope, 'env> {
+    /// Spawns a job in the pool, which may borrow anything outliving the
+    /// scope
+    pub fn spawn<F>(&self, f: F)
+    where
+        F: FnOnce() + Send + 'scope,
+    {
+        *self.state.running.lock().unwrap() += 1;
+
+        let state = Arc::clone(&self.state);
+        let job: Box<dyn FnOnce() + Send + 'scope> = Box::new(move || {
+            // A panicking job does not take its thread down
+            if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(f)) {
+                state.panic.lock().unwrap().get_or_insert(payload);
+            }
+
+            let mut running = state.running.lock().unwrap();
+            *running -= 1;
+            if *running == 0 {
+                state.done.notify_all();
             }
         });
 
-        Worker {
-            thread: Some(thread),
+        // SAFETY: the scope waits for its jobs to be done before returning,
+        // even when panicking, so that what they borrow outlives them
+        let job: Job = unsafe { std::mem::transmute(job) };
+        self.pool.push(job);
+    }
+}
+
+/// Returns the message of a panic from its payload, which is a string
+/// unless given to `panic_any`
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    match payload.downcast_ref::<&str>() {
+        Some(message) => message.to_string(),
+        None => match payload.downcast_ref::<String>() {
+            Some(message) => message.clone(),
+            None => "unknown panic".to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn scope_works() {
+        let pool = ThreadPool::new(3);
+        let values: Vec<usize> = (1..=100).collect();
+        let sum = AtomicUsize::new(0);
+
+        // The pool is reused from a scope to another
+        for _ in 0..2 {
+            pool.scope(|scope| {
+                for chunk in values.chunks(7) {
+                    let sum = &sum;
+                    scope.spawn(move || {
+                        sum.fetch_add(chunk.iter().sum(), Ordering::SeqCst);
+                    });
+                }
+            });
         }
+
+        assert_eq!(2 * 5050, sum.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn stealing_works() {
+        // The first job waits for all the others, half of which are queued
+        // behind it: they must be stolen by the other thread
+        let pool = ThreadPool::new(2);
+        let done = AtomicUsize::new(0);
+        let waited = pool.scope(|scope| {
+            let (sender, receiver) = std::sync::mpsc::channel();
+            let done = &done;
+            scope.spawn(move || {
+                let start = Instant::now();
+                while done.load(Ordering::SeqCst) < 9 && start.elapsed() < Duration::from_secs(5) {
+                    thread::yield_now();
+                }
+                sender.send(done.load(Ordering::SeqCst)).unwrap();
+            });
+            for _ in 0..9 {
+                scope.spawn(move || {
+                    done.fetch_add(1, Ordering::SeqCst);
+                });
+            }
+
+            receiver.recv().unwrap()
+        });
+
+        assert_eq!(9, waited);
+    }
+
+    #[test]
+    fn panic_works() {
+        let pool = ThreadPool::new(2);
+        let done = AtomicUsize::new(0);
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            pool.scope(|scope| {
+                scope.spawn(|| panic!("job failed"));
+                for _ in 0..4 {
+                    scope.spawn(|| {
+                        done.fetch_add(1, Ordering::SeqCst);
+                    });
+                }
+            })
+        }));
+
+        // The other jobs are done, and the pool still works
+        assert_eq!("job failed", panic_message(&*result.unwrap_err()));
+        assert_eq!(4, done.load(Ordering::SeqCst));
+        assert_eq!(2, pool.scope(|_| 2));
+
+        let payload = panic::catch_unwind(|| panic!("tile {}", 3)).unwrap_err();
+        assert_eq!("tile 3", panic_message(&*payload));
+        let payload = panic::catch_unwind(|| panic::panic_any(3)).unwrap_err();
+        assert_eq!("unknown panic", panic_message(&*payload));
     }
 }
@@ -1,11 +1,41 @@
-use rand::{thread_rng, Rng};
+use std::cell::RefCell;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+thread_local! {
+    /// Generator of the current thread, seeded at random until [`seed`] is
+    /// called
+    static GENERATOR: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+}
+
+/// Seeds the generator of the current thread, so that the numbers it
+/// returns next are the same from one run to another
+pub fn seed(seed: u64) {
+    GENERATOR.with(|generator| *generator.borrow_mut() = StdRng::seed_from_u64(seed));
+}
 
 /// Returns a random real number in [0.0, 1.0[
 pub fn canonical_random() -> f64 {
-    thread_rng().gen_range(0.0..1.0)
+    GENERATOR.with(|generator| generator.borrow_mut().gen_range(0.0..1.0))
 }
 
 /// Returns a random real number in [`min`, `max`[
 pub fn random_range(min: f64, max: f64) -> f64 {
-    thread_rng().gen_range(min..max)
+    GENERATOR.with(|generator| generator.borrow_mut().gen_range(min..max))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn seed_works() {
+        seed(42);
+        let first: Vec<f64> = (0..4).map(|_| canonical_random()).collect();
+        seed(42);
+        let second: Vec<f64> = (0..4).map(|_| canonical_random()).collect();
+
+        assert_eq!(first, second);
+    }
 }
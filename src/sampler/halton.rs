@@ -0,0 +1,115 @@
+use std::sync::Arc;
+
+use crate::sampler::{hash, mix, permutation_element, to_unit, Sampler};
+
+/// Number of dimensions with a Halton sequence, following ones being
+/// independent
+const DIMENSIONS: usize = 1000;
+
+/// A sampler taking the samples of each dimension from the radical inverse
+/// of their index in a prime base, the `n`th prime for the `n`th
+/// dimension. The digits are Owen-scrambled differently for each pixel and
+/// dimension: without it, the first samples of large bases only cover the
+/// start of `[0.0, 1.0[`.
+pub struct HaltonSampler {
+    seed: u64,
+    primes: Arc<Vec<u32>>,
+    pixel: (u32, u32),
+    index: u32,
+    dimension: usize,
+}
+
+impl HaltonSampler {
+    /// Constructs a new sampler, whose scrambling depends on the `seed`
+    pub fn new(seed: u64) -> HaltonSampler {
+        let mut primes: Vec<u32> = Vec::with_capacity(DIMENSIONS);
+        let mut candidate = 2;
+        while primes.len() < DIMENSIONS {
+            if primes
+                .iter()
+                .take_while(|&&p| p * p <= candidate)
+                .all(|&p| candidate % p != 0)
+            {
+                primes.push(candidate);
+            }
+            candidate += 1;
+        }
+
+        HaltonSampler {
+            seed,
+            primes: Arc::new(primes),
+            pixel: (0, 0),
+            index: 0,
+            dimension: 0,
+        }
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn clone_sampler(&self) -> Box<dyn Sampler + Send> {
+        Box::new(HaltonSampler {
+            seed: self.seed,
+            primes: Arc::clone(&self.primes),
+            pixel: (0, 0),
+            index: 0,
+            dimension: 0,
+        })
+    }
+
+    fn start_pixel_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.pixel = (x, y);
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let dimension = self.dimension;
+        self.dimension += 1;
+
+        let seed = hash(&[
+            self.seed,
+            self.pixel.0 as u64,
+            self.pixel.1 as u64,
+            dimension as u64,
+        ]);
+        if dimension >= self.primes.len() {
+            return to_unit(hash(&[seed, self.index as u64]) as u32);
+        }
+
+        owen_scrambled_radical_inverse(self.primes[dimension], self.index, seed)
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.get_1d(), self.get_1d())
+    }
+}
+
+/// Mirrors the digits of `index` in the given base around the decimal
+/// point, permuting each digit depending on the digits before it
+fn owen_scrambled_radical_inverse(base: u32, mut index: u32, seed: u64) -> f64 {
+    let inverse_base = 1.0 / base as f64;
+    let mut value = 0.0;
+    let mut scale = inverse_base;
+    let mut previous_digits: u64 = 0;
+
+    // Scramble as many digits as indices below 2^16 have, zeros included,
+    // so that up to 65536 samples stay stratified
+    let mut position = 0;
+    while base.pow(position) < 1 << 16 {
+        let digit = index % base;
+        let digit_seed =
+            mix(seed ^ previous_digits.wrapping_mul(0x9e3779b97f4a7c15) ^ position as u64);
+        value += permutation_element(digit, base, digit_seed as u32) as f64 * scale;
+
+        previous_digits = previous_digits * base as u64 + digit as u64;
+        index /= base;
+        scale *= inverse_base;
+        position += 1;
+    }
+
+    // The following digits would all be scrambled at random
+    let tail = mix(seed ^ previous_digits.wrapping_mul(0x9e3779b97f4a7c15) ^ !0);
+    value += to_unit((tail ^ index as u64) as u32) * base as f64 * scale;
+
+    value.min(1.0 - f64::EPSILON)
+}
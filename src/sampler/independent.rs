@@ -0,0 +1,38 @@
+use crate::sampler::{hash, mix, to_unit, Sampler};
+
+/// A sampler returning independent random numbers, from a generator
+/// seeded by the pixel and the index of the sample
+pub struct IndependentSampler {
+    seed: u64,
+    /// State of the SplitMix64 generator of the current sample
+    state: u64,
+}
+
+impl IndependentSampler {
+    pub fn new(seed: u64) -> IndependentSampler {
+        IndependentSampler { seed, state: seed }
+    }
+
+    fn next(&mut self) -> f64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        to_unit((mix(self.state) >> 32) as u32)
+    }
+}
+
+impl Sampler for IndependentSampler {
+    fn clone_sampler(&self) -> Box<dyn Sampler + Send> {
+        Box::new(IndependentSampler::new(self.seed))
+    }
+
+    fn start_pixel_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.state = hash(&[self.seed, x as u64, y as u64, index as u64]);
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        self.next()
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.next(), self.next())
+    }
+}
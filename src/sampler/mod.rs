@@ -0,0 +1,137 @@
+pub mod halton;
+pub mod independent;
+pub mod sobol;
+pub mod stratified;
+
+/// Represents a source of samples in `[0.0, 1.0[`, drawn dimension after
+/// dimension for each sample of each pixel.
+///
+/// The camera takes the first dimensions (position in the pixel, then on
+/// the lens), materials the following ones at each bounce. Samplers other
+/// than the independent one spread the samples of a pixel more evenly than
+/// random numbers, which reduces noise at equal sample counts.
+pub trait Sampler {
+    /// Returns a new sampler of the same kind, to be used by another thread
+    fn clone_sampler(&self) -> Box<dyn Sampler + Send>;
+
+    /// Starts the sample of index `index` of the pixel at column `x` and
+    /// row `y`, from its first dimension
+    fn start_pixel_sample(&mut self, x: u32, y: u32, index: u32);
+
+    /// Returns the next dimension of the current sample
+    fn get_1d(&mut self) -> f64;
+
+    /// Returns the next two dimensions of the current sample
+    fn get_2d(&mut self) -> (f64, f64);
+}
+
+/// Mixes the bits of a value (the finalizer of SplitMix64)
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+/// Returns a hash of several values, used to decorrelate pixels and
+/// dimensions
+fn hash(values: &[u64]) -> u64 {
+    values.iter().fold(0, |hash, &value| {
+        mix(hash ^ value.wrapping_add(0x9e3779b97f4a7c15))
+    })
+}
+
+/// Converts 32 random bits to a number in `[0.0, 1.0[`
+fn to_unit(bits: u32) -> f64 {
+    bits as f64 / 4294967296.0
+}
+
+/// Returns the element of index `i` of a random permutation of `0..length`
+/// chosen by `seed`, without storing it (Kensler, "Correlated Multi-Jittered
+/// Sampling")
+fn permutation_element(mut i: u32, length: u32, seed: u32) -> u32 {
+    let mut w = length - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+
+    // Permute within the next power of two until landing in the range
+    loop {
+        i ^= seed;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= seed >> 16;
+        i ^= (i & w) >> 4;
+        i ^= seed >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= seed >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | seed >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+
+        if i < length {
+            break;
+        }
+    }
+
+    (i.wrapping_add(seed)) % length
+}
+
+#[cfg(test)]
+mod test {
+    use super::halton::HaltonSampler;
+    use super::independent::IndependentSampler;
+    use super::sobol::SobolSampler;
+    use super::stratified::StratifiedSampler;
+    use super::*;
+
+    #[test]
+    fn samplers_work() {
+        let samplers: Vec<Box<dyn Sampler + Send>> = vec![
+            Box::new(IndependentSampler::new(1)),
+            Box::new(StratifiedSampler::new(64, 1)),
+            Box::new(HaltonSampler::new(1)),
+            Box::new(SobolSampler::new(1)),
+        ];
+
+        for (k, mut sampler) in samplers.into_iter().enumerate() {
+            let mut cells = [0; 16];
+            let mut bins = [0; 16];
+            for index in 0..64 {
+                sampler.start_pixel_sample(3, 7, index);
+                let (x, y) = sampler.get_2d();
+                let z = sampler.get_1d();
+                let (u, v) = sampler.get_2d();
+
+                for value in [x, y, z, u, v] {
+                    assert!((0.0..1.0).contains(&value));
+                }
+                cells[(4.0 * x) as usize + 4 * (4.0 * y) as usize] += 1;
+                bins[(16.0 * z) as usize] += 1;
+            }
+
+            // Unlike random numbers, which often leave some of them empty,
+            // samples are spread evenly in the bins of one dimension and the
+            // cells of two
+            if k > 0 {
+                assert!(bins.iter().all(|&count| count > 0));
+                assert!(cells.iter().all(|&count| count > 0));
+            }
+
+            // Samples only depend on the seed, pixel, index and dimension
+            let mut other = sampler.clone_sampler();
+            sampler.start_pixel_sample(4, 2, 5);
+            other.start_pixel_sample(4, 2, 5);
+            assert_eq!(sampler.get_2d(), other.get_2d());
+            assert_eq!(sampler.get_1d(), other.get_1d());
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use crate::sampler::{hash, to_unit, Sampler};
+
+/// A sampler taking each pair of dimensions from the first two dimensions
+/// of the Sobol sequence, Owen-scrambled and shuffled differently for each
+/// pixel and pair (Burley, "Practical Hash-based Owen Scrambling")
+pub struct SobolSampler {
+    seed: u64,
+    pixel: (u32, u32),
+    index: u32,
+    dimension: u32,
+}
+
+impl SobolSampler {
+    /// Constructs a new sampler, whose scrambling depends on the `seed`
+    pub fn new(seed: u64) -> SobolSampler {
+        SobolSampler {
+            seed,
+            pixel: (0, 0),
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    /// Returns a seed for the current dimension
+    fn dimension_seed(&self, salt: u64) -> u32 {
+        hash(&[
+            self.seed,
+            self.pixel.0 as u64,
+            self.pixel.1 as u64,
+            self.dimension as u64,
+            salt,
+        ]) as u32
+    }
+
+    /// Returns the index of the current sample in the sequence of the
+    /// current dimension
+    fn shuffled_index(&self) -> u32 {
+        nested_uniform_scramble(self.index, self.dimension_seed(0))
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn clone_sampler(&self) -> Box<dyn Sampler + Send> {
+        Box::new(SobolSampler::new(self.seed))
+    }
+
+    fn start_pixel_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.pixel = (x, y);
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let index = self.shuffled_index();
+        let value = nested_uniform_scramble(index.reverse_bits(), self.dimension_seed(1));
+        self.dimension += 1;
+
+        to_unit(value)
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let index = self.shuffled_index();
+        let x = nested_uniform_scramble(index.reverse_bits(), self.dimension_seed(1));
+        let y = nested_uniform_scramble(sobol_second_dimension(index), self.dimension_seed(2));
+        self.dimension += 2;
+
+        (to_unit(x), to_unit(y))
+    }
+}
+
+/// Returns the second dimension of the Sobol sequence
+fn sobol_second_dimension(index: u32) -> u32 {
+    let mut value = 0;
+    let mut direction: u32 = 1 << 31;
+
+    for bit in 0..32 {
+        if index >> bit & 1 == 1 {
+            value ^= direction;
+        }
+        direction ^= direction >> 1;
+    }
+
+    value
+}
+
+/// Applies a random Owen scrambling chosen by `seed` to the bits of a
+/// number: each bit is flipped or not depending on the bits above it
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+/// Hash whose bits only depend on the bits below them (with the constants
+/// of Vegdahl)
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50b47c);
+    x ^= x.wrapping_mul(0xb82f1e52);
+    x ^= x.wrapping_mul(0xc7afe638);
+    x ^= x.wrapping_mul(0x8d22f6e6);
+    x
+}
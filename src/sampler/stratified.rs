@@ -0,0 +1,90 @@
+use crate::sampler::{hash, permutation_element, to_unit, Sampler};
+
+/// A sampler splitting each dimension in as many strata as there are
+/// samples by pixel, and jittering one sample in each of them. The strata
+/// are visited in a different order for each pixel and dimension, and the
+/// jitter is a hash of the sample, so that samples are reproducible.
+pub struct StratifiedSampler {
+    seed: u64,
+    samples_per_pixel: u32,
+    /// Columns and rows of the strata of two dimensions at once
+    grid: (u32, u32),
+    pixel: (u32, u32),
+    index: u32,
+    dimension: u32,
+}
+
+impl StratifiedSampler {
+    /// Constructs a new sampler, whose samples depend on the `seed`
+    pub fn new(samples_per_pixel: u32, seed: u64) -> StratifiedSampler {
+        let samples_per_pixel = samples_per_pixel.max(1);
+
+        // The squarest grid with one stratum per sample
+        let columns = (1..=samples_per_pixel)
+            .take_while(|columns| columns * columns <= samples_per_pixel)
+            .filter(|columns| samples_per_pixel.is_multiple_of(*columns))
+            .last()
+            .unwrap_or(1);
+
+        StratifiedSampler {
+            seed,
+            samples_per_pixel,
+            grid: (columns, samples_per_pixel / columns),
+            pixel: (0, 0),
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    /// Returns a hash of the current pixel and dimension
+    fn dimension_seed(&self) -> u64 {
+        hash(&[
+            self.seed,
+            self.pixel.0 as u64,
+            self.pixel.1 as u64,
+            self.dimension as u64,
+        ])
+    }
+
+    /// Returns the stratum of the current sample among `count` strata
+    fn stratum(&self, count: u32) -> u32 {
+        permutation_element(self.index % count, count, self.dimension_seed() as u32)
+    }
+
+    /// Returns the jitter of the current sample in its stratum
+    fn jitter(&self, salt: u64) -> f64 {
+        to_unit(hash(&[self.dimension_seed(), self.index as u64, salt]) as u32)
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn clone_sampler(&self) -> Box<dyn Sampler + Send> {
+        Box::new(StratifiedSampler::new(self.samples_per_pixel, self.seed))
+    }
+
+    fn start_pixel_sample(&mut self, x: u32, y: u32, index: u32) {
+        self.pixel = (x, y);
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let stratum = self.stratum(self.samples_per_pixel);
+        let value = (stratum as f64 + self.jitter(0)) / self.samples_per_pixel as f64;
+        self.dimension += 1;
+
+        value
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let (columns, rows) = self.grid;
+        let stratum = self.stratum(columns * rows);
+        let value = (
+            ((stratum % columns) as f64 + self.jitter(0)) / columns as f64,
+            ((stratum / columns) as f64 + self.jitter(1)) / rows as f64,
+        );
+        self.dimension += 2;
+
+        value
+    }
+}
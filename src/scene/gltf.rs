@@ -0,0 +1,541 @@
+//! Loader of glTF 2.0 scenes (`.gltf` with their resources, or `.glb`).
+//!
+//! Node transforms are applied to the vertices of meshes, which become
+//! [`TriangleMesh`] objects with a [`MetallicRoughness`] material. The first
+//! perspective camera of the scene gives its [`View`], and punctual lights
+//! (`KHR_lights_punctual`) become [`Light`]s, their intensity taken as is:
+//! the sky has a radiance of 1.0.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use gltf::camera::Projection;
+use gltf::image::Format;
+use gltf::khr_lights_punctual::Kind;
+use gltf::mesh::Mode;
+
+use crate::color::Color;
+use crate::hittable::hittable_list::HittableList;
+use crate::hittable::triangle_mesh::TriangleMesh;
+use crate::light::{DirectionalLight, Light, PointLight, SpotLight};
+use crate::material::metallic_roughness::MetallicRoughness;
+use crate::material::Material;
+use crate::mesh::MeshData;
+use crate::scene::{Scene, SceneError, View};
+use crate::texture::image_texture::{srgb_to_linear, ImageTexture};
+use crate::texture::solid_color::SolidColor;
+use crate::transform::Transform;
+use crate::vec3::{Point3, Vec3};
+
+/// Loads the default scene of a glTF file
+pub fn load(path: &Path) -> Result<Scene, SceneError> {
+    let (document, buffers, images) = gltf::import(path)?;
+    Loader::new(&buffers, &images).load(&document)
+}
+
+/// Returns the files a glTF file refers to: its buffers and images given by
+/// relative URIs, decoded into paths relative to its directory. Embedded
+/// resources and other URIs are left out.
+pub fn dependencies(path: &Path) -> Result<Vec<String>, SceneError> {
+    let document = gltf::Gltf::open(path)?.document;
+
+    let buffers = document
+        .buffers()
+        .filter_map(|buffer| match buffer.source() {
+            gltf::buffer::Source::Uri(uri) => Some(uri),
+            gltf::buffer::Source::Bin => None,
+        });
+    let images = document.images().filter_map(|image| match image.source() {
+        gltf::image::Source::Uri { uri, .. } => Some(uri),
+        gltf::image::Source::View { .. } => None,
+    });
+
+    // Like in `gltf::import`, URIs without a scheme are relative paths
+    Ok(buffers
+        .chain(images)
+        .filter(|uri| !uri.contains(':'))
+        .map(decode_uri)
+        .collect())
+}
+
+/// Decodes the percent-encoded bytes of a URI
+fn decode_uri(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut k = 0;
+    while k < bytes.len() {
+        let byte = match (bytes[k], uri.get(k + 1..k + 3)) {
+            (b'%', Some(hex)) => u8::from_str_radix(hex, 16).ok(),
+            _ => None,
+        };
+        match byte {
+            Some(byte) => {
+                decoded.push(byte);
+                k += 3;
+            }
+            None => {
+                decoded.push(bytes[k]);
+                k += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Returns the set of surface coordinates read by the textures of a
+/// material, which must all read the same one as meshes hold a single set
+fn tex_coord(material: &gltf::Material) -> Result<u32, SceneError> {
+    let pbr = material.pbr_metallic_roughness();
+    let mut sets = [
+        pbr.base_color_texture().map(|info| info.tex_coord()),
+        pbr.metallic_roughness_texture()
+            .map(|info| info.tex_coord()),
+        material.emissive_texture().map(|info| info.tex_coord()),
+    ]
+    .into_iter()
+    .flatten();
+
+    let set = sets.next().unwrap_or(0);
+    if sets.any(|other| other != set) {
+        return Err(SceneError::Unsupported(
+            "materials whose textures read different sets of coordinates".to_string(),
+        ));
+    }
+
+    Ok(set)
+}
+
+/// Converts the content of a glTF file, keeping track of the objects
+/// already converted
+struct Loader<'a> {
+    buffers: &'a [gltf::buffer::Data],
+    images: &'a [gltf::image::Data],
+    materials: HashMap<Option<usize>, Arc<dyn Material + Sync + Send>>,
+    world: HittableList,
+    lights: Vec<Arc<dyn Light + Sync + Send>>,
+    view: Option<View>,
+}
+
+fn vec3([x, y, z]: [f32; 3]) -> Vec3 {
+    Vec3::new(x as f64, y as f64, z as f64)
+}
+
+impl<'a> Loader<'a> {
+    fn new(buffers: &'a [gltf::buffer::Data], images: &'a [gltf::image::Data]) -> Loader<'a> {
+        Loader {
+            buffers,
+            images,
+            materials: HashMap::new(),
+            world: HittableList::new(),
+            lights: Vec::new(),
+            view: None,
+        }
+    }
+
+    fn load(mut self, document: &gltf::Document) -> Result<Scene, SceneError> {
+        let scene = document
+            .default_scene()
+            .or_else(|| document.scenes().next())
+            .ok_or_else(|| SceneError::Unsupported("file without scene".to_string()))?;
+
+        for node in scene.nodes() {
+            self.node(&node, Transform::identity())?;
+        }
+
+        Ok(Scene {
+            world: Arc::new(self.world),
+            lights: self.lights,
+            view: self.view,
+            resolution: None,
+            samples_per_pixel: None,
+        })
+    }
+
+    /// Converts a node and its children, `parent` being the transformation
+    /// of its parent node
+    fn node(&mut self, node: &gltf::Node, parent: Transform) -> Result<(), SceneError> {
+        let (translation, [x, y, z, w], scale) = node.transform().decomposed();
+
+        // Rotations are given as unit quaternions
+        let axis = vec3([x, y, z]);
+        let rotation = if axis.near_zero() {
+            Transform::identity()
+        } else {
+            let angle = 2.0 * (w as f64).clamp(-1.0, 1.0).acos();
+            Transform::rotate(angle.to_degrees(), axis)
+        };
+
+        let transform = parent
+            * Transform::translate(vec3(translation))
+            * rotation
+            * Transform::scale(vec3(scale));
+
+        if let Some(mesh) = node.mesh() {
+            for primitive in mesh.primitives() {
+                self.primitive(&primitive, transform)?;
+            }
+        }
+
+        // Cameras and lights look along -Z, with +Y up
+        let origin = transform.point(Point3::zero());
+        let forward = transform.vector(Vec3::new(0.0, 0.0, -1.0));
+
+        if let Some(camera) = node.camera() {
+            if let (None, Projection::Perspective(perspective)) = (&self.view, camera.projection())
+            {
+                self.view = Some(View {
+                    lookfrom: origin,
+                    lookat: origin + forward.normalized(),
+                    vup: transform.vector(Vec3::new(0.0, 1.0, 0.0)),
+                    vertical_fov: (perspective.yfov() as f64).to_degrees(),
+                    aperture: 0.0,
+                    focus_dist: 1.0,
+                    aspect_ratio: perspective.aspect_ratio().map(|ratio| ratio as f64),
+                });
+            }
+        }
+
+        if let Some(light) = node.light() {
+            let intensity = light.intensity() as f64 * vec3(light.color());
+            let light: Arc<dyn Light + Sync + Send> = match light.kind() {
+                Kind::Directional => Arc::new(DirectionalLight::new(forward, intensity)),
+                Kind::Point => Arc::new(PointLight::new(origin, intensity)),
+                Kind::Spot {
+                    inner_cone_angle,
+                    outer_cone_angle,
+                } => Arc::new(SpotLight::new(
+                    origin,
+                    forward,
+                    intensity,
+                    inner_cone_angle as f64,
+                    outer_cone_angle as f64,
+                )),
+            };
+            self.lights.push(light);
+        }
+
+        for child in node.children() {
+            self.node(&child, transform)?;
+        }
+
+        Ok(())
+    }
+
+    /// Converts a primitive of a mesh, moved by `transform`
+    fn primitive(
+        &mut self,
+        primitive: &gltf::Primitive,
+        transform: Transform,
+    ) -> Result<(), SceneError> {
+        let reader = primitive.reader(|buffer| Some(&self.buffers[buffer.index()]));
+
+        let positions: Vec<Point3> = match reader.read_positions() {
+            Some(positions) => positions.map(|p| transform.point(vec3(p))).collect(),
+            None => return Ok(()),
+        };
+
+        let normal_transform = transform.normal_transform();
+        let normals = reader.read_normals().map(|normals| {
+            normals
+                .map(|n| normal_transform.vector(vec3(n)).normalized())
+                .collect()
+        });
+
+        // Meshes hold the coordinates read by the textures of their material,
+        // which start from the top of textures in glTF
+        let material = primitive.material();
+        let uvs = reader.read_tex_coords(tex_coord(&material)?).map(|uvs| {
+            uvs.into_f32()
+                .map(|[u, v]| (u as f64, 1.0 - v as f64))
+                .collect()
+        });
+
+        let colors = reader
+            .read_colors(0)
+            .map(|colors| colors.into_rgb_f32().map(vec3).collect());
+
+        let indices: Vec<usize> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().map(|i| i as usize).collect(),
+            None => (0..positions.len()).collect(),
+        };
+
+        let mut triangles: Vec<[usize; 3]> = match primitive.mode() {
+            Mode::Triangles => indices
+                .chunks_exact(3)
+                .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+                .collect(),
+            Mode::TriangleStrip => indices
+                .windows(3)
+                .enumerate()
+                .map(|(i, w)| {
+                    if i % 2 == 0 {
+                        [w[0], w[1], w[2]]
+                    } else {
+                        [w[1], w[0], w[2]]
+                    }
+                })
+                .collect(),
+            Mode::TriangleFan => indices
+                .windows(2)
+                .skip(1)
+                .map(|w| [indices[0], w[0], w[1]])
+                .collect(),
+            mode => {
+                return Err(SceneError::Unsupported(format!(
+                    "primitives of mode {:?}",
+                    mode
+                )))
+            }
+        };
+
+        if transform.flips_orientation() {
+            for triangle in &mut triangles {
+                triangle.swap(1, 2);
+            }
+        }
+
+        let data = MeshData {
+            positions,
+            normals,
+            uvs,
+            colors,
+            triangles,
+        };
+
+        let material = self.material(&material);
+        self.world.add(Arc::new(TriangleMesh::new(data, material)?));
+
+        Ok(())
+    }
+
+    /// Converts a material, or returns it if it was already converted
+    fn material(&mut self, material: &gltf::Material) -> Arc<dyn Material + Sync + Send> {
+        if let Some(converted) = self.materials.get(&material.index()) {
+            return Arc::clone(converted);
+        }
+
+        let pbr = material.pbr_metallic_roughness();
+        let [r, g, b, _] = pbr.base_color_factor();
+        let base_color = vec3([r, g, b]);
+        let emissive = vec3(material.emissive_factor());
+
+        let mut converted = MetallicRoughness::new(
+            base_color,
+            pbr.metallic_factor() as f64,
+            pbr.roughness_factor() as f64,
+        );
+
+        if let Some(info) = pbr.base_color_texture() {
+            converted = converted.with_base_color(Arc::new(self.texture(
+                &info.texture(),
+                base_color,
+                true,
+            )));
+        }
+        if let Some(info) = pbr.metallic_roughness_texture() {
+            converted = converted.with_metallic_roughness(Arc::new(self.texture(
+                &info.texture(),
+                Color::new(1.0, 1.0, 1.0),
+                false,
+            )));
+        }
+        converted = match material.emissive_texture() {
+            Some(info) => {
+                converted.with_emissive(Arc::new(self.texture(&info.texture(), emissive, true)))
+            }
+            None => converted.with_emissive(Arc::new(SolidColor::new(emissive))),
+        };
+
+        let converted: Arc<dyn Material + Sync + Send> = Arc::new(converted);
+        self.materials
+            .insert(material.index(), Arc::clone(&converted));
+
+        converted
+    }
+
+    /// Converts the image of a texture, multiplied by `factor`. Colors
+    /// encoded in sRGB are converted to linear values.
+    fn texture(&self, texture: &gltf::Texture, factor: Color, srgb: bool) -> ImageTexture {
+        let data = &self.images[texture.source().index()];
+
+        let (channels, size) = match data.format {
+            Format::R8 => (1, 1),
+            Format::R8G8 => (2, 1),
+            Format::R8G8B8 => (3, 1),
+            Format::R8G8B8A8 => (4, 1),
+            Format::R16 => (1, 2),
+            Format::R16G16 => (2, 2),
+            Format::R16G16B16 => (3, 2),
+            Format::R16G16B16A16 => (4, 2),
+            Format::R32G32B32FLOAT => (3, 4),
+            Format::R32G32B32A32FLOAT => (4, 4),
+        };
+
+        let pixels = data
+            .pixels
+            .chunks_exact(channels * size)
+            .map(|pixel| {
+                // Gray images (with or without alpha) only have one color
+                let channel = |c: usize| {
+                    let c = if channels < 3 { 0 } else { c };
+                    let bytes = &pixel[c * size..(c + 1) * size];
+                    let value = match size {
+                        1 => bytes[0] as f64 / 255.0,
+                        2 => u16::from_ne_bytes([bytes[0], bytes[1]]) as f64 / 65535.0,
+                        _ => f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+                    };
+                    if srgb && size < 4 {
+                        srgb_to_linear(value)
+                    } else {
+                        value
+                    }
+                };
+                factor * Color::new(channel(0), channel(1), channel(2))
+            })
+            .collect();
+
+        ImageTexture::new(data.width as usize, data.height as usize, pixels)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A triangle under a node moved up by 1.0, seen by a camera and lit by
+    /// a point light. The buffer holds the three positions of the triangle.
+    const TRIANGLE: &str = r#"{
+        "asset": { "version": "2.0" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0, 1, 2] }],
+        "nodes": [
+            { "mesh": 0, "translation": [0.0, 1.0, 0.0] },
+            { "camera": 0, "translation": [0.0, 0.0, 5.0] },
+            {
+                "extensions": { "KHR_lights_punctual": { "light": 0 } },
+                "translation": [0.0, 3.0, 0.0]
+            }
+        ],
+        "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 }, "material": 0 }] }],
+        "materials": [{ "pbrMetallicRoughness": { "baseColorFactor": [1.0, 0.5, 0.0, 1.0] } }],
+        "cameras": [{ "type": "perspective", "perspective": { "yfov": 0.5, "znear": 0.1 } }],
+        "extensionsUsed": ["KHR_lights_punctual"],
+        "extensions": {
+            "KHR_lights_punctual": { "lights": [{ "type": "point", "intensity": 2.0 }] }
+        },
+        "accessors": [{
+            "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+            "min": [0.0, 0.0, 0.0], "max": [1.0, 1.0, 0.0]
+        }],
+        "bufferViews": [{ "buffer": 0, "byteLength": 36 }],
+        "buffers": [{
+            "byteLength": 36,
+            "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAA"
+        }]
+    }"#;
+
+    #[test]
+    fn load_works() {
+        let (document, buffers, images) = gltf::import_slice(TRIANGLE).unwrap();
+        let scene = Loader::new(&buffers, &images).load(&document).unwrap();
+
+        let view = scene.view.unwrap();
+        assert_eq!(Point3::new(0.0, 0.0, 5.0), view.lookfrom);
+        assert_eq!(Point3::new(0.0, 0.0, 4.0), view.lookat);
+        assert!((view.vertical_fov - 0.5f64.to_degrees()).abs() < 1e-6);
+        assert_eq!(1, scene.lights.len());
+
+        // The triangle is hit where the node moved it
+        let r = crate::ray::Ray::new(Point3::new(0.2, 1.2, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_record = scene.world.hit(&r, 0.0, f64::INFINITY).unwrap();
+        assert!((hit_record.t - 1.0).abs() < 1e-9);
+
+        let r = crate::ray::Ray::new(Point3::new(0.2, 0.2, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        assert!(scene.world.hit(&r, 0.0, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn tex_coord_works() {
+        // The texture reads the second set of coordinates, the first one
+        // being all zeros
+        let gltf = r#"{
+            "asset": { "version": "2.0" },
+            "scenes": [{ "nodes": [0] }],
+            "nodes": [{ "mesh": 0 }],
+            "meshes": [{
+                "primitives": [{
+                    "attributes": { "POSITION": 0, "TEXCOORD_0": 1, "TEXCOORD_1": 2 },
+                    "material": 0
+                }]
+            }],
+            "materials": [{
+                "pbrMetallicRoughness": { "baseColorTexture": { "index": 0, "texCoord": 1 } }
+            }],
+            "textures": [{ "source": 0 }],
+            "images": [{
+                "uri": "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAIAAACQd1PeAAAADElEQVR4nGP438AAAAQBAYDFKhhdAAAAAElFTkSuQmCC"
+            }],
+            "accessors": [
+                {
+                    "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+                    "min": [0.0, 0.0, 0.0], "max": [1.0, 1.0, 0.0]
+                },
+                { "bufferView": 0, "byteOffset": 36, "componentType": 5126, "count": 3, "type": "VEC2" },
+                { "bufferView": 0, "byteOffset": 60, "componentType": 5126, "count": 3, "type": "VEC2" }
+            ],
+            "bufferViews": [{ "buffer": 0, "byteLength": 84 }],
+            "buffers": [{
+                "byteLength": 84,
+                "uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAIA/AAAAAAAAAAAAAIA/"
+            }]
+        }"#;
+        // Images are only imported from files
+        let path =
+            std::env::temp_dir().join(format!("tex_coord_works_{}.gltf", std::process::id()));
+        std::fs::write(&path, gltf).unwrap();
+        let scene = load(&path).unwrap();
+
+        let r = crate::ray::Ray::new(Point3::new(0.2, 0.3, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let hit_record = scene.world.hit(&r, 0.0, f64::INFINITY).unwrap();
+        assert!((hit_record.u - 0.2).abs() < 1e-6);
+        assert!((hit_record.v - 0.7).abs() < 1e-6);
+
+        // Textures of a material reading different sets are not supported
+        let mixed = gltf.replace(
+            r#""texCoord": 1 } }"#,
+            r#""texCoord": 1 }, "metallicRoughnessTexture": { "index": 0 } }"#,
+        );
+        std::fs::write(&path, mixed).unwrap();
+        assert!(load(&path).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn dependencies_works() {
+        let path =
+            std::env::temp_dir().join(format!("dependencies_works_{}.gltf", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"{
+                "asset": { "version": "2.0" },
+                "buffers": [
+                    { "byteLength": 36, "uri": "meshes/tri%20angle.bin" },
+                    { "byteLength": 4, "uri": "data:application/octet-stream;base64,AAAAAA==" }
+                ],
+                "images": [{ "uri": "wood.png" }, { "uri": "https://example.com/sky.png" }]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec!["meshes/tri angle.bin".to_string(), "wood.png".to_string()],
+            dependencies(&path).unwrap()
+        );
+        assert!(dependencies(Path::new("no_such_file.gltf")).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,93 @@
+pub mod gltf;
+pub mod pbrt;
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::sync::Arc;
+
+use crate::hittable::Hittable;
+use crate::light::Light;
+use crate::mesh::MeshError;
+use crate::vec3::{Point3, Vec3};
+
+/// Where the scene is seen from, as given to [`Camera::new`](crate::camera::Camera::new)
+pub struct View {
+    pub lookfrom: Point3,
+    pub lookat: Point3,
+    pub vup: Vec3,
+    /// Vertical field of view, in degrees
+    pub vertical_fov: f64,
+    pub aperture: f64,
+    pub focus_dist: f64,
+    /// Width of the image divided by its height, if the scene gives it
+    pub aspect_ratio: Option<f64>,
+}
+
+/// A scene loaded from a file
+pub struct Scene {
+    pub world: Arc<dyn Hittable + Sync + Send>,
+    pub lights: Vec<Arc<dyn Light + Sync + Send>>,
+    /// The camera of the scene, if it has one
+    pub view: Option<View>,
+    /// Width and height of the image, if the scene gives them
+    pub resolution: Option<(u32, u32)>,
+    /// Number of samples by pixel, if the scene gives it
+    pub samples_per_pixel: Option<i32>,
+}
+
+/// Error occurring while loading a scene
+#[derive(Debug)]
+pub enum SceneError {
+    /// The file could not be read
+    Io(io::Error),
+    /// The glTF file could not be read or is invalid
+    Gltf(::gltf::Error),
+    /// A mesh referenced by the scene could not be loaded
+    Mesh(MeshError),
+    /// The scene uses a feature which is not supported
+    Unsupported(String),
+    /// The content of the file is invalid
+    Malformed(String),
+}
+
+impl Display for SceneError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SceneError::Io(err) => write!(f, "cannot read scene file: {}", err),
+            SceneError::Gltf(err) => write!(f, "invalid glTF file: {}", err),
+            SceneError::Mesh(err) => write!(f, "{}", err),
+            SceneError::Unsupported(feature) => write!(f, "unsupported feature: {}", feature),
+            SceneError::Malformed(message) => write!(f, "malformed scene file: {}", message),
+        }
+    }
+}
+
+impl Error for SceneError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SceneError::Io(err) => Some(err),
+            SceneError::Gltf(err) => Some(err),
+            SceneError::Mesh(err) => Some(err),
+            SceneError::Unsupported(_) | SceneError::Malformed(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for SceneError {
+    fn from(err: io::Error) -> Self {
+        SceneError::Io(err)
+    }
+}
+
+impl From<::gltf::Error> for SceneError {
+    fn from(err: ::gltf::Error) -> Self {
+        SceneError::Gltf(err)
+    }
+}
+
+impl From<MeshError> for SceneError {
+    fn from(err: MeshError) -> Self {
+        SceneError::Mesh(err)
+    }
+}
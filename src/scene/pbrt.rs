@@ -0,0 +1,809 @@
+//! Loader of a subset of pbrt-v3 scene files.
+//!
+//! Supported directives are the transformations (`LookAt`, `Translate`,
+//! `Scale`, `Rotate`, `Transform`, `ConcatTransform`...), `Camera
+//! "perspective"`, `Film`, `Sampler`, `AttributeBegin`/`AttributeEnd`,
+//! `Material "matte"`, `"metal"` and `"glass"`, `Shape "sphere"`,
+//! `"trianglemesh"` and `"plymesh"`, and `LightSource "point"`, `"spot"`
+//! and `"distant"`. Render settings of pbrt (`Integrator`, `PixelFilter`,
+//! `Accelerator`) are ignored, any other directive is an error.
+//!
+//! pbrt images are mirrored compared to this renderer's camera, so the
+//! whole scene is mirrored around the camera to match them.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::color::Color;
+use crate::hittable::hittable_list::HittableList;
+use crate::hittable::sphere::Sphere;
+use crate::hittable::triangle_mesh::TriangleMesh;
+use crate::light::{DirectionalLight, Light, PointLight, SpotLight};
+use crate::material::dielectric::Dielectric;
+use crate::material::lambertian::Lambertian;
+use crate::material::metal::Metal;
+use crate::material::Material;
+use crate::mesh::MeshData;
+use crate::scene::{Scene, SceneError, View};
+use crate::transform::Transform;
+use crate::vec3::{Point3, Vec3};
+
+/// Loads a pbrt scene file; meshes of `plymesh` shapes are looked for next
+/// to it
+pub fn load(path: &Path) -> Result<Scene, SceneError> {
+    let text = std::fs::read_to_string(path)?;
+    parse(&text, path.parent().unwrap_or_else(|| Path::new("")))
+}
+
+/// Returns the files a pbrt scene file refers to, as written in it: the
+/// meshes of its `plymesh` shapes, relative to its directory
+pub fn dependencies(path: &Path) -> Result<Vec<String>, SceneError> {
+    let text = std::fs::read_to_string(path)?;
+    let mut parser = Parser {
+        tokens: tokenize(&text)?,
+        position: 0,
+    };
+
+    let mut files = Vec::new();
+    while let Some((token, _)) = parser.next() {
+        if token == Token::Identifier("Shape".to_string())
+            && parser.peek() == Some(&Token::String("plymesh".to_string()))
+        {
+            parser.next();
+            if let Some(filename) = parser.parameters()?.string("filename") {
+                files.push(filename.to_string());
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+/// Parses the content of a pbrt scene file
+fn parse(text: &str, directory: &Path) -> Result<Scene, SceneError> {
+    let mut parser = Parser {
+        tokens: tokenize(text)?,
+        position: 0,
+    };
+    let mut loader = Loader::new(directory);
+
+    while let Some((token, line)) = parser.next() {
+        let directive = match token {
+            Token::Identifier(directive) => directive,
+            token => {
+                return Err(SceneError::Malformed(format!(
+                    "line {}: expected a directive, found {:?}",
+                    line, token
+                )))
+            }
+        };
+
+        loader
+            .directive(&directive, &mut parser)
+            .map_err(|err| match err {
+                SceneError::Malformed(message) => {
+                    SceneError::Malformed(format!("line {}: {}", line, message))
+                }
+                SceneError::Unsupported(feature) => {
+                    SceneError::Unsupported(format!("line {}: {}", line, feature))
+                }
+                err => err,
+            })?;
+    }
+
+    loader.finish()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Identifier(String),
+    String(String),
+    Number(f64),
+    Open,
+    Close,
+}
+
+/// Splits the text into tokens, with the line where they are
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>, SceneError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            c if c.is_whitespace() => (),
+            '#' => while chars.next_if(|&c| c != '\n').is_some() {},
+            '[' => tokens.push((Token::Open, line)),
+            ']' => tokens.push((Token::Close, line)),
+            '"' => {
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\n') | None => {
+                            return Err(SceneError::Malformed(format!(
+                                "line {}: unterminated string",
+                                line
+                            )))
+                        }
+                        Some(c) => string.push(c),
+                    }
+                }
+                tokens.push((Token::String(string), line));
+            }
+            c => {
+                let mut word = c.to_string();
+                while let Some(c) =
+                    chars.next_if(|&c| !c.is_whitespace() && !matches!(c, '[' | ']' | '"' | '#'))
+                {
+                    word.push(c);
+                }
+
+                let token = if c.is_ascii_alphabetic() {
+                    Token::Identifier(word)
+                } else {
+                    Token::Number(word.parse().map_err(|_| {
+                        SceneError::Malformed(format!("line {}: invalid number {:?}", line, word))
+                    })?)
+                };
+                tokens.push((token, line));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Reads the arguments of directives from the tokens
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+}
+
+fn malformed(message: &str) -> SceneError {
+    SceneError::Malformed(message.to_string())
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<(Token, usize)> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn string(&mut self) -> Result<String, SceneError> {
+        match self.next() {
+            Some((Token::String(string), _)) => Ok(string),
+            _ => Err(malformed("expected a string")),
+        }
+    }
+
+    /// Reads `count` numbers, which may be enclosed in brackets
+    fn numbers(&mut self, count: usize) -> Result<Vec<f64>, SceneError> {
+        let bracketed = self.peek() == Some(&Token::Open);
+        if bracketed {
+            self.position += 1;
+        }
+
+        let numbers = (0..count)
+            .map(|_| match self.next() {
+                Some((Token::Number(number), _)) => Ok(number),
+                _ => Err(malformed(&format!("expected {} numbers", count))),
+            })
+            .collect::<Result<Vec<f64>, SceneError>>()?;
+
+        if bracketed && self.next().map(|(token, _)| token) != Some(Token::Close) {
+            return Err(malformed("expected a closing bracket"));
+        }
+
+        Ok(numbers)
+    }
+
+    /// Reads a list of parameters, like `"float radius" [ 2 ]`
+    fn parameters(&mut self) -> Result<Parameters, SceneError> {
+        let mut parameters = HashMap::new();
+
+        while let Some(Token::String(declaration)) = self.peek() {
+            let words: Vec<&str> = declaration.split_ascii_whitespace().collect();
+            let (kind, name) = match words.as_slice() {
+                [kind, name] => (kind.to_string(), name.to_string()),
+                _ => return Err(malformed(&format!("invalid parameter {:?}", declaration))),
+            };
+            self.position += 1;
+
+            let mut values = Vec::new();
+            if self.peek() == Some(&Token::Open) {
+                self.position += 1;
+                loop {
+                    match self.next() {
+                        Some((Token::Close, _)) => break,
+                        Some((Token::Open, _)) | None => {
+                            return Err(malformed(&format!("invalid values of {:?}", name)))
+                        }
+                        Some((value, _)) => values.push(value),
+                    }
+                }
+            } else {
+                match self.next() {
+                    Some((token, _)) if token != Token::Close => values.push(token),
+                    _ => return Err(malformed(&format!("missing value of {:?}", name))),
+                }
+            }
+
+            parameters.insert(name, (kind, values));
+        }
+
+        Ok(Parameters { parameters })
+    }
+}
+
+/// Parameters of a directive, by name, with their type
+struct Parameters {
+    parameters: HashMap<String, (String, Vec<Token>)>,
+}
+
+impl Parameters {
+    /// Returns the numbers given for a parameter
+    fn numbers(&self, name: &str) -> Result<Option<Vec<f64>>, SceneError> {
+        match self.parameters.get(name) {
+            None => Ok(None),
+            Some((_, values)) => values
+                .iter()
+                .map(|value| match value {
+                    Token::Number(number) => Ok(*number),
+                    _ => Err(malformed(&format!("{:?} must be numbers", name))),
+                })
+                .collect::<Result<Vec<f64>, SceneError>>()
+                .map(Some),
+        }
+    }
+
+    fn float(&self, name: &str, default: f64) -> Result<f64, SceneError> {
+        match self.numbers(name)?.as_deref() {
+            None => Ok(default),
+            Some([value]) => Ok(*value),
+            Some(_) => Err(malformed(&format!("{:?} must be a single number", name))),
+        }
+    }
+
+    fn point(&self, name: &str, default: Point3) -> Result<Point3, SceneError> {
+        match self.numbers(name)?.as_deref() {
+            None => Ok(default),
+            Some([x, y, z]) => Ok(Point3::new(*x, *y, *z)),
+            Some(_) => Err(malformed(&format!("{:?} must have three numbers", name))),
+        }
+    }
+
+    /// Returns a color, given in RGB or as a single gray value
+    fn color(&self, name: &str, default: Color) -> Result<Color, SceneError> {
+        if let Some((kind, _)) = self.parameters.get(name) {
+            if !matches!(kind.as_str(), "rgb" | "color" | "float") {
+                return Err(SceneError::Unsupported(format!(
+                    "{} values of {:?}",
+                    kind, name
+                )));
+            }
+        }
+
+        match self.numbers(name)?.as_deref() {
+            None => Ok(default),
+            Some([gray]) => Ok(Color::new(*gray, *gray, *gray)),
+            Some([r, g, b]) => Ok(Color::new(*r, *g, *b)),
+            Some(_) => Err(malformed(&format!("{:?} must be an RGB color", name))),
+        }
+    }
+
+    fn string(&self, name: &str) -> Option<&str> {
+        match self.parameters.get(name) {
+            Some((_, values)) => match values.as_slice() {
+                [Token::String(string)] => Some(string),
+                _ => None,
+            },
+            None => None,
+        }
+    }
+}
+
+/// Perspective camera, placed by `camera_to_world`
+struct CameraParameters {
+    camera_to_world: Transform,
+    fov: f64,
+    lens_radius: f64,
+    focal_distance: f64,
+}
+
+/// Transformation and material applying to the next shapes
+#[derive(Clone)]
+struct GraphicsState {
+    transform: Transform,
+    material: Arc<dyn Material + Sync + Send>,
+}
+
+/// Builds the scene directive after directive
+struct Loader<'a> {
+    directory: &'a Path,
+    state: GraphicsState,
+    stack: Vec<GraphicsState>,
+    /// Reflection of the scene turning pbrt images into this renderer's
+    mirror: Transform,
+    camera: Option<CameraParameters>,
+    resolution: (u32, u32),
+    samples_per_pixel: Option<i32>,
+    world: HittableList,
+    lights: Vec<Arc<dyn Light + Sync + Send>>,
+}
+
+impl<'a> Loader<'a> {
+    fn new(directory: &'a Path) -> Loader<'a> {
+        Loader {
+            directory,
+            state: GraphicsState {
+                transform: Transform::identity(),
+                material: Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+            },
+            stack: Vec::new(),
+            mirror: Transform::identity(),
+            camera: None,
+            resolution: (640, 480),
+            samples_per_pixel: None,
+            world: HittableList::new(),
+            lights: Vec::new(),
+        }
+    }
+
+    fn directive(&mut self, directive: &str, parser: &mut Parser) -> Result<(), SceneError> {
+        let transform = &mut self.state.transform;
+
+        match directive {
+            "Identity" => *transform = Transform::identity(),
+            "Translate" => {
+                let v = parser.numbers(3)?;
+                *transform = *transform * Transform::translate(Vec3::new(v[0], v[1], v[2]));
+            }
+            "Scale" => {
+                let v = parser.numbers(3)?;
+                *transform = *transform * Transform::scale(Vec3::new(v[0], v[1], v[2]));
+            }
+            "Rotate" => {
+                let v = parser.numbers(4)?;
+                *transform = *transform * Transform::rotate(v[0], Vec3::new(v[1], v[2], v[3]));
+            }
+            "LookAt" => {
+                let v = parser.numbers(9)?;
+                *transform = *transform
+                    * Transform::look_at(
+                        Point3::new(v[0], v[1], v[2]),
+                        Point3::new(v[3], v[4], v[5]),
+                        Vec3::new(v[6], v[7], v[8]),
+                    );
+            }
+            "Transform" | "ConcatTransform" => {
+                // Matrices are given column by column
+                let v = parser.numbers(16)?;
+                let mut rows = [[0.0; 4]; 4];
+                for (i, row) in rows.iter_mut().enumerate() {
+                    for (j, value) in row.iter_mut().enumerate() {
+                        *value = v[4 * j + i];
+                    }
+                }
+
+                *transform = if directive == "Transform" {
+                    Transform::new(rows)
+                } else {
+                    *transform * Transform::new(rows)
+                };
+            }
+            "Camera" => {
+                let kind = parser.string()?;
+                let parameters = parser.parameters()?;
+                if kind != "perspective" {
+                    return Err(SceneError::Unsupported(format!("camera {:?}", kind)));
+                }
+                self.camera(&parameters)?;
+            }
+            "Film" => {
+                parser.string()?;
+                let parameters = parser.parameters()?;
+                self.resolution = (
+                    parameters.float("xresolution", 640.0)? as u32,
+                    parameters.float("yresolution", 480.0)? as u32,
+                );
+            }
+            "Sampler" => {
+                parser.string()?;
+                let parameters = parser.parameters()?;
+                self.samples_per_pixel = Some(parameters.float("pixelsamples", 16.0)? as i32);
+            }
+            "Integrator" | "PixelFilter" | "Accelerator" => {
+                parser.string()?;
+                parser.parameters()?;
+            }
+            "WorldBegin" => *transform = Transform::identity(),
+            "WorldEnd" | "ReverseOrientation" => (),
+            "AttributeBegin" | "TransformBegin" => self.stack.push(self.state.clone()),
+            "AttributeEnd" => {
+                self.state = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| malformed("unmatched AttributeEnd"))?;
+            }
+            "TransformEnd" => {
+                let saved = self
+                    .stack
+                    .pop()
+                    .ok_or_else(|| malformed("unmatched TransformEnd"))?;
+                self.state.transform = saved.transform;
+            }
+            "Material" => {
+                let kind = parser.string()?;
+                let parameters = parser.parameters()?;
+                self.state.material = material(&kind, &parameters)?;
+            }
+            "Shape" => {
+                let kind = parser.string()?;
+                let parameters = parser.parameters()?;
+                self.shape(&kind, &parameters)?;
+            }
+            "LightSource" => {
+                let kind = parser.string()?;
+                let parameters = parser.parameters()?;
+                self.light(&kind, &parameters)?;
+            }
+            _ => return Err(SceneError::Unsupported(format!("directive {}", directive))),
+        }
+
+        Ok(())
+    }
+
+    /// Places the camera at the current transformation, and mirrors the
+    /// scene if pbrt would see it mirrored
+    fn camera(&mut self, parameters: &Parameters) -> Result<(), SceneError> {
+        let camera_to_world = self
+            .state
+            .transform
+            .inverse()
+            .ok_or_else(|| malformed("singular camera transformation"))?;
+
+        let eye = camera_to_world.point(Point3::zero());
+        let forward = camera_to_world.vector(Vec3::new(0.0, 0.0, 1.0));
+        let up = camera_to_world.vector(Vec3::new(0.0, 1.0, 0.0));
+        let right = camera_to_world.vector(Vec3::new(1.0, 0.0, 0.0));
+
+        // Right of the image of this renderer's camera
+        let u = Vec3::cross(&up, &-forward);
+        self.mirror = if Vec3::dot(&u, &right) < 0.0 {
+            Transform::mirror(eye, u)
+        } else {
+            Transform::identity()
+        };
+
+        self.camera = Some(CameraParameters {
+            camera_to_world,
+            fov: parameters.float("fov", 90.0)?,
+            lens_radius: parameters.float("lensradius", 0.0)?,
+            focal_distance: parameters.float("focaldistance", 1e6)?,
+        });
+
+        Ok(())
+    }
+
+    /// Returns the transformation from the current space to the scene
+    fn to_scene(&self) -> Transform {
+        self.mirror * self.state.transform
+    }
+
+    fn shape(&mut self, kind: &str, parameters: &Parameters) -> Result<(), SceneError> {
+        let transform = self.to_scene();
+        let material = Arc::clone(&self.state.material);
+
+        match kind {
+            "sphere" => {
+                let radius = parameters.float("radius", 1.0)?;
+
+                // Spheres stay spheres: uneven scales are averaged
+                let scale = [
+                    Vec3::new(1.0, 0.0, 0.0),
+                    Vec3::new(0.0, 1.0, 0.0),
+                    Vec3::new(0.0, 0.0, 1.0),
+                ]
+                .iter()
+                .map(|axis| transform.vector(*axis).length())
+                .sum::<f64>()
+                    / 3.0;
+
+                self.world.add(Arc::new(Sphere::new(
+                    transform.point(Point3::zero()),
+                    scale * radius,
+                    material,
+                )));
+            }
+            "trianglemesh" => {
+                let triples = |numbers: Vec<f64>| -> Result<Vec<Vec3>, SceneError> {
+                    if !numbers.len().is_multiple_of(3) {
+                        return Err(malformed("vectors must have three numbers"));
+                    }
+                    Ok(numbers
+                        .chunks_exact(3)
+                        .map(|v| Vec3::new(v[0], v[1], v[2]))
+                        .collect())
+                };
+
+                let positions = triples(
+                    parameters
+                        .numbers("P")?
+                        .ok_or_else(|| malformed("triangle mesh without positions"))?,
+                )?;
+                let normals = parameters.numbers("N")?.map(triples).transpose()?;
+                let uvs = match parameters.numbers("uv")? {
+                    Some(uvs) => Some(uvs),
+                    None => parameters.numbers("st")?,
+                }
+                .map(|uvs| uvs.chunks_exact(2).map(|uv| (uv[0], uv[1])).collect());
+
+                let indices = match parameters.numbers("indices")? {
+                    Some(indices) => indices,
+                    None if positions.len() == 3 => vec![0.0, 1.0, 2.0],
+                    None => return Err(malformed("triangle mesh without indices")),
+                };
+                if !indices.len().is_multiple_of(3)
+                    || indices.iter().any(|i| *i < 0.0 || i.fract() != 0.0)
+                {
+                    return Err(malformed("invalid triangle indices"));
+                }
+                let triangles = indices
+                    .chunks_exact(3)
+                    .map(|t| [t[0] as usize, t[1] as usize, t[2] as usize])
+                    .collect();
+
+                let data = MeshData {
+                    positions,
+                    normals,
+                    uvs,
+                    colors: None,
+                    triangles,
+                };
+                self.mesh(data, transform, material)?;
+            }
+            "plymesh" => {
+                let filename = parameters
+                    .string("filename")
+                    .ok_or_else(|| malformed("PLY mesh without file name"))?;
+                let data = crate::mesh::load(&self.directory.join(filename))?;
+                self.mesh(data, transform, material)?;
+            }
+            _ => return Err(SceneError::Unsupported(format!("shape {:?}", kind))),
+        }
+
+        Ok(())
+    }
+
+    /// Adds a mesh moved by `transform`
+    fn mesh(
+        &mut self,
+        mut data: MeshData,
+        transform: Transform,
+        material: Arc<dyn Material + Sync + Send>,
+    ) -> Result<(), SceneError> {
+        let normal_transform = transform.normal_transform();
+        for p in &mut data.positions {
+            *p = transform.point(*p);
+        }
+        for n in data.normals.iter_mut().flatten() {
+            *n = normal_transform.vector(*n).normalized();
+        }
+        if transform.flips_orientation() {
+            for triangle in &mut data.triangles {
+                triangle.swap(1, 2);
+            }
+        }
+
+        self.world.add(Arc::new(TriangleMesh::new(data, material)?));
+        Ok(())
+    }
+
+    fn light(&mut self, kind: &str, parameters: &Parameters) -> Result<(), SceneError> {
+        let transform = self.to_scene();
+        let scale = parameters.color("scale", Color::new(1.0, 1.0, 1.0))?;
+        let from = parameters.point("from", Point3::zero())?;
+        let to = parameters.point("to", Point3::new(0.0, 0.0, 1.0))?;
+
+        let light: Arc<dyn Light + Sync + Send> = match kind {
+            "point" => Arc::new(PointLight::new(
+                transform.point(from),
+                scale * parameters.color("I", Color::new(1.0, 1.0, 1.0))?,
+            )),
+            "spot" => {
+                let cone_angle = parameters.float("coneangle", 30.0)?;
+                let cone_delta = parameters.float("conedeltaangle", 5.0)?;
+                Arc::new(SpotLight::new(
+                    transform.point(from),
+                    transform.vector(to - from),
+                    scale * parameters.color("I", Color::new(1.0, 1.0, 1.0))?,
+                    (cone_angle - cone_delta).max(0.0).to_radians(),
+                    cone_angle.to_radians(),
+                ))
+            }
+            "distant" => Arc::new(DirectionalLight::new(
+                transform.vector(to - from),
+                scale * parameters.color("L", Color::new(1.0, 1.0, 1.0))?,
+            )),
+            _ => return Err(SceneError::Unsupported(format!("light {:?}", kind))),
+        };
+
+        self.lights.push(light);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Scene, SceneError> {
+        if !self.stack.is_empty() {
+            return Err(malformed("unmatched AttributeBegin"));
+        }
+
+        let (width, height) = self.resolution;
+        let aspect_ratio = width as f64 / height as f64;
+
+        let view = self.camera.map(|camera| {
+            let c2w = camera.camera_to_world;
+            let eye = c2w.point(Point3::zero());
+
+            // The field of view of pbrt spans the shorter side of the image
+            let half = (camera.fov.to_radians() / 2.0).tan();
+            let vertical_fov = if aspect_ratio < 1.0 {
+                2.0 * (half / aspect_ratio).atan().to_degrees()
+            } else {
+                camera.fov
+            };
+
+            let (aperture, focus_dist) = if camera.lens_radius > 0.0 {
+                (2.0 * camera.lens_radius, camera.focal_distance)
+            } else {
+                (0.0, 1.0)
+            };
+
+            View {
+                lookfrom: eye,
+                lookat: eye + c2w.vector(Vec3::new(0.0, 0.0, 1.0)).normalized(),
+                vup: c2w.vector(Vec3::new(0.0, 1.0, 0.0)),
+                vertical_fov,
+                aperture,
+                focus_dist,
+                aspect_ratio: Some(aspect_ratio),
+            }
+        });
+
+        Ok(Scene {
+            world: Arc::new(self.world),
+            lights: self.lights,
+            view,
+            resolution: Some(self.resolution),
+            samples_per_pixel: self.samples_per_pixel,
+        })
+    }
+}
+
+/// Converts a material of pbrt
+fn material(
+    kind: &str,
+    parameters: &Parameters,
+) -> Result<Arc<dyn Material + Sync + Send>, SceneError> {
+    Ok(match kind {
+        "matte" => Arc::new(Lambertian::new(
+            parameters.color("Kd", Color::new(0.5, 0.5, 0.5))?,
+        )),
+        "metal" => {
+            // Reflectance at normal incidence of the complex index of
+            // refraction, copper by default
+            let eta = parameters.color("eta", Color::new(0.2004, 0.9240, 1.1022))?;
+            let k = parameters.color("k", Color::new(3.9129, 2.4528, 2.1421))?;
+            let reflectance =
+                |eta: f64, k: f64| ((eta - 1.0).powi(2) + k * k) / ((eta + 1.0).powi(2) + k * k);
+
+            Arc::new(Metal::new(
+                Color::new(
+                    reflectance(eta.x(), k.x()),
+                    reflectance(eta.y(), k.y()),
+                    reflectance(eta.z(), k.z()),
+                ),
+                parameters.float("roughness", 0.01)?,
+            ))
+        }
+        "glass" => {
+            let index = parameters.float("index", 1.5)?;
+            Arc::new(Dielectric::new(parameters.float("eta", index)?))
+        }
+        _ => return Err(SceneError::Unsupported(format!("material {:?}", kind))),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ray::Ray;
+
+    const SCENE: &str = r#"
+        # A sphere and a triangle under a point light
+        LookAt 0 0 -10  0 0 0  0 1 0
+        Camera "perspective" "float fov" [ 30 ]
+        Film "image" "integer xresolution" [ 200 ] "integer yresolution" [ 100 ]
+            "string filename" "out.exr"
+        Sampler "halton" "integer pixelsamples" 64
+        WorldBegin
+        LightSource "point" "rgb I" [ 5 5 5 ] "point from" [ 0 5 0 ]
+        AttributeBegin
+            Translate 2 0 0
+            Material "glass"
+            Shape "sphere" "float radius" 0.5
+        AttributeEnd
+        Material "matte" "rgb Kd" [ 0.2 0.4 0.6 ]
+        Shape "trianglemesh" "integer indices" [ 0 1 2 ]
+            "point P" [ -3 -1 0  -1 -1 0  -3 1 0 ]
+        WorldEnd
+    "#;
+
+    #[test]
+    fn parse_works() {
+        let scene = parse(SCENE, Path::new("")).unwrap();
+
+        assert_eq!(Some((200, 100)), scene.resolution);
+        assert_eq!(Some(64), scene.samples_per_pixel);
+        assert_eq!(1, scene.lights.len());
+
+        let view = scene.view.unwrap();
+        assert_eq!(Point3::new(0.0, 0.0, -10.0), view.lookfrom);
+        assert_eq!(30.0, view.vertical_fov);
+
+        // pbrt sees +X on the right from there, unlike this renderer's camera:
+        // the scene is mirrored
+        let r = Ray::new(Point3::new(-2.0, 0.0, -10.0), Vec3::new(0.0, 0.0, 1.0));
+        let hit_record = scene.world.hit(&r, 0.0, f64::INFINITY).unwrap();
+        assert!((hit_record.t - 9.5).abs() < 1e-9);
+
+        let r = Ray::new(Point3::new(2.5, -0.5, -10.0), Vec3::new(0.0, 0.0, 1.0));
+        let hit_record = scene.world.hit(&r, 0.0, f64::INFINITY).unwrap();
+        assert!((hit_record.t - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn dependencies_works() {
+        let path =
+            std::env::temp_dir().join(format!("dependencies_works_{}.pbrt", std::process::id()));
+        let scene = format!(
+            "{}\nShape \"plymesh\" \"string filename\" \"meshes/bunny.ply\"\nShape \"plymesh\" \"string filename\" [ \"/data/dragon.ply\" ]\n",
+            SCENE
+        );
+        std::fs::write(&path, scene).unwrap();
+
+        assert_eq!(
+            vec!["meshes/bunny.ply", "/data/dragon.ply"],
+            dependencies(&path).unwrap()
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn errors_give_the_line() {
+        let scene = "WorldBegin\nShape \"cylinder\"\n";
+        match parse(scene, Path::new("")) {
+            Err(SceneError::Unsupported(message)) => assert!(message.starts_with("line 2")),
+            _ => panic!("cylinders are not supported"),
+        }
+
+        let scene = "WorldBegin\nAttributeEnd\n";
+        assert!(matches!(
+            parse(scene, Path::new("")),
+            Err(SceneError::Malformed(_))
+        ));
+
+        let scene = "Translate 1 2\n";
+        assert!(matches!(
+            parse(scene, Path::new("")),
+            Err(SceneError::Malformed(_))
+        ));
+    }
+}
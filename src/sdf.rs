@@ -0,0 +1,142 @@
+//! Library of signed distance functions and combinators, to be rendered with
+//! the [`Sdf`](crate::hittable::sdf::Sdf) hittable.
+//!
+//! A signed distance function returns the distance from a point to the
+//! surface of an object: negative inside, positive outside. Combinators build
+//! new functions from existing ones.
+
+use std::sync::Arc;
+
+use crate::vec3::{Point3, Vec3};
+
+/// A signed distance function
+pub type Distance = Arc<dyn Fn(Point3) -> f64 + Sync + Send>;
+
+/// Returns the vector of the absolute values of each coordinate
+fn abs(p: Vec3) -> Vec3 {
+    Vec3::new(p.x().abs(), p.y().abs(), p.z().abs())
+}
+
+/// Returns the vector of the maximums of each coordinate with `m`
+fn max(p: Vec3, m: f64) -> Vec3 {
+    Vec3::new(p.x().max(m), p.y().max(m), p.z().max(m))
+}
+
+/// A sphere of given `center` and `radius`
+pub fn sphere(center: Point3, radius: f64) -> Distance {
+    Arc::new(move |p| (p - center).length() - radius)
+}
+
+/// A box of given `center` and `half_size`, whose edges are rounded with
+/// `radius`
+pub fn rounded_box(center: Point3, half_size: Vec3, radius: f64) -> Distance {
+    let inner = half_size - Vec3::new(radius, radius, radius);
+    Arc::new(move |p| {
+        let q = abs(p - center) - inner;
+        max(q, 0.0).length() + q.x().max(q.y().max(q.z())).min(0.0) - radius
+    })
+}
+
+/// A torus around the Y axis, with `major_radius` the distance from the
+/// center to the middle of the tube and `minor_radius` the radius of the tube
+pub fn torus(center: Point3, major_radius: f64, minor_radius: f64) -> Distance {
+    Arc::new(move |p| {
+        let p = p - center;
+        let ring = (p.x() * p.x() + p.z() * p.z()).sqrt() - major_radius;
+        (ring * ring + p.y() * p.y()).sqrt() - minor_radius
+    })
+}
+
+/// A Menger sponge fractal, an axis-aligned cube of given `center` and
+/// `half_size` recursively pierced `iterations` times
+pub fn menger_sponge(center: Point3, half_size: f64, iterations: u32) -> Distance {
+    Arc::new(move |p| {
+        // Work in the frame of the unit sponge
+        let p = (p - center) / half_size;
+        let q = abs(p) - Vec3::new(1.0, 1.0, 1.0);
+        let mut d = max(q, 0.0).length() + q.x().max(q.y().max(q.z())).min(0.0);
+
+        let mut scale = 1.0;
+        for _ in 0..iterations {
+            let a = Vec3::new(
+                (p.x() * scale).rem_euclid(2.0) - 1.0,
+                (p.y() * scale).rem_euclid(2.0) - 1.0,
+                (p.z() * scale).rem_euclid(2.0) - 1.0,
+            );
+            scale *= 3.0;
+            let r = abs(Vec3::new(1.0, 1.0, 1.0) - 3.0 * abs(a));
+
+            let da = r.x().max(r.y());
+            let db = r.y().max(r.z());
+            let dc = r.z().max(r.x());
+            let cross = (da.min(db.min(dc)) - 1.0) / scale;
+
+            d = d.max(cross);
+        }
+
+        d * half_size
+    })
+}
+
+/// Points inside either object
+pub fn union(a: Distance, b: Distance) -> Distance {
+    Arc::new(move |p| a(p).min(b(p)))
+}
+
+/// Points inside both objects
+pub fn intersection(a: Distance, b: Distance) -> Distance {
+    Arc::new(move |p| a(p).max(b(p)))
+}
+
+/// Points inside `a` but not inside `b`
+pub fn difference(a: Distance, b: Distance) -> Distance {
+    Arc::new(move |p| a(p).max(-b(p)))
+}
+
+/// Union of both objects blended on a distance of about `k`
+pub fn smooth_union(a: Distance, b: Distance, k: f64) -> Distance {
+    Arc::new(move |p| {
+        let (da, db) = (a(p), b(p));
+        let h = (0.5 + 0.5 * (db - da) / k).clamp(0.0, 1.0);
+        db * (1.0 - h) + da * h - k * h * (1.0 - h)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sphere_works() {
+        let f = sphere(Point3::new(1.0, 0.0, 0.0), 1.0);
+        assert_eq!(-1.0, f(Point3::new(1.0, 0.0, 0.0)));
+        assert_eq!(2.0, f(Point3::new(4.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn rounded_box_works() {
+        let f = rounded_box(Point3::zero(), Vec3::new(1.0, 2.0, 3.0), 0.5);
+        assert_eq!(0.0, f(Point3::new(1.0, 0.0, 0.0)));
+        assert_eq!(-1.0, f(Point3::new(0.0, 0.0, 2.0)));
+        assert_eq!(1.0, f(Point3::new(0.0, 3.0, 0.0)));
+    }
+
+    #[test]
+    fn menger_sponge_is_pierced() {
+        let f = menger_sponge(Point3::zero(), 1.0, 3);
+        assert!(f(Point3::new(0.99, 0.99, 0.99)) < 0.0);
+        assert!(f(Point3::zero()) > 0.0);
+    }
+
+    #[test]
+    fn combinators_work() {
+        let a = sphere(Point3::new(-1.0, 0.0, 0.0), 1.5);
+        let b = sphere(Point3::new(1.0, 0.0, 0.0), 1.5);
+        let p = Point3::new(-2.0, 0.0, 0.0);
+
+        assert_eq!(-0.5, union(a.clone(), b.clone())(p));
+        assert_eq!(1.5, intersection(a.clone(), b.clone())(p));
+        assert_eq!(-0.5, difference(a.clone(), b.clone())(p));
+        assert!(smooth_union(a, b, 0.5)(Point3::zero()) < -0.5);
+    }
+}
@@ -0,0 +1,57 @@
+use crate::color::Color;
+use crate::hittable::HitRecord;
+use crate::texture::Texture;
+
+/// A texture given by an image, wrapped around the surface coordinates:
+/// `(0.0, 0.0)` is the bottom left corner of the image and the image repeats
+/// outside of [0.0, 1.0]
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    /// Colors of the pixels, row by row from the top of the image
+    pixels: Vec<Color>,
+}
+
+impl ImageTexture {
+    /// Constructs a new texture from the colors of the pixels, row by row
+    /// from the top of the image
+    ///
+    /// # Panics
+    ///
+    /// The `new` function will panic if there is not exactly `width * height`
+    /// pixels.
+    pub fn new(width: usize, height: usize, pixels: Vec<Color>) -> ImageTexture {
+        assert_eq!(width * height, pixels.len());
+
+        ImageTexture {
+            width,
+            height,
+            pixels,
+        }
+    }
+}
+
+/// Converts a color component from sRGB encoding to linear
+pub fn srgb_to_linear(value: f64) -> f64 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, record: &HitRecord) -> Color {
+        if self.pixels.is_empty() {
+            return Color::new(0.0, 1.0, 1.0);
+        }
+
+        let u = record.u - record.u.floor();
+        let v = 1.0 - (record.v - record.v.floor());
+
+        let i = ((u * self.width as f64) as usize).min(self.width - 1);
+        let j = ((v * self.height as f64) as usize).min(self.height - 1);
+
+        self.pixels[j * self.width + i]
+    }
+}
@@ -0,0 +1,12 @@
+pub mod image_texture;
+pub mod solid_color;
+pub mod vertex_color;
+
+use crate::color::Color;
+use crate::hittable::HitRecord;
+
+/// Represents a color varying on the surface of an object
+pub trait Texture {
+    /// Returns the color of the texture at the point of the hit
+    fn value(&self, record: &HitRecord) -> Color;
+}
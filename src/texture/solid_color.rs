@@ -0,0 +1,20 @@
+use crate::color::Color;
+use crate::hittable::HitRecord;
+use crate::texture::Texture;
+
+/// A texture of a single color
+pub struct SolidColor {
+    color: Color,
+}
+
+impl SolidColor {
+    pub fn new(color: Color) -> SolidColor {
+        SolidColor { color }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _record: &HitRecord) -> Color {
+        self.color
+    }
+}
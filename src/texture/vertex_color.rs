@@ -0,0 +1,21 @@
+use crate::color::Color;
+use crate::hittable::HitRecord;
+use crate::texture::Texture;
+
+/// A texture using the colors of the vertices of a mesh, interpolated on
+/// its triangles. Objects without vertex colors get the `fallback` color.
+pub struct VertexColor {
+    fallback: Color,
+}
+
+impl VertexColor {
+    pub fn new(fallback: Color) -> VertexColor {
+        VertexColor { fallback }
+    }
+}
+
+impl Texture for VertexColor {
+    fn value(&self, record: &HitRecord) -> Color {
+        record.vertex_color.unwrap_or(self.fallback)
+    }
+}
@@ -0,0 +1,241 @@
+use std::str::FromStr;
+
+/// A rectangle of pixels rendered as a single job
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Tile {
+    /// Column of the first pixel
+    pub x: u32,
+    /// Row of the first pixel
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Tile {
+    /// Returns the tile grown by `margin` pixels on every side, but kept
+    /// within an image of the given size
+    pub fn expanded(&self, margin: u32, image_width: u32, image_height: u32) -> Tile {
+        let x = self.x.saturating_sub(margin);
+        let y = self.y.saturating_sub(margin);
+
+        Tile {
+            x,
+            y,
+            width: (self.x + self.width + margin).min(image_width) - x,
+            height: (self.y + self.height + margin).min(image_height) - y,
+        }
+    }
+
+    /// Returns whether every pixel of `other` is in the tile
+    pub fn contains(&self, other: &Tile) -> bool {
+        let end = |start: u32, length: u32| start as u64 + length as u64;
+
+        self.x <= other.x
+            && self.y <= other.y
+            && end(other.x, other.width) <= end(self.x, self.width)
+            && end(other.y, other.height) <= end(self.y, self.height)
+    }
+}
+
+/// Order in which tiles are rendered
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TileOrder {
+    /// From the center of the image outwards, where the subject usually is
+    Spiral,
+    /// Along a Hilbert curve, so that consecutive tiles are neighbours
+    Hilbert,
+}
+
+impl FromStr for TileOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "spiral" => Ok(TileOrder::Spiral),
+            "hilbert" => Ok(TileOrder::Hilbert),
+            _ => Err(format!("unknown tile order: {}", s)),
+        }
+    }
+}
+
+/// Splits an image in square tiles of `size` pixels (smaller on the right
+/// and top edges), sorted in the given order
+///
+/// # Panics
+///
+/// The `tiles` function will panic if the size is zero.
+pub fn tiles(image_width: u32, image_height: u32, size: u32, order: TileOrder) -> Vec<Tile> {
+    assert!(size > 0);
+
+    let columns = image_width.div_ceil(size);
+    let rows = image_height.div_ceil(size);
+
+    let cells = match order {
+        TileOrder::Spiral => spiral(columns, rows),
+        TileOrder::Hilbert => hilbert(columns, rows),
+    };
+
+    cells
+        .into_iter()
+        .map(|(column, row)| Tile {
+            x: column * size,
+            y: row * size,
+            width: size.min(image_width - column * size),
+            height: size.min(image_height - row * size),
+        })
+        .collect()
+}
+
+/// Returns the cells of a grid along a square spiral from its center
+fn spiral(columns: u32, rows: u32) -> Vec<(u32, u32)> {
+    let total = (columns * rows) as usize;
+    let mut cells = Vec::with_capacity(total);
+
+    let (mut x, mut y) = ((columns as i64 - 1) / 2, (rows as i64 - 1) / 2);
+    let directions = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+    let mut leg = 1;
+    let mut direction = 0;
+
+    while cells.len() < total {
+        // Legs grow every two turns: 1, 1, 2, 2, 3, 3...
+        for _ in 0..2 {
+            for _ in 0..leg {
+                if (0..columns as i64).contains(&x) && (0..rows as i64).contains(&y) {
+                    cells.push((x as u32, y as u32));
+                }
+                x += directions[direction].0;
+                y += directions[direction].1;
+            }
+            direction = (direction + 1) % 4;
+        }
+        leg += 1;
+    }
+
+    cells
+}
+
+/// Returns the cells of a grid along a Hilbert curve covering it
+fn hilbert(columns: u32, rows: u32) -> Vec<(u32, u32)> {
+    let side = columns.max(rows).next_power_of_two();
+
+    let mut cells: Vec<(u32, u32)> = (0..rows)
+        .flat_map(|y| (0..columns).map(move |x| (x, y)))
+        .collect();
+    cells.sort_by_key(|&(x, y)| hilbert_index(side, x, y));
+
+    cells
+}
+
+/// Returns the position of the cell `(x, y)` along the Hilbert curve
+/// covering a square grid of `side` cells, a power of two
+fn hilbert_index(side: u32, mut x: u32, mut y: u32) -> u64 {
+    let mut index = 0;
+    let mut s = side / 2;
+
+    while s > 0 {
+        let rx = (x & s > 0) as u32;
+        let ry = (y & s > 0) as u32;
+        index += (s as u64) * (s as u64) * ((3 * rx) ^ ry) as u64;
+
+        // Rotate the quadrant so that the curve continues
+        if ry == 0 {
+            if rx == 1 {
+                x = side - 1 - x;
+                y = side - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+
+    index
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Checks that every pixel is in exactly one tile
+    fn assert_covers(tiles: &[Tile], width: u32, height: u32) {
+        let mut covered = vec![0; (width * height) as usize];
+        for tile in tiles {
+            for y in tile.y..tile.y + tile.height {
+                for x in tile.x..tile.x + tile.width {
+                    covered[(y * width + x) as usize] += 1;
+                }
+            }
+        }
+        assert!(covered.iter().all(|&count| count == 1));
+    }
+
+    #[test]
+    fn spiral_works() {
+        let tiles = tiles(100, 70, 16, TileOrder::Spiral);
+
+        assert_covers(&tiles, 100, 70);
+        assert_eq!((48, 32), (tiles[0].x, tiles[0].y));
+
+        let corner = tiles.iter().find(|tile| tile.x == 96 && tile.y == 64);
+        assert_eq!(
+            (4, 6),
+            corner.map(|tile| (tile.width, tile.height)).unwrap()
+        );
+    }
+
+    #[test]
+    fn hilbert_works() {
+        let tiles = tiles(128, 128, 16, TileOrder::Hilbert);
+        assert_covers(&tiles, 128, 128);
+
+        // Consecutive tiles are neighbours
+        for pair in tiles.windows(2) {
+            let dx = (pair[0].x as i64 - pair[1].x as i64).abs();
+            let dy = (pair[0].y as i64 - pair[1].y as i64).abs();
+            assert_eq!(16, dx + dy);
+        }
+
+        assert_covers(&super::tiles(100, 70, 16, TileOrder::Hilbert), 100, 70);
+    }
+
+    #[test]
+    fn expanded_works() {
+        let tile = Tile {
+            x: 16,
+            y: 0,
+            width: 16,
+            height: 16,
+        };
+        let expected = Tile {
+            x: 14,
+            y: 0,
+            width: 18,
+            height: 18,
+        };
+        assert_eq!(expected, tile.expanded(2, 32, 40));
+    }
+
+    #[test]
+    fn contains_works() {
+        let image = Tile {
+            x: 0,
+            y: 0,
+            width: 32,
+            height: 40,
+        };
+        let tile = Tile {
+            x: 16,
+            y: 24,
+            width: 16,
+            height: 16,
+        };
+        assert!(image.contains(&tile));
+        assert!(image.contains(&image));
+        assert!(!tile.contains(&image));
+        assert!(!image.contains(&Tile { x: 17, ..tile }));
+        assert!(!image.contains(&Tile {
+            x: u32::MAX,
+            width: 2,
+            ..tile
+        }));
+    }
+}
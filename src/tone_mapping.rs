@@ -0,0 +1,230 @@
+use std::str::FromStr;
+
+use crate::color::Color;
+use crate::vec3::Vec3;
+
+/// Operator compressing the linear colors of a render into the range of a
+/// display
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ToneMapper {
+    /// Clips every component above 1
+    Clamp,
+    /// Maps the luminance `L` to `L / (1 + L)`
+    Reinhard,
+    /// Like Reinhard, but the luminance of the white point is mapped to 1
+    ExtendedReinhard,
+    /// Fit of the ACES filmic curve by Stephen Hill
+    Aces,
+    /// Approximation of the AgX curve of Blender, desaturating highlights
+    Agx,
+}
+
+impl FromStr for ToneMapper {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "clamp" => Ok(ToneMapper::Clamp),
+            "reinhard" => Ok(ToneMapper::Reinhard),
+            "extended-reinhard" => Ok(ToneMapper::ExtendedReinhard),
+            "aces" => Ok(ToneMapper::Aces),
+            "agx" => Ok(ToneMapper::Agx),
+            _ => Err(format!("unknown tone mapper: {}", s)),
+        }
+    }
+}
+
+/// Conversion of the linear colors of a render to the sRGB colors of a
+/// display
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ToneMapping {
+    /// Exposure compensation, in stops
+    pub exposure: f64,
+    pub tone_mapper: ToneMapper,
+    /// Luminance mapped to white by the extended Reinhard operator
+    pub white_point: f64,
+}
+
+impl ToneMapping {
+    /// Returns the sRGB-encoded color, between 0 and 1, displaying a linear
+    /// color
+    pub fn encode(&self, color: Color) -> Color {
+        let exposed = 2.0_f64.powf(self.exposure) * color;
+        let exposed = Color::new(
+            exposed.x().max(0.0),
+            exposed.y().max(0.0),
+            exposed.z().max(0.0),
+        );
+
+        let mapped = match self.tone_mapper {
+            ToneMapper::Clamp => exposed,
+            ToneMapper::Reinhard => scale_luminance(exposed, |l| l / (1.0 + l)),
+            ToneMapper::ExtendedReinhard => {
+                let white = self.white_point * self.white_point;
+                scale_luminance(exposed, |l| l * (1.0 + l / white) / (1.0 + l))
+            }
+            ToneMapper::Aces => aces(exposed),
+            ToneMapper::Agx => agx(exposed),
+        };
+
+        Color::new(
+            linear_to_srgb(mapped.x().clamp(0.0, 1.0)),
+            linear_to_srgb(mapped.y().clamp(0.0, 1.0)),
+            linear_to_srgb(mapped.z().clamp(0.0, 1.0)),
+        )
+    }
+}
+
+/// Converts a color component from linear to sRGB encoding
+pub fn linear_to_srgb(value: f64) -> f64 {
+    if value <= 0.0031308 {
+        12.92 * value
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Returns the color scaled so that its luminance `l` becomes `curve(l)`
+fn scale_luminance(color: Color, curve: impl Fn(f64) -> f64) -> Color {
+    let luminance = color.luminance();
+    if luminance <= 0.0 {
+        return color;
+    }
+
+    curve(luminance) / luminance * color
+}
+
+/// Returns the product of a 3x3 matrix, given by rows, and a color
+fn transform(m: &[[f64; 3]; 3], color: Color) -> Color {
+    Color::new(
+        Vec3::dot(&Vec3::new(m[0][0], m[0][1], m[0][2]), &color),
+        Vec3::dot(&Vec3::new(m[1][0], m[1][1], m[1][2]), &color),
+        Vec3::dot(&Vec3::new(m[2][0], m[2][1], m[2][2]), &color),
+    )
+}
+
+/// Applies a function to every component of a color
+fn map(color: Color, f: impl Fn(f64) -> f64) -> Color {
+    Color::new(f(color.x()), f(color.y()), f(color.z()))
+}
+
+/// Fit of the reference rendering and output transforms of ACES, from
+/// linear sRGB to linear sRGB
+fn aces(color: Color) -> Color {
+    const INPUT: [[f64; 3]; 3] = [
+        [0.59719, 0.35458, 0.04823],
+        [0.07600, 0.90834, 0.01566],
+        [0.02840, 0.13383, 0.83777],
+    ];
+    const OUTPUT: [[f64; 3]; 3] = [
+        [1.60475, -0.53108, -0.07367],
+        [-0.10208, 1.10813, -0.00605],
+        [-0.00327, -0.07276, 1.07602],
+    ];
+
+    let fitted = map(transform(&INPUT, color), |v| {
+        (v * (v + 0.0245786) - 0.000090537) / (v * (0.983729 * v + 0.4329510) + 0.238081)
+    });
+
+    transform(&OUTPUT, fitted)
+}
+
+/// Polynomial approximation of the AgX base contrast curve, from linear
+/// sRGB to linear sRGB
+fn agx(color: Color) -> Color {
+    const INSET: [[f64; 3]; 3] = [
+        [0.842479062253094, 0.0784335999999992, 0.0792237451477643],
+        [0.0423282422610123, 0.878468636469772, 0.0791661274605434],
+        [0.0423756549057051, 0.0784336, 0.879142973793104],
+    ];
+    const OUTSET: [[f64; 3]; 3] = [
+        [1.19687900512017, -0.0980208811401368, -0.0990297440797205],
+        [-0.0528968517574562, 1.15190312990417, -0.0989611768448433],
+        [-0.0529716355144438, -0.0980434501171241, 1.15107367264116],
+    ];
+    // Range of the curve, in stops around middle gray
+    const MIN_EV: f64 = -12.47393;
+    const MAX_EV: f64 = 4.026069;
+
+    let curve = |v: f64| {
+        let x = (v.log2().clamp(MIN_EV, MAX_EV) - MIN_EV) / (MAX_EV - MIN_EV);
+        let x2 = x * x;
+        let x4 = x2 * x2;
+        let y = 15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x
+            + 0.4298 * x2
+            + 0.1191 * x
+            - 0.00232;
+
+        // The curve gives display values with a gamma of 2.2
+        y.clamp(0.0, 1.0).powf(2.2)
+    };
+
+    transform(&OUTSET, map(transform(&INSET, color), curve))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tone_mapping(tone_mapper: ToneMapper) -> ToneMapping {
+        ToneMapping {
+            exposure: 0.0,
+            tone_mapper,
+            white_point: 4.0,
+        }
+    }
+
+    #[test]
+    fn linear_to_srgb_works() {
+        assert_eq!(0.0, linear_to_srgb(0.0));
+        assert!((linear_to_srgb(1.0) - 1.0).abs() < 1e-12);
+        assert!((linear_to_srgb(0.5) - 0.735357).abs() < 1e-6);
+
+        // Continuous where the linear segment ends
+        let knee = 0.0031308;
+        assert!((linear_to_srgb(knee) - linear_to_srgb(knee + 1e-9)).abs() < 1e-6);
+
+        let srgb = crate::texture::image_texture::srgb_to_linear(linear_to_srgb(0.2));
+        assert!((srgb - 0.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn encode_works() {
+        let black = Color::new(0.0, 0.0, 0.0);
+        let gray = Color::new(0.18, 0.18, 0.18);
+        let bright = Color::new(50.0, 20.0, 10.0);
+
+        for tone_mapper in [
+            ToneMapper::Clamp,
+            ToneMapper::Reinhard,
+            ToneMapper::ExtendedReinhard,
+            ToneMapper::Aces,
+            ToneMapper::Agx,
+        ] {
+            let tone_mapping = tone_mapping(tone_mapper);
+            assert!(tone_mapping.encode(black).length() < 0.05);
+
+            let (gray, bright) = (tone_mapping.encode(gray), tone_mapping.encode(bright));
+            assert!(gray.x() > 0.2 && gray.x() < 0.6);
+            assert!(bright.x() <= 1.0 && bright.x() > gray.x());
+        }
+
+        // Highlights keep their hue, unlike with clamping
+        let reinhard = tone_mapping(ToneMapper::Reinhard).encode(bright);
+        assert!(reinhard.x() > reinhard.y() && reinhard.y() > reinhard.z());
+
+        // The white point becomes white
+        let white = tone_mapping(ToneMapper::ExtendedReinhard).encode(Color::new(4.0, 4.0, 4.0));
+        assert!((white.x() - 1.0).abs() < 1e-9);
+
+        // One stop more doubles linear values
+        let exposed = ToneMapping {
+            exposure: 1.0,
+            ..tone_mapping(ToneMapper::Clamp)
+        };
+        assert_eq!(
+            tone_mapping(ToneMapper::Clamp).encode(Color::new(0.2, 0.2, 0.2)),
+            exposed.encode(Color::new(0.1, 0.1, 0.1))
+        );
+    }
+}
@@ -0,0 +1,299 @@
+use std::ops::Mul;
+
+use crate::vec3::{Point3, Vec3};
+
+/// An affine transformation of the space, as a 4x4 matrix acting on column
+/// vectors
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    m: [[f64; 4]; 4],
+}
+
+impl Transform {
+    /// Constructs a transformation from the rows of its matrix
+    pub fn new(m: [[f64; 4]; 4]) -> Transform {
+        Transform { m }
+    }
+
+    /// Returns the transformation which does nothing
+    pub fn identity() -> Transform {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            row[i] = 1.0;
+        }
+        Transform { m }
+    }
+
+    /// Returns the transformation moving points by `offset`
+    pub fn translate(offset: Vec3) -> Transform {
+        let mut transform = Transform::identity();
+        transform.m[0][3] = offset.x();
+        transform.m[1][3] = offset.y();
+        transform.m[2][3] = offset.z();
+        transform
+    }
+
+    /// Returns the transformation scaling each axis by the matching
+    /// component of `factors`
+    pub fn scale(factors: Vec3) -> Transform {
+        let mut transform = Transform::identity();
+        transform.m[0][0] = factors.x();
+        transform.m[1][1] = factors.y();
+        transform.m[2][2] = factors.z();
+        transform
+    }
+
+    /// Returns the rotation of `degrees` around `axis`, counter-clockwise
+    /// when the axis points towards the viewer
+    pub fn rotate(degrees: f64, axis: Vec3) -> Transform {
+        let a = axis.normalized();
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let (x, y, z) = (a.x(), a.y(), a.z());
+
+        Transform::new([
+            [
+                cos + x * x * (1.0 - cos),
+                x * y * (1.0 - cos) - z * sin,
+                x * z * (1.0 - cos) + y * sin,
+                0.0,
+            ],
+            [
+                y * x * (1.0 - cos) + z * sin,
+                cos + y * y * (1.0 - cos),
+                y * z * (1.0 - cos) - x * sin,
+                0.0,
+            ],
+            [
+                z * x * (1.0 - cos) - y * sin,
+                z * y * (1.0 - cos) + x * sin,
+                cos + z * z * (1.0 - cos),
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Returns the transformation from the world to the space of a camera at
+    /// `eye` looking at `look`, where the camera looks along +Z with +Y up
+    /// and +X on the right of the image (left-handed, as in pbrt)
+    pub fn look_at(eye: Point3, look: Point3, up: Vec3) -> Transform {
+        let direction = (look - eye).normalized();
+        let right = Vec3::cross(&up.normalized(), &direction).normalized();
+        let up = Vec3::cross(&direction, &right);
+
+        let camera_to_world = Transform::new([
+            [right.x(), up.x(), direction.x(), eye.x()],
+            [right.y(), up.y(), direction.y(), eye.y()],
+            [right.z(), up.z(), direction.z(), eye.z()],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+
+        camera_to_world
+            .inverse()
+            .unwrap_or_else(Transform::identity)
+    }
+
+    /// Returns the reflection across the plane through `origin` and
+    /// perpendicular to `normal`
+    pub fn mirror(origin: Point3, normal: Vec3) -> Transform {
+        let n = normal.normalized();
+        let offset = 2.0 * Vec3::dot(&origin, &n);
+        let row = |i: usize, a: f64| {
+            [
+                -2.0 * a * n.x() + if i == 0 { 1.0 } else { 0.0 },
+                -2.0 * a * n.y() + if i == 1 { 1.0 } else { 0.0 },
+                -2.0 * a * n.z() + if i == 2 { 1.0 } else { 0.0 },
+                offset * a,
+            ]
+        };
+
+        Transform::new([
+            row(0, n.x()),
+            row(1, n.y()),
+            row(2, n.z()),
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Returns the inverse transformation, or `None` if the matrix is
+    /// singular
+    pub fn inverse(&self) -> Option<Transform> {
+        // Gauss-Jordan elimination with partial pivoting
+        let mut a = self.m;
+        let mut inverse = Transform::identity().m;
+
+        for column in 0..4 {
+            let pivot = (column..4)
+                .max_by(|&i, &j| a[i][column].abs().total_cmp(&a[j][column].abs()))
+                .unwrap();
+            if a[pivot][column].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(column, pivot);
+            inverse.swap(column, pivot);
+
+            let factor = 1.0 / a[column][column];
+            for k in 0..4 {
+                a[column][k] *= factor;
+                inverse[column][k] *= factor;
+            }
+
+            for row in 0..4 {
+                if row != column {
+                    let factor = a[row][column];
+                    for k in 0..4 {
+                        a[row][k] -= factor * a[column][k];
+                        inverse[row][k] -= factor * inverse[column][k];
+                    }
+                }
+            }
+        }
+
+        Some(Transform::new(inverse))
+    }
+
+    /// Returns the transformation of surface normals (the inverse
+    /// transpose), so that they stay perpendicular to transformed surfaces.
+    /// Transformed normals are not normalized.
+    pub fn normal_transform(&self) -> Transform {
+        self.inverse().unwrap_or(*self).transposed()
+    }
+
+    /// Returns whether the transformation mirrors the space, turning the
+    /// triangles of meshes inside out
+    pub fn flips_orientation(&self) -> bool {
+        let x = self.vector(Vec3::new(1.0, 0.0, 0.0));
+        let y = self.vector(Vec3::new(0.0, 1.0, 0.0));
+        let z = self.vector(Vec3::new(0.0, 0.0, 1.0));
+
+        Vec3::dot(&Vec3::cross(&x, &y), &z) < 0.0
+    }
+
+    /// Returns the transposed matrix
+    fn transposed(&self) -> Transform {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.m[j][i];
+            }
+        }
+        Transform { m }
+    }
+
+    /// Transforms a point
+    pub fn point(&self, p: Point3) -> Point3 {
+        let m = &self.m;
+        let x = m[0][0] * p.x() + m[0][1] * p.y() + m[0][2] * p.z() + m[0][3];
+        let y = m[1][0] * p.x() + m[1][1] * p.y() + m[1][2] * p.z() + m[1][3];
+        let z = m[2][0] * p.x() + m[2][1] * p.y() + m[2][2] * p.z() + m[2][3];
+        let w = m[3][0] * p.x() + m[3][1] * p.y() + m[3][2] * p.z() + m[3][3];
+
+        if w == 1.0 {
+            Point3::new(x, y, z)
+        } else {
+            Point3::new(x, y, z) / w
+        }
+    }
+
+    /// Transforms a direction, ignoring the translation
+    pub fn vector(&self, v: Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * v.x() + m[0][1] * v.y() + m[0][2] * v.z(),
+            m[1][0] * v.x() + m[1][1] * v.y() + m[1][2] * v.z(),
+            m[2][0] * v.x() + m[2][1] * v.y() + m[2][2] * v.z(),
+        )
+    }
+}
+
+impl Mul for Transform {
+    type Output = Transform;
+
+    /// Composes two transformations: `a * b` applies `b` first, then `a`
+    fn mul(self, rhs: Transform) -> Self::Output {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[i][k] * rhs.m[k][j]).sum();
+            }
+        }
+        Transform { m }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_near(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-9, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn compose_works() {
+        let transform = Transform::translate(Vec3::new(1.0, 2.0, 3.0))
+            * Transform::rotate(90.0, Vec3::new(0.0, 0.0, 1.0))
+            * Transform::scale(Vec3::new(2.0, 2.0, 2.0));
+
+        assert_near(
+            Point3::new(1.0, 4.0, 3.0),
+            transform.point(Point3::new(1.0, 0.0, 0.0)),
+        );
+        assert_near(
+            Vec3::new(-2.0, 0.0, 0.0),
+            transform.vector(Vec3::new(0.0, 1.0, 0.0)),
+        );
+    }
+
+    #[test]
+    fn inverse_works() {
+        let transform = Transform::translate(Vec3::new(1.0, -2.0, 0.5))
+            * Transform::rotate(30.0, Vec3::new(1.0, 1.0, 0.0))
+            * Transform::scale(Vec3::new(1.0, 3.0, 0.5));
+        let p = Point3::new(0.3, -0.7, 2.0);
+
+        assert_near(p, transform.inverse().unwrap().point(transform.point(p)));
+        assert!(Transform::scale(Vec3::new(1.0, 0.0, 1.0))
+            .inverse()
+            .is_none());
+    }
+
+    #[test]
+    fn look_at_works() {
+        let transform = Transform::look_at(
+            Point3::new(0.0, 0.0, -5.0),
+            Point3::zero(),
+            Vec3::new(0.0, 1.0, 0.0),
+        );
+
+        assert_near(Point3::new(0.0, 0.0, 5.0), transform.point(Point3::zero()));
+        assert_near(
+            Vec3::new(1.0, 0.0, 0.0),
+            transform.vector(Vec3::new(1.0, 0.0, 0.0)),
+        );
+    }
+
+    #[test]
+    fn mirror_works() {
+        let transform = Transform::mirror(Point3::new(1.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0));
+
+        assert_near(
+            Point3::new(-1.0, 5.0, 2.0),
+            transform.point(Point3::new(3.0, 5.0, 2.0)),
+        );
+        assert!(transform.flips_orientation());
+        assert!(!(transform * transform).flips_orientation());
+    }
+
+    #[test]
+    fn normal_works() {
+        // Squashing a slanted plane makes it steeper
+        let transform = Transform::scale(Vec3::new(1.0, 0.5, 1.0));
+        let n = transform
+            .normal_transform()
+            .vector(Vec3::new(1.0, 1.0, 0.0))
+            .normalized();
+
+        assert_near(Vec3::new(1.0, 2.0, 0.0).normalized(), n);
+    }
+}
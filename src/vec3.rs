@@ -1,4 +1,5 @@
 use crate::random::*;
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, PI};
 use std::fmt::{Display, Formatter};
 use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
 
@@ -46,27 +47,37 @@ impl Vec3 {
         }
     }
 
-    /// Returns a random [`Vec3`] in the unit sphere (`-1.0..1.0`)
-    pub fn random_in_unit_sphere() -> Vec3 {
-        let mut p = Vec3::random_range(-1.0, 1.0);
-        while p.length_squared() >= 1.0 {
-            p = Vec3::random_range(-1.0, 1.0);
-        }
-        p
+    /// Returns a [`Vec3`] in the unit sphere, uniformly distributed, from
+    /// a sample of the unit square for its direction and a sample for its
+    /// distance to the origin
+    pub fn sample_in_unit_sphere(direction: (f64, f64), distance: f64) -> Vec3 {
+        distance.cbrt() * Vec3::sample_unit_vector(direction)
     }
 
-    /// Returns a random [`Vec3`] with `x` and `y` pointing to an unit disk
-    pub fn random_in_unit_disk() -> Vec3 {
-        let mut p = Vec3::new(random_range(-1.0, 1.0), random_range(-1.0, 1.0), 0.0);
-        while p.length_squared() >= 1.0 {
-            p = Vec3::new(random_range(-1.0, 1.0), random_range(-1.0, 1.0), 0.0);
+    /// Returns a [`Vec3`] with `x` and `y` pointing to an unit disk, uniformly
+    /// distributed, from a sample of the unit square (with the concentric
+    /// mapping of Shirley and Chiu, which keeps strata compact)
+    pub fn sample_in_unit_disk((u, v): (f64, f64)) -> Vec3 {
+        let (a, b) = (2.0 * u - 1.0, 2.0 * v - 1.0);
+        if a == 0.0 && b == 0.0 {
+            return Vec3::zero();
         }
-        p
-    }
 
-    /// Returns a random unit vector
-    pub fn random_unit_vector() -> Vec3 {
-        Vec3::random_in_unit_sphere().normalized()
+        let (radius, theta) = if a.abs() > b.abs() {
+            (a, FRAC_PI_4 * (b / a))
+        } else {
+            (b, FRAC_PI_2 - FRAC_PI_4 * (a / b))
+        };
+        Vec3::new(radius * theta.cos(), radius * theta.sin(), 0.0)
+    }
+
+    /// Returns a unit vector, uniformly distributed, from a sample of the
+    /// unit square
+    pub fn sample_unit_vector((u, v): (f64, f64)) -> Vec3 {
+        let z = 1.0 - 2.0 * u;
+        let radius = (1.0 - z * z).max(0.0).sqrt();
+        let phi = 2.0 * PI * v;
+        Vec3::new(radius * phi.cos(), radius * phi.sin(), z)
     }
 
     pub fn x(&self) -> f64 {